@@ -1,27 +1,353 @@
+use codespan::Span;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::format;
+
 type Name = String;
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Program {
+    pub items: Vec<Item>,
+}
+
+/// Prints the program in the formatting of `faang fmt`, as source that parses back into the
+/// same program.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&format::format("", self))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Item {
+    Struct(Struct),
+    Enum(Enum),
+    Function(Function),
+    Import(Import),
+    Use(Use),
+    Test(Test),
+    /// A top level statement, which is compiled into the generated `main`.
+    Statement(Statement),
+}
+
+/// An `import "utils.faang";`, which loads another source file as the module `utils`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Import {
+    pub path: String,
+    #[serde(with = "span")]
+    pub span: Span,
+}
+
+/// A `use utils::helper;`, which lets the functions of a file call `utils::helper` as `helper`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Use {
+    pub module: Name,
+    pub name: Name,
+    #[serde(with = "span")]
+    pub span: Span,
+}
+
+/// A `test "name" { ... }` block, which is only compiled by `faang test`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Test {
+    pub name: String,
+    pub body: Vec<Statement>,
+    #[serde(with = "span")]
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Struct {
+    pub name: Name,
+    pub fields: Vec<(Name, Type)>,
+    #[serde(with = "span")]
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Enum {
+    pub name: Name,
+    pub variants: Vec<Variant>,
+    #[serde(with = "span")]
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Variant {
+    pub name: Name,
+    pub fields: Vec<Type>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Function {
+    pub name: Name,
+    pub type_parameters: Vec<Name>,
+    pub parameters: Vec<(Name, Type)>,
+    pub return_type: Option<Type>,
+    pub body: Vec<Statement>,
+    /// The doc comment before the function.
+    pub doc: Option<String>,
+    pub attributes: Vec<Attribute>,
+    #[serde(with = "span")]
+    pub span: Span,
+}
+
+/// An attribute before a function, such as `#[allow(unused_variables)]`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Attribute {
+    pub name: Name,
+    pub arguments: Vec<Name>,
+    #[serde(with = "span")]
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Type {
+    Int,
+    Bool,
+    String,
+    /// A file opened with `open`.
+    File,
+    /// A user defined type, such as a struct or an enum.
+    Named(Name),
+    /// The type of functions and lambdas, such as `func(int, int) -> bool`.
+    Function(Vec<Type>, Option<Box<Type>>),
+    /// An array, such as `[int]`.
+    Array(Box<Type>),
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Bool => write!(f, "bool"),
+            Type::String => write!(f, "string"),
+            Type::File => write!(f, "File"),
+            Type::Named(name) => write!(f, "{}", name),
+            Type::Array(element_type) => write!(f, "[{}]", element_type),
+            Type::Function(parameters, return_type) => {
+                let parameters = parameters
+                    .iter()
+                    .map(|parameter| parameter.to_string())
+                    .collect::<Vec<_>>();
+                write!(f, "func({})", parameters.join(", "))?;
+                if let Some(return_type) = return_type {
+                    write!(f, " -> {}", return_type)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Statement {
+    pub kind: StatementKind,
+    #[serde(with = "span")]
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum StatementKind {
+    Let(Name, Option<Type>, Expression),
+    Assignment(Expression, Expression),
+    Expression(Expression),
+    Return(Option<Expression>),
+    If(Expression, Vec<Statement>, Vec<Statement>),
+    While(Expression, Vec<Statement>),
+    /// A statement that failed to parse, whose error is reported by the parser.
+    Error,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Expression {
     Invocation(Name, Vec<Expression>),
+    /// A call of a function value that is not named by a variable, such as `fns.compare(a, b)`.
+    Call(Box<Expression>, Vec<Expression>),
     StringLiteral(String),
+    IntegerLiteral(i64),
+    BooleanLiteral(bool),
+    Null,
+    Variable(Name),
+    FieldAccess(Box<Expression>, Name),
+    StructLiteral(Name, Vec<(Name, Expression)>),
+    /// An enum variant, such as `Option::Some(1)`.
+    Variant(Name, Name, Vec<Expression>),
+    Match(Box<Expression>, Vec<MatchArm>),
+    Lambda(Box<Lambda>),
+    /// An array literal, such as `[1, 2, 3]`.
+    Array(Vec<Expression>),
+    Index(Box<Expression>, Box<Expression>),
+    Unary(UnaryOperator, Box<Expression>),
+    Binary(Box<Expression>, BinaryOperator, Box<Expression>),
+    /// An expression that failed to parse, whose error is reported by the parser.
+    Error,
+}
+
+/// Prints the expression as source that parses back into the same expression.
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&format::format_expression(self))
+    }
+}
+
+/// A lambda expression, such as `|x| x * 2`.
+///
+/// The types of the parameters and the return type may be omitted when they can be inferred.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Lambda {
+    pub parameters: Vec<(Name, Option<Type>)>,
+    pub return_type: Option<Type>,
+    pub body: LambdaBody,
+    #[serde(with = "span")]
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LambdaBody {
+    Expression(Expression),
+    Block(Vec<Statement>),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MatchArm {
+    pub pattern: Pattern,
+    pub body: MatchArmBody,
+    /// The span of the pattern and the body, without the `,` after them.
+    #[serde(with = "span")]
+    pub span: Span,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MatchArmBody {
+    Expression(Expression),
+    Block(Vec<Statement>),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Pattern {
+    Wildcard,
+    Binding(Name),
+    IntegerLiteral(i64),
+    BooleanLiteral(bool),
+    /// An enum variant, such as `Option::Some(x)`.
+    Variant(Name, Name, Vec<Pattern>),
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Wildcard => write!(f, "_"),
+            Pattern::Binding(name) => write!(f, "{}", name),
+            Pattern::IntegerLiteral(value) => write!(f, "{}", value),
+            Pattern::BooleanLiteral(value) => write!(f, "{}", value),
+            Pattern::Variant(enum_name, variant, fields) => {
+                write!(f, "{}::{}", enum_name, variant)?;
+                if !fields.is_empty() {
+                    let fields = fields
+                        .iter()
+                        .map(|field| field.to_string())
+                        .collect::<Vec<_>>();
+                    write!(f, "({})", fields.join(", "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum UnaryOperator {
+    Negate,
+    Not,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Remainder,
+    Equal,
+    NotEqual,
+    LessThan,
+    LessThanOrEqual,
+    GreaterThan,
+    GreaterThanOrEqual,
+    And,
+    Or,
+}
+
+/// Prints the operator as it is written in source, such as `<=`.
+impl fmt::Display for BinaryOperator {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Remainder => "%",
+            BinaryOperator::Equal => "==",
+            BinaryOperator::NotEqual => "!=",
+            BinaryOperator::LessThan => "<",
+            BinaryOperator::LessThanOrEqual => "<=",
+            BinaryOperator::GreaterThan => ">",
+            BinaryOperator::GreaterThanOrEqual => ">=",
+            BinaryOperator::And => "&&",
+            BinaryOperator::Or => "||",
+        })
+    }
 }
 
 #[derive(Debug, PartialEq)]
 pub enum Keyword {
     Func,
+    Struct,
+    Let,
+    Return,
+    If,
+    Else,
+    While,
+    Enum,
+    Match,
+    Import,
+    Use,
+    Test,
+}
+
+/// Spans are serialized as the offsets of their start and end.
+mod span {
+    use codespan::Span;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(span: &Span, serializer: S) -> Result<S::Ok, S::Error> {
+        (span.start().to_usize() as u32, span.end().to_usize() as u32).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Span, D::Error> {
+        let (start, end) = <(u32, u32)>::deserialize(deserializer)?;
+        Ok(Span::new(start, end))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::lexer::{Lexer, LexicalError, Token};
     use crate::parser;
+    use lalrpop_util::ParseError;
     use pretty_assertions::assert_eq;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use proptest::sample::select;
 
     #[test]
     fn parses_func_keyword() {
         let keyword_parser = parser::KeywordParser::new();
         let mut errors = vec![];
-        let keyword = keyword_parser.parse(&mut errors, "func").unwrap();
+        let keyword = keyword_parser.parse(&mut errors, Lexer::new("func")).unwrap();
         assert_eq!(keyword, Keyword::Func);
         assert_eq!(errors.len(), 0);
     }
@@ -31,7 +357,7 @@ mod tests {
         let string_parser = parser::StringLiteralParser::new();
         let mut errors = vec![];
         let string = string_parser
-            .parse(&mut errors, r#""hello world""#)
+            .parse(&mut errors, Lexer::new(r#""hello world""#))
             .unwrap();
         assert_eq!(string, "hello world");
         assert_eq!(errors.len(), 0);
@@ -42,7 +368,7 @@ mod tests {
         let string_parser = parser::StringLiteralParser::new();
         let mut errors = vec![];
         let string = string_parser
-            .parse(&mut errors, r#""hello\"\nworld""#)
+            .parse(&mut errors, Lexer::new(r#""hello\"\nworld""#))
             .unwrap();
         assert_eq!(string, "hello\"\nworld");
         assert_eq!(errors.len(), 0);
@@ -53,7 +379,7 @@ mod tests {
         let expr_parser = parser::ExpressionParser::new();
         let mut errors = vec![];
         let invocation = expr_parser
-            .parse(&mut errors, r#"println("asdf")"#)
+            .parse(&mut errors, Lexer::new(r#"println("asdf")"#))
             .unwrap();
         assert_eq!(
             invocation,
@@ -64,4 +390,882 @@ mod tests {
         );
         assert_eq!(errors.len(), 0);
     }
+
+    #[test]
+    fn parses_struct_declaration() {
+        let program_parser = parser::ProgramParser::new();
+        let mut errors = vec![];
+        let program = program_parser
+            .parse(&mut errors, Lexer::new("struct Point { x: int, y: int }"))
+            .unwrap();
+        assert_eq!(
+            program,
+            Program {
+                items: vec![Item::Struct(Struct {
+                    name: "Point".into(),
+                    fields: vec![("x".into(), Type::Int), ("y".into(), Type::Int)],
+                    span: Span::new(0, 31),
+                })]
+            }
+        );
+        assert_eq!(errors.len(), 0);
+    }
+
+    #[test]
+    fn parses_struct_literal_and_field_access() {
+        let expr_parser = parser::ExpressionParser::new();
+        let mut errors = vec![];
+        let expression = expr_parser
+            .parse(&mut errors, Lexer::new("Node { val: 1, next: null }.val + 2"))
+            .unwrap();
+        assert_eq!(
+            expression,
+            Expression::Binary(
+                Box::new(Expression::FieldAccess(
+                    Box::new(Expression::StructLiteral(
+                        "Node".into(),
+                        vec![
+                            ("val".into(), Expression::IntegerLiteral(1)),
+                            ("next".into(), Expression::Null),
+                        ]
+                    )),
+                    "val".into()
+                )),
+                BinaryOperator::Add,
+                Box::new(Expression::IntegerLiteral(2))
+            )
+        );
+        assert_eq!(errors.len(), 0);
+    }
+
+    #[test]
+    fn parses_field_assignment() {
+        let program_parser = parser::ProgramParser::new();
+        let mut errors = vec![];
+        let program = program_parser
+            .parse(&mut errors, Lexer::new("p.x = p.y * 2"))
+            .unwrap();
+        assert_eq!(
+            program.items,
+            vec![Item::Statement(Statement {
+                kind: StatementKind::Assignment(
+                    Expression::FieldAccess(
+                        Box::new(Expression::Variable("p".into())),
+                        "x".into()
+                    ),
+                    Expression::Binary(
+                        Box::new(Expression::FieldAccess(
+                            Box::new(Expression::Variable("p".into())),
+                            "y".into()
+                        )),
+                        BinaryOperator::Multiply,
+                        Box::new(Expression::IntegerLiteral(2))
+                    )
+                ),
+                span: Span::new(0, 13),
+            })]
+        );
+        assert_eq!(errors.len(), 0);
+    }
+
+    #[test]
+    fn struct_literals_are_not_parsed_in_conditions() {
+        let program_parser = parser::ProgramParser::new();
+        let mut errors = vec![];
+        let program = program_parser
+            .parse(&mut errors, Lexer::new("while node { node = node.next; }"))
+            .unwrap();
+        match &program.items[0] {
+            Item::Statement(Statement {
+                kind: StatementKind::While(condition, body),
+                ..
+            }) => {
+                assert_eq!(condition, &Expression::Variable("node".into()));
+                assert_eq!(body.len(), 1);
+            }
+            item => panic!("expected a while statement, found {:?}", item),
+        }
+        assert_eq!(errors.len(), 0);
+    }
+
+    #[test]
+    fn parses_enum_declaration() {
+        let program_parser = parser::ProgramParser::new();
+        let mut errors = vec![];
+        let program = program_parser
+            .parse(&mut errors, Lexer::new("enum Option { Some(int), None }"))
+            .unwrap();
+        assert_eq!(
+            program,
+            Program {
+                items: vec![Item::Enum(Enum {
+                    name: "Option".into(),
+                    variants: vec![
+                        Variant {
+                            name: "Some".into(),
+                            fields: vec![Type::Int],
+                        },
+                        Variant {
+                            name: "None".into(),
+                            fields: vec![],
+                        },
+                    ],
+                    span: Span::new(0, 31),
+                })]
+            }
+        );
+        assert_eq!(errors.len(), 0);
+    }
+
+    #[test]
+    fn parses_match_expression() {
+        let expr_parser = parser::ExpressionParser::new();
+        let mut errors = vec![];
+        let expression = expr_parser
+            .parse(
+                &mut errors,
+                Lexer::new("match opt { Option::Some(-1) => 0, Option::Some(num) => num, _ => { return 1; } }"),
+            )
+            .unwrap();
+        assert_eq!(
+            expression,
+            Expression::Match(
+                Box::new(Expression::Variable("opt".into())),
+                vec![
+                    MatchArm {
+                        pattern: Pattern::Variant(
+                            "Option".into(),
+                            "Some".into(),
+                            vec![Pattern::IntegerLiteral(-1)]
+                        ),
+                        body: MatchArmBody::Expression(Expression::IntegerLiteral(0)),
+                        span: Span::new(12, 33),
+                    },
+                    MatchArm {
+                        pattern: Pattern::Variant(
+                            "Option".into(),
+                            "Some".into(),
+                            vec![Pattern::Binding("num".into())]
+                        ),
+                        body: MatchArmBody::Expression(Expression::Variable("num".into())),
+                        span: Span::new(35, 59),
+                    },
+                    MatchArm {
+                        pattern: Pattern::Wildcard,
+                        body: MatchArmBody::Block(vec![Statement {
+                            kind: StatementKind::Return(Some(Expression::IntegerLiteral(1))),
+                            span: Span::new(68, 76),
+                        }]),
+                        span: Span::new(61, 79),
+                    },
+                ]
+            )
+        );
+        assert_eq!(errors.len(), 0);
+    }
+
+    #[test]
+    fn parses_negative_integer_patterns() {
+        let expr_parser = parser::ExpressionParser::new();
+        let mut errors = vec![];
+        let expression = expr_parser
+            .parse(
+                &mut errors,
+                Lexer::new("match num { -9223372036854775808 => 0, -9223372036854775809 => 1 }"),
+            )
+            .unwrap();
+        match expression {
+            Expression::Match(_, arms) => assert_eq!(
+                arms.into_iter().map(|arm| arm.pattern).collect::<Vec<_>>(),
+                vec![
+                    Pattern::IntegerLiteral(i64::MIN),
+                    Pattern::IntegerLiteral(0)
+                ]
+            ),
+            expression => panic!("expected a match, found {:?}", expression),
+        }
+        assert_eq!(
+            errors,
+            vec![ParseError::User {
+                error: LexicalError::IntegerTooLarge { start: 39, end: 59 }
+            }]
+        );
+    }
+
+    #[test]
+    fn match_statements_do_not_need_semicolons() {
+        let program_parser = parser::ProgramParser::new();
+        let mut errors = vec![];
+        let program = program_parser
+            .parse(
+                &mut errors,
+                Lexer::new("match flag { true => println(\"yes\"), false => {} } println(\"done\")"),
+            )
+            .unwrap();
+        assert_eq!(program.items.len(), 2);
+        match &program.items[0] {
+            Item::Statement(Statement {
+                kind: StatementKind::Expression(Expression::Match(_, arms)),
+                ..
+            }) => assert_eq!(arms.len(), 2),
+            item => panic!("expected a match statement, found {:?}", item),
+        }
+        assert_eq!(errors.len(), 0);
+    }
+
+    #[test]
+    fn parses_lambda_with_function_type() {
+        let program_parser = parser::ProgramParser::new();
+        let mut errors = vec![];
+        let program = program_parser
+            .parse(&mut errors, Lexer::new("let f: func(int) -> bool = |num| num > 0"))
+            .unwrap();
+        assert_eq!(
+            program.items,
+            vec![Item::Statement(Statement {
+                kind: StatementKind::Let(
+                    "f".into(),
+                    Some(Type::Function(vec![Type::Int], Some(Box::new(Type::Bool)))),
+                    Expression::Lambda(Box::new(Lambda {
+                        parameters: vec![("num".into(), None)],
+                        return_type: None,
+                        body: LambdaBody::Expression(Expression::Binary(
+                            Box::new(Expression::Variable("num".into())),
+                            BinaryOperator::GreaterThan,
+                            Box::new(Expression::IntegerLiteral(0))
+                        )),
+                        span: Span::new(27, 40),
+                    }))
+                ),
+                span: Span::new(0, 40),
+            })]
+        );
+        assert_eq!(errors.len(), 0);
+    }
+
+    #[test]
+    fn parses_test_blocks() {
+        let program_parser = parser::ProgramParser::new();
+        let mut errors = vec![];
+        let program = program_parser
+            .parse(&mut errors, Lexer::new("test \"adds\" { assert(true, \"ok\"); }"))
+            .unwrap();
+        assert_eq!(
+            program.items,
+            vec![Item::Test(Test {
+                name: "adds".into(),
+                body: vec![Statement {
+                    kind: StatementKind::Expression(Expression::Invocation(
+                        "assert".into(),
+                        vec![
+                            Expression::BooleanLiteral(true),
+                            Expression::StringLiteral("ok".into())
+                        ]
+                    )),
+                    span: Span::new(14, 32),
+                }],
+                span: Span::new(0, 35),
+            })]
+        );
+        assert_eq!(errors.len(), 0);
+    }
+
+    #[test]
+    fn parses_arrays() {
+        let program_parser = parser::ProgramParser::new();
+        let mut errors = vec![];
+        let program = program_parser
+            .parse(&mut errors, Lexer::new("let grid: [[int]] = [[1], [2, 3]]"))
+            .unwrap();
+        assert_eq!(
+            program.items,
+            vec![Item::Statement(Statement {
+                kind: StatementKind::Let(
+                    "grid".into(),
+                    Some(Type::Array(Box::new(Type::Array(Box::new(Type::Int))))),
+                    Expression::Array(vec![
+                        Expression::Array(vec![Expression::IntegerLiteral(1)]),
+                        Expression::Array(vec![
+                            Expression::IntegerLiteral(2),
+                            Expression::IntegerLiteral(3)
+                        ]),
+                    ])
+                ),
+                span: Span::new(0, 33),
+            })]
+        );
+
+        let expr_parser = parser::ExpressionParser::new();
+        let expression = expr_parser.parse(&mut errors, Lexer::new("grid[1][0]")).unwrap();
+        assert_eq!(
+            expression,
+            Expression::Index(
+                Box::new(Expression::Index(
+                    Box::new(Expression::Variable("grid".into())),
+                    Box::new(Expression::IntegerLiteral(1))
+                )),
+                Box::new(Expression::IntegerLiteral(0))
+            )
+        );
+        assert_eq!(errors.len(), 0);
+    }
+
+    #[test]
+    fn parses_single_character_identifiers() {
+        let expr_parser = parser::ExpressionParser::new();
+        let mut errors = vec![];
+        let expression = expr_parser.parse(&mut errors, Lexer::new("a[i]")).unwrap();
+        assert_eq!(
+            expression,
+            Expression::Index(
+                Box::new(Expression::Variable("a".into())),
+                Box::new(Expression::Variable("i".into()))
+            )
+        );
+        assert_eq!(errors.len(), 0);
+    }
+
+    #[test]
+    fn parses_calls_of_function_values() {
+        let expr_parser = parser::ExpressionParser::new();
+        let mut errors = vec![];
+        let expression = expr_parser
+            .parse(
+                &mut errors,
+                Lexer::new("ops.apply(Option::Some(1))(|| -> int { return 2; })"),
+            )
+            .unwrap();
+        match expression {
+            Expression::Call(callee, args) => {
+                assert_eq!(
+                    *callee,
+                    Expression::Call(
+                        Box::new(Expression::FieldAccess(
+                            Box::new(Expression::Variable("ops".into())),
+                            "apply".into()
+                        )),
+                        vec![Expression::Variant(
+                            "Option".into(),
+                            "Some".into(),
+                            vec![Expression::IntegerLiteral(1)]
+                        )]
+                    )
+                );
+                match &args[..] {
+                    [Expression::Lambda(lambda)] => {
+                        assert_eq!(lambda.parameters, vec![]);
+                        assert_eq!(lambda.return_type, Some(Type::Int));
+                    }
+                    args => panic!("expected a lambda, found {:?}", args),
+                }
+            }
+            expression => panic!("expected a call, found {:?}", expression),
+        }
+        assert_eq!(errors.len(), 0);
+    }
+
+    #[test]
+    fn parses_generic_function_declaration() {
+        let program_parser = parser::ProgramParser::new();
+        let mut errors = vec![];
+        let program = program_parser
+            .parse(&mut errors, Lexer::new("func swap<TT, UU>(pair: Pair) {}"))
+            .unwrap();
+        assert_eq!(
+            program.items,
+            vec![Item::Function(Function {
+                name: "swap".into(),
+                type_parameters: vec!["TT".into(), "UU".into()],
+                parameters: vec![("pair".into(), Type::Named("Pair".into()))],
+                return_type: None,
+                body: vec![],
+                doc: None,
+                attributes: vec![],
+                span: Span::new(0, 32),
+            })]
+        );
+        assert_eq!(errors.len(), 0);
+    }
+
+    #[test]
+    fn parses_imports() {
+        let program_parser = parser::ProgramParser::new();
+        let mut errors = vec![];
+        let program = program_parser
+            .parse(&mut errors, Lexer::new("import \"lib/utils.faang\"; use utils::helper;"))
+            .unwrap();
+        assert_eq!(
+            program.items,
+            vec![
+                Item::Import(Import {
+                    path: "lib/utils.faang".into(),
+                    span: Span::new(0, 25),
+                }),
+                Item::Use(Use {
+                    module: "utils".into(),
+                    name: "helper".into(),
+                    span: Span::new(26, 44),
+                }),
+            ]
+        );
+        assert_eq!(errors.len(), 0);
+    }
+
+    #[test]
+    fn recovers_from_syntax_errors() {
+        let program_parser = parser::ProgramParser::new();
+        let mut errors = vec![];
+        let program = program_parser
+            .parse(
+                &mut errors,
+                Lexer::new("func check() {\n    let = 3;\n    println(1 +, \"ok\");\n}\nlet x = 1"),
+            )
+            .unwrap();
+        assert_eq!(
+            program.items,
+            vec![
+                Item::Function(Function {
+                    name: "check".into(),
+                    type_parameters: vec![],
+                    parameters: vec![],
+                    return_type: None,
+                    body: vec![
+                        Statement {
+                            kind: StatementKind::Error,
+                            span: Span::new(19, 26),
+                        },
+                        Statement {
+                            kind: StatementKind::Expression(Expression::Invocation(
+                                "println".into(),
+                                vec![Expression::Error, Expression::StringLiteral("ok".into())]
+                            )),
+                            span: Span::new(32, 50),
+                        },
+                    ],
+                    doc: None,
+                    attributes: vec![],
+                    span: Span::new(0, 53),
+                }),
+                Item::Statement(Statement {
+                    kind: StatementKind::Let("x".into(), None, Expression::IntegerLiteral(1)),
+                    span: Span::new(54, 63),
+                }),
+            ]
+        );
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn parses_comments() {
+        let program_parser = parser::ProgramParser::new();
+        let mut errors = vec![];
+        let program = program_parser
+            .parse(
+                &mut errors,
+                Lexer::new("// Tools.\n/// Does nothing.\n///\n///   Really.\nfunc id(/* x */) {}"),
+            )
+            .unwrap();
+        assert_eq!(
+            program.items,
+            vec![Item::Function(Function {
+                name: "id".into(),
+                type_parameters: vec![],
+                parameters: vec![],
+                return_type: None,
+                body: vec![],
+                doc: Some("Does nothing.\n\n  Really.".into()),
+                attributes: vec![],
+                span: Span::new(46, 65),
+            })]
+        );
+        assert_eq!(errors.len(), 0);
+    }
+
+    #[test]
+    fn parses_attributes() {
+        let program_parser = parser::ProgramParser::new();
+        let mut errors = vec![];
+        let program = program_parser
+            .parse(
+                &mut errors,
+                Lexer::new(
+                    "/// Unused.\n#[allow(unused_variables, shadowing)]\n#[deny()]\nfunc id() {}",
+                ),
+            )
+            .unwrap();
+        match &program.items[..] {
+            [Item::Function(function)] => {
+                assert_eq!(function.doc, Some("Unused.".into()));
+                assert_eq!(
+                    function.attributes,
+                    vec![
+                        Attribute {
+                            name: "allow".into(),
+                            arguments: vec!["unused_variables".into(), "shadowing".into()],
+                            span: Span::new(12, 49),
+                        },
+                        Attribute {
+                            name: "deny".into(),
+                            arguments: vec![],
+                            span: Span::new(50, 59),
+                        },
+                    ]
+                );
+                assert_eq!(function.span, Span::new(60, 72));
+            }
+            items => panic!("expected a function, found {:?}", items),
+        }
+        assert_eq!(errors.len(), 0);
+    }
+
+    fn name() -> impl Strategy<Value = Name> {
+        "[a-zA-Z][a-zA-Z0-9_]{0,4}".prop_filter("keywords are not names", |name| {
+            matches!(
+                Lexer::new(name).next(),
+                Some(Ok((_, Token::Identifier(_), _)))
+            )
+        })
+    }
+
+    fn ty() -> impl Strategy<Value = Type> {
+        let leaf = prop_oneof![
+            Just(Type::Int),
+            Just(Type::Bool),
+            Just(Type::String),
+            Just(Type::File),
+            name().prop_map(Type::Named),
+        ];
+        leaf.prop_recursive(2, 8, 2, |inner| {
+            prop_oneof![
+                inner.clone().prop_map(|ty| Type::Array(Box::new(ty))),
+                (vec(inner.clone(), 0..3), proptest::option::of(inner)).prop_map(
+                    |(parameters, return_type)| {
+                        Type::Function(parameters, return_type.map(Box::new))
+                    }
+                ),
+            ]
+        })
+    }
+
+    fn pattern() -> impl Strategy<Value = Pattern> {
+        let leaf = prop_oneof![
+            Just(Pattern::Wildcard),
+            name().prop_map(Pattern::Binding),
+            any::<i64>().prop_map(Pattern::IntegerLiteral),
+            any::<bool>().prop_map(Pattern::BooleanLiteral),
+        ];
+        leaf.prop_recursive(2, 8, 2, |inner| {
+            (name(), name(), vec(inner, 0..3)).prop_map(|(enum_name, variant, fields)| {
+                Pattern::Variant(enum_name, variant, fields)
+            })
+        })
+    }
+
+    /// Expressions in the form the parser produces them, so that printing and parsing them
+    /// gives them back. Integer literals are not negative, since `-1` is parsed as a negation
+    /// of `1`, and a variable or a variant without fields is never called, since calling them
+    /// is parsed as an invocation or as a variant with fields.
+    fn expression() -> impl Strategy<Value = Expression> {
+        let leaf = prop_oneof![
+            any::<String>().prop_map(Expression::StringLiteral),
+            (0..=i64::MAX).prop_map(Expression::IntegerLiteral),
+            any::<bool>().prop_map(Expression::BooleanLiteral),
+            Just(Expression::Null),
+            name().prop_map(Expression::Variable),
+        ];
+        leaf.prop_recursive(4, 32, 3, |inner| {
+            let unary_operators = vec![UnaryOperator::Negate, UnaryOperator::Not];
+            let binary_operators = vec![
+                BinaryOperator::Add,
+                BinaryOperator::Subtract,
+                BinaryOperator::Multiply,
+                BinaryOperator::Divide,
+                BinaryOperator::Remainder,
+                BinaryOperator::Equal,
+                BinaryOperator::NotEqual,
+                BinaryOperator::LessThan,
+                BinaryOperator::LessThanOrEqual,
+                BinaryOperator::GreaterThan,
+                BinaryOperator::GreaterThanOrEqual,
+                BinaryOperator::And,
+                BinaryOperator::Or,
+            ];
+            let callee = inner
+                .clone()
+                .prop_filter("named calls", |callee| match callee {
+                    Expression::Variable(_) => false,
+                    Expression::Variant(_, _, args) => !args.is_empty(),
+                    _ => true,
+                });
+            let parameter = (name(), proptest::option::of(ty()));
+            prop_oneof![
+                (name(), vec(inner.clone(), 0..3))
+                    .prop_map(|(name, args)| Expression::Invocation(name, args)),
+                (callee, vec(inner.clone(), 0..3))
+                    .prop_map(|(callee, args)| Expression::Call(Box::new(callee), args)),
+                (inner.clone(), name())
+                    .prop_map(|(object, field)| Expression::FieldAccess(Box::new(object), field)),
+                (name(), vec((name(), inner.clone()), 0..3))
+                    .prop_map(|(name, fields)| Expression::StructLiteral(name, fields)),
+                (name(), name(), vec(inner.clone(), 0..3))
+                    .prop_map(|(name, variant, args)| Expression::Variant(name, variant, args)),
+                (inner.clone(), vec((pattern(), inner.clone()), 0..3)).prop_map(
+                    |(scrutinee, arms)| {
+                        let arms = arms
+                            .into_iter()
+                            .map(|(pattern, body)| MatchArm {
+                                pattern,
+                                body: MatchArmBody::Expression(body),
+                                span: Span::initial(),
+                            })
+                            .collect();
+                        Expression::Match(Box::new(scrutinee), arms)
+                    }
+                ),
+                (vec(parameter, 0..3), inner.clone()).prop_map(|(parameters, body)| {
+                    Expression::Lambda(Box::new(Lambda {
+                        parameters,
+                        return_type: None,
+                        body: LambdaBody::Expression(body),
+                        span: Span::initial(),
+                    }))
+                }),
+                vec(inner.clone(), 0..3).prop_map(Expression::Array),
+                (inner.clone(), inner.clone()).prop_map(|(array, index)| {
+                    Expression::Index(Box::new(array), Box::new(index))
+                }),
+                (select(unary_operators), inner.clone())
+                    .prop_map(|(operator, operand)| Expression::Unary(operator, Box::new(operand))),
+                (inner.clone(), select(binary_operators), inner).prop_map(
+                    |(lhs, operator, rhs)| {
+                        Expression::Binary(Box::new(lhs), operator, Box::new(rhs))
+                    }
+                ),
+            ]
+        })
+    }
+
+    /// Statements without spans, since spans are not printed. Their expressions hold no
+    /// blocks.
+    fn statement() -> impl Strategy<Value = Statement> {
+        let leaf = prop_oneof![
+            (name(), proptest::option::of(ty()), expression())
+                .prop_map(|(name, ty, value)| StatementKind::Let(name, ty, value)),
+            (expression(), expression())
+                .prop_map(|(place, value)| StatementKind::Assignment(place, value)),
+            expression().prop_map(StatementKind::Expression),
+            proptest::option::of(expression()).prop_map(StatementKind::Return),
+        ];
+        let kind = leaf.prop_recursive(3, 16, 3, |inner| {
+            let block = vec(
+                inner.prop_map(|kind| Statement {
+                    kind,
+                    span: Span::initial(),
+                }),
+                0..3,
+            );
+            prop_oneof![
+                (expression(), block.clone(), block.clone()).prop_map(
+                    |(condition, then, otherwise)| StatementKind::If(condition, then, otherwise)
+                ),
+                (expression(), block)
+                    .prop_map(|(condition, body)| StatementKind::While(condition, body)),
+            ]
+        });
+        kind.prop_map(|kind| Statement {
+            kind,
+            span: Span::initial(),
+        })
+    }
+
+    fn function() -> impl Strategy<Value = Function> {
+        // Half of the lines start with a space, which the parser keeps after stripping one.
+        let doc = vec(" ?[ -~]{0,8}", 1..3).prop_map(|lines| lines.join("\n"));
+        let attribute = (name(), vec(name(), 0..3)).prop_map(|(name, arguments)| Attribute {
+            name,
+            arguments,
+            span: Span::initial(),
+        });
+        (
+            name(),
+            vec(name(), 0..3),
+            vec((name(), ty()), 0..3),
+            proptest::option::of(ty()),
+            vec(statement(), 0..3),
+            proptest::option::of(doc),
+            vec(attribute, 0..2),
+        )
+            .prop_map(
+                |(name, type_parameters, parameters, return_type, body, doc, attributes)| {
+                    Function {
+                        name,
+                        type_parameters,
+                        parameters,
+                        return_type,
+                        body,
+                        doc,
+                        attributes,
+                        span: Span::initial(),
+                    }
+                },
+            )
+    }
+
+    fn item() -> impl Strategy<Value = Item> {
+        let variant = (name(), vec(ty(), 0..3)).prop_map(|(name, fields)| Variant { name, fields });
+        prop_oneof![
+            (name(), vec((name(), ty()), 0..3)).prop_map(|(name, fields)| {
+                Item::Struct(Struct {
+                    name,
+                    fields,
+                    span: Span::initial(),
+                })
+            }),
+            (name(), vec(variant, 0..3)).prop_map(|(name, variants)| {
+                Item::Enum(Enum {
+                    name,
+                    variants,
+                    span: Span::initial(),
+                })
+            }),
+            function().prop_map(Item::Function),
+            any::<String>().prop_map(|path| Item::Import(Import {
+                path,
+                span: Span::initial(),
+            })),
+            (name(), name()).prop_map(|(module, name)| Item::Use(Use {
+                module,
+                name,
+                span: Span::initial(),
+            })),
+            (any::<String>(), vec(statement(), 0..3)).prop_map(|(name, body)| {
+                Item::Test(Test {
+                    name,
+                    body,
+                    span: Span::initial(),
+                })
+            }),
+            statement().prop_map(Item::Statement),
+        ]
+    }
+
+    /// Resets the spans of a parsed program to those of the generated programs. The
+    /// statements inside expressions are left alone, since none are generated.
+    /// Clears the spans in some statements, which are not printed.
+    fn clear_statements(statements: &mut [Statement]) {
+        for statement in statements {
+            statement.span = Span::initial();
+            match &mut statement.kind {
+                StatementKind::Let(_, _, value)
+                | StatementKind::Expression(value)
+                | StatementKind::Return(Some(value)) => clear_expression(value),
+                StatementKind::Assignment(place, value) => {
+                    clear_expression(place);
+                    clear_expression(value);
+                }
+                StatementKind::If(condition, then, otherwise) => {
+                    clear_expression(condition);
+                    clear_statements(then);
+                    clear_statements(otherwise);
+                }
+                StatementKind::While(condition, body) => {
+                    clear_expression(condition);
+                    clear_statements(body);
+                }
+                StatementKind::Return(None) | StatementKind::Error => {}
+            }
+        }
+    }
+
+    fn clear_expression(expression: &mut Expression) {
+        match expression {
+            Expression::Invocation(_, args)
+            | Expression::Variant(_, _, args)
+            | Expression::Array(args) => args.iter_mut().for_each(clear_expression),
+            Expression::Call(callee, args) => {
+                clear_expression(callee);
+                args.iter_mut().for_each(clear_expression);
+            }
+            Expression::FieldAccess(object, _) | Expression::Unary(_, object) => {
+                clear_expression(object)
+            }
+            Expression::StructLiteral(_, fields) => {
+                for (_, value) in fields {
+                    clear_expression(value);
+                }
+            }
+            Expression::Match(scrutinee, arms) => {
+                clear_expression(scrutinee);
+                for arm in arms {
+                    arm.span = Span::initial();
+                    match &mut arm.body {
+                        MatchArmBody::Expression(body) => clear_expression(body),
+                        MatchArmBody::Block(body) => clear_statements(body),
+                    }
+                }
+            }
+            Expression::Lambda(lambda) => {
+                lambda.span = Span::initial();
+                match &mut lambda.body {
+                    LambdaBody::Expression(body) => clear_expression(body),
+                    LambdaBody::Block(body) => clear_statements(body),
+                }
+            }
+            Expression::Index(lhs, rhs) | Expression::Binary(lhs, _, rhs) => {
+                clear_expression(lhs);
+                clear_expression(rhs);
+            }
+            _ => {}
+        }
+    }
+
+    fn without_spans(mut program: Program) -> Program {
+        for item in &mut program.items {
+            match item {
+                Item::Struct(declaration) => declaration.span = Span::initial(),
+                Item::Enum(declaration) => declaration.span = Span::initial(),
+                Item::Function(function) => {
+                    function.span = Span::initial();
+                    for attribute in &mut function.attributes {
+                        attribute.span = Span::initial();
+                    }
+                    clear_statements(&mut function.body);
+                }
+                Item::Import(import) => import.span = Span::initial(),
+                Item::Use(declaration) => declaration.span = Span::initial(),
+                Item::Test(test) => {
+                    test.span = Span::initial();
+                    clear_statements(&mut test.body);
+                }
+                Item::Statement(statement) => clear_statements(std::slice::from_mut(statement)),
+            }
+        }
+        program
+    }
+
+    proptest! {
+        #[test]
+        fn prints_expressions_that_parse_back(expression in expression()) {
+            let source = expression.to_string();
+            let parsed = parser::ExpressionParser::new()
+                .parse(&mut vec![], Lexer::new(&source))
+                .map(|mut parsed| {
+                    clear_expression(&mut parsed);
+                    parsed
+                });
+            prop_assert_eq!(parsed, Ok(expression), "printed as {}", source);
+        }
+
+        #[test]
+        fn prints_programs_that_parse_back(items in vec(item(), 0..4)) {
+            let program = Program { items };
+            let source = program.to_string();
+            let mut errors = vec![];
+            let parsed = parser::ProgramParser::new()
+                .parse(&mut errors, Lexer::new(&source))
+                .map(without_spans);
+            prop_assert_eq!(parsed, Ok(program), "printed as {}", source);
+            prop_assert_eq!(errors, vec![], "printed as {}", source);
+        }
+    }
 }
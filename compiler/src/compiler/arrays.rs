@@ -0,0 +1,146 @@
+//! Arrays.
+//!
+//! An array is a pointer to its length followed by a pointer to its elements, so that arrays
+//! of any length have the same type and `args` can point into `argv` without copying it.
+use inkwell::{
+    types::{BasicType, StructType},
+    values::{BasicValueEnum, IntValue, PointerValue},
+    AddressSpace,
+};
+
+use super::{check_type, external, Compiler};
+use crate::ast::{Expression, Type};
+
+impl<'a> Compiler<'a> {
+    /// The type an array of `element_type` points to.
+    pub(super) fn array_type(&self, element_type: &Type) -> Result<StructType, String> {
+        Ok(self.context.struct_type(
+            &[
+                self.context.i64_type().into(),
+                self.llvm_type(element_type)?
+                    .ptr_type(AddressSpace::Generic)
+                    .into(),
+            ],
+            false,
+        ))
+    }
+
+    /// Compiles an array literal. The type of its elements is the expected one, or the type
+    /// of its first element.
+    pub(super) fn compile_array(
+        &mut self,
+        elements: &[Expression],
+        expected: Option<&Type>,
+    ) -> Result<(BasicValueEnum, Type), String> {
+        let mut element_type = match expected {
+            Some(Type::Array(element_type)) => Some((**element_type).clone()),
+            _ => None,
+        };
+        let mut values = vec![];
+        for element in elements {
+            let (value, value_type) = self.compile_expression(element, element_type.as_ref())?;
+            match &element_type {
+                Some(element_type) => check_type(element_type, &value_type)?,
+                None => element_type = Some(value_type),
+            }
+            values.push(value);
+        }
+        let element_type =
+            element_type.ok_or_else(|| "cannot infer the type of an empty array".to_string())?;
+        let array_type = self.array_type(&element_type)?;
+
+        let element_llvm_type = self.llvm_type(&element_type)?;
+        let size = element_llvm_type
+            .array_type(values.len() as u32)
+            .size_of()
+            .expect("array elements are sized");
+        let memory = self
+            .builder
+            .build_call(
+                self.module
+                    .get_function(external::stdlib::MALLOC)
+                    .expect("malloc should be declared"),
+                &[size.into()],
+                "elements",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap();
+        let data = self.builder.build_pointer_cast(
+            memory.into_pointer_value(),
+            element_llvm_type.ptr_type(AddressSpace::Generic),
+            "elements",
+        );
+        for (index, value) in values.into_iter().enumerate() {
+            let index = self.context.i64_type().const_int(index as u64, false);
+            let pointer = unsafe { self.builder.build_in_bounds_gep(data, &[index], "element") };
+            self.builder.build_store(pointer, value);
+        }
+
+        let pointer = self.build_malloc(array_type, "array");
+        let length_pointer = unsafe { self.builder.build_struct_gep(pointer, 0, "length") };
+        self.builder.build_store(
+            length_pointer,
+            self.context
+                .i64_type()
+                .const_int(elements.len() as u64, false),
+        );
+        let data_pointer = unsafe { self.builder.build_struct_gep(pointer, 1, "elements") };
+        self.builder.build_store(data_pointer, data);
+
+        Ok((pointer.into(), Type::Array(Box::new(element_type))))
+    }
+
+    /// Compiles `array[index]` as a place, returning a pointer to the element.
+    pub(super) fn compile_element_place(
+        &mut self,
+        array: &Expression,
+        index: &Expression,
+    ) -> Result<(PointerValue, Type), String> {
+        let (array, ty) = self.compile_expression(array, None)?;
+        let element_type = match ty {
+            Type::Array(element_type) => *element_type,
+            ty => return Err(format!("cannot index into a value of type `{}`", ty)),
+        };
+        let (index, index_type) = self.compile_expression(index, Some(&Type::Int))?;
+        check_type(&Type::Int, &index_type)?;
+
+        let data_pointer = unsafe {
+            self.builder
+                .build_struct_gep(array.into_pointer_value(), 1, "elements")
+        };
+        let data = self
+            .builder
+            .build_load(data_pointer, "elements")
+            .into_pointer_value();
+        let pointer = unsafe {
+            self.builder
+                .build_in_bounds_gep(data, &[index.into_int_value()], "element")
+        };
+
+        Ok((pointer, element_type))
+    }
+
+    /// Compiles the builtin `len`, which takes an array of any type.
+    pub(super) fn compile_len(&mut self, args: &[Expression]) -> Result<IntValue, String> {
+        if args.len() != 1 {
+            return Err(format!(
+                "function `len` takes 1 argument(s) but {} were supplied",
+                args.len()
+            ));
+        }
+        let (array, ty) = self.compile_expression(&args[0], None)?;
+        if !matches!(ty, Type::Array(_)) {
+            return Err(format!("expected an array, found `{}`", ty));
+        }
+
+        let length_pointer = unsafe {
+            self.builder
+                .build_struct_gep(array.into_pointer_value(), 0, "length")
+        };
+        Ok(self
+            .builder
+            .build_load(length_pointer, "length")
+            .into_int_value())
+    }
+}
@@ -0,0 +1,625 @@
+//! Lambdas and function values.
+//!
+//! A function value is a pair of a function pointer and a pointer to its environment, which
+//! holds copies of the variables captured by a lambda. The environment is passed to the
+//! function as its first argument.
+use inkwell::{
+    module::Linkage,
+    types::{BasicType, FunctionType, PointerType, StructType},
+    values::{BasicValueEnum, FunctionValue, PointerValue},
+    AddressSpace,
+};
+use std::collections::HashMap;
+
+use super::{can_tail_call, check_type, patterns, stdlib, Compiler, Variable, SYNTAX_ERROR};
+use crate::ast::{
+    BinaryOperator, Expression, Lambda, LambdaBody, MatchArmBody, Pattern, Statement,
+    StatementKind, Type,
+};
+
+impl<'a> Compiler<'a> {
+    /// Compiles a lambda, using the expected types of its parameters and the expected return
+    /// type, if they are known, for the types that are not written. An expected return type
+    /// of `Some(None)` means that the lambda is not expected to return a value.
+    pub(super) fn compile_lambda(
+        &mut self,
+        lambda: &Lambda,
+        expected_parameters: Option<&[Type]>,
+        expected_return_type: Option<Option<&Type>>,
+    ) -> Result<(BasicValueEnum, Type), String> {
+        let expected_parameters =
+            expected_parameters.filter(|parameters| parameters.len() == lambda.parameters.len());
+
+        let mut parameters = vec![];
+        for (index, (name, ty)) in lambda.parameters.iter().enumerate() {
+            let ty = ty
+                .as_ref()
+                .map(|ty| self.resolve(ty))
+                .or_else(|| expected_parameters.map(|parameters| parameters[index].clone()))
+                .ok_or_else(|| format!("cannot infer the type of parameter `{}`", name))?;
+            parameters.push((name.clone(), ty));
+        }
+        let return_type = match (&lambda.return_type, expected_return_type, &lambda.body) {
+            (Some(ty), _, _) => Some(self.resolve(ty)),
+            (None, Some(return_type), _) => return_type.cloned(),
+            (None, None, LambdaBody::Expression(body)) => {
+                let mut locals = vec![parameters.iter().cloned().collect()];
+                self.type_of(body, &mut locals)?
+            }
+            (None, None, LambdaBody::Block(_)) => None,
+        };
+        let ty = Type::Function(
+            parameters.iter().map(|(_, ty)| ty.clone()).collect(),
+            return_type.clone().map(Box::new),
+        );
+
+        // Captured variables are copied into the environment when the lambda is created.
+        let captures = free_variables(lambda)
+            .into_iter()
+            .filter_map(|name| {
+                self.lookup(&name)
+                    .ok()
+                    .map(|variable| (name, variable.ty.clone(), variable.pointer))
+            })
+            .collect::<Vec<_>>();
+        let env_type = self.context.struct_type(
+            &captures
+                .iter()
+                .map(|(_, ty, _)| self.llvm_type(ty))
+                .collect::<Result<Vec<_>, _>>()?,
+            false,
+        );
+        let env = if captures.is_empty() {
+            self.env_type().const_null()
+        } else {
+            let env = self.build_malloc(env_type, "env");
+            for (index, (name, _, pointer)) in captures.iter().enumerate() {
+                let value = self.builder.build_load(*pointer, name);
+                let field_pointer =
+                    unsafe { self.builder.build_struct_gep(env, index as u32, name) };
+                self.builder.build_store(field_pointer, value);
+            }
+            self.builder.build_pointer_cast(env, self.env_type(), "env")
+        };
+
+        let fn_type = self.closure_fn_type(
+            &parameters
+                .iter()
+                .map(|(_, ty)| ty.clone())
+                .collect::<Vec<_>>(),
+            return_type.as_ref(),
+        )?;
+        let fcn = self
+            .module
+            .add_function("FAANG_lambda", fn_type, Some(Linkage::Internal));
+
+        let block = self.builder.get_insert_block().unwrap();
+        let function = self.function.replace(fcn);
+        let outer_return_type = std::mem::replace(&mut self.return_type, return_type);
+        let in_main = std::mem::replace(&mut self.in_main, false);
+        let scopes = std::mem::take(&mut self.scopes);
+
+        let result = self.compile_lambda_body(lambda, fcn, &captures, env_type, &parameters);
+
+        self.function = function;
+        self.return_type = outer_return_type;
+        self.in_main = in_main;
+        self.scopes = scopes;
+        self.builder.position_at_end(&block);
+        result?;
+
+        let closure = self.build_closure(&ty, fcn.as_global_value().as_pointer_value(), env)?;
+        Ok((closure, ty))
+    }
+
+    fn compile_lambda_body(
+        &mut self,
+        lambda: &Lambda,
+        fcn: FunctionValue,
+        captures: &[(String, Type, PointerValue)],
+        env_type: StructType,
+        parameters: &[(String, Type)],
+    ) -> Result<(), String> {
+        let block = self.context.append_basic_block(&fcn, "entry");
+        self.builder.position_at_end(&block);
+
+        let mut scope = HashMap::new();
+        let values = fcn.get_params();
+        if !captures.is_empty() {
+            let env = self.builder.build_pointer_cast(
+                values[0].into_pointer_value(),
+                env_type.ptr_type(AddressSpace::Generic),
+                "env",
+            );
+            for (index, (name, ty, _)) in captures.iter().enumerate() {
+                let field_pointer =
+                    unsafe { self.builder.build_struct_gep(env, index as u32, name) };
+                let value = self.builder.build_load(field_pointer, name);
+                let pointer = self.create_entry_block_alloca(ty, name)?;
+                self.builder.build_store(pointer, value);
+                scope.insert(
+                    name.clone(),
+                    Variable {
+                        pointer,
+                        ty: ty.clone(),
+                    },
+                );
+            }
+        }
+        for ((name, ty), value) in parameters.iter().zip(&values[1..]) {
+            let pointer = self.create_entry_block_alloca(ty, name)?;
+            self.builder.build_store(pointer, *value);
+            scope.insert(
+                name.clone(),
+                Variable {
+                    pointer,
+                    ty: ty.clone(),
+                },
+            );
+        }
+        self.scopes.push(scope);
+
+        match &lambda.body {
+            LambdaBody::Expression(body) => match self.return_type.clone() {
+                Some(return_type) => {
+                    let (value, value_type) = self.compile_expression(body, Some(&return_type))?;
+                    check_type(&return_type, &value_type)?;
+                    self.builder.build_return(Some(&value));
+                }
+                None => {
+                    self.compile_expression_statement(body)?;
+                    self.builder.build_return(None);
+                }
+            },
+            LambdaBody::Block(body) => {
+                self.compile_statements(body)?;
+                self.finish_function("lambda", body)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compiles a function of the program used as a value.
+    pub(super) fn compile_function_value(
+        &mut self,
+        name: &str,
+    ) -> Result<(BasicValueEnum, Type), String> {
+        let name = &self.resolve_function(name);
+        if self.generics.contains_key(name) {
+            return Err(format!("cannot use generic function `{}` as a value", name));
+        }
+        let signature = self
+            .signatures
+            .get(name)
+            .cloned()
+            .or_else(|| stdlib::signature(name))
+            .ok_or_else(|| format!("cannot find value `{}` in this scope", name))?;
+        let fcn = self
+            .module
+            .get_function(&format!("FAANG_{}", name))
+            .ok_or_else(|| format!("cannot find value `{}` in this scope", name))?;
+        let ty = Type::Function(
+            signature.parameters.clone(),
+            signature.return_type.clone().map(Box::new),
+        );
+
+        // Functions of the program do not take an environment, so they are called through
+        // a wrapper that ignores it.
+        let wrapper_name = format!("FAANG_{}.closure", name);
+        let wrapper = match self.module.get_function(&wrapper_name) {
+            Some(wrapper) => wrapper,
+            None => {
+                let fn_type =
+                    self.closure_fn_type(&signature.parameters, signature.return_type.as_ref())?;
+                let wrapper =
+                    self.module
+                        .add_function(&wrapper_name, fn_type, Some(Linkage::LinkOnceODR));
+                let builder = self.context.create_builder();
+                let block = self.context.append_basic_block(&wrapper, "entry");
+                builder.position_at_end(&block);
+                let args = wrapper.get_params()[1..].to_vec();
+                match signature.return_type {
+                    Some(_) => {
+                        let call = builder.build_call(fcn, &args, "val");
+                        call.set_tail_call(can_tail_call(&args));
+                        builder.build_return(Some(&call.try_as_basic_value().left().unwrap()));
+                    }
+                    None => {
+                        let call = builder.build_call(fcn, &args, "");
+                        call.set_tail_call(can_tail_call(&args));
+                        builder.build_return(None);
+                    }
+                }
+                wrapper
+            }
+        };
+
+        let env = self.env_type().const_null();
+        let closure = self.build_closure(&ty, wrapper.as_global_value().as_pointer_value(), env)?;
+        Ok((closure, ty))
+    }
+
+    /// Calls a function value of type `ty`.
+    pub(super) fn compile_closure_call(
+        &mut self,
+        description: &str,
+        closure: BasicValueEnum,
+        ty: &Type,
+        args: &[Expression],
+        tail_call: bool,
+    ) -> Result<Option<(BasicValueEnum, Type)>, String> {
+        let (parameters, return_type) = match ty {
+            Type::Function(parameters, return_type) => (parameters, return_type),
+            _ => return Err(format!("expected a function, found `{}`", ty)),
+        };
+
+        let mut values = vec![];
+        let closure = closure.into_struct_value();
+        let fn_pointer = self
+            .builder
+            .build_extract_value(closure, 0, "fn")
+            .unwrap()
+            .into_pointer_value();
+        values.push(self.builder.build_extract_value(closure, 1, "env").unwrap());
+        values.extend(self.compile_arguments(description, args, parameters)?);
+
+        let call_name = if return_type.is_some() { "val" } else { "" };
+        let call = self.builder.build_call(fn_pointer, &values, call_name);
+        call.set_tail_call(tail_call && can_tail_call(&values));
+
+        Ok(return_type.as_ref().map(|return_type| {
+            let value = call.try_as_basic_value().left().unwrap();
+            (value, (**return_type).clone())
+        }))
+    }
+
+    /// The type of the function in a function value, which takes the environment first.
+    pub(super) fn closure_fn_type(
+        &self,
+        parameters: &[Type],
+        return_type: Option<&Type>,
+    ) -> Result<FunctionType, String> {
+        let mut parameter_types = vec![self.env_type().into()];
+        for ty in parameters {
+            parameter_types.push(self.llvm_type(ty)?);
+        }
+
+        Ok(match return_type {
+            Some(ty) => self.llvm_type(ty)?.fn_type(&parameter_types, false),
+            None => self.context.void_type().fn_type(&parameter_types, false),
+        })
+    }
+
+    fn env_type(&self) -> PointerType {
+        self.context.i8_type().ptr_type(AddressSpace::Generic)
+    }
+
+    fn build_closure(
+        &self,
+        ty: &Type,
+        function: PointerValue,
+        env: PointerValue,
+    ) -> Result<BasicValueEnum, String> {
+        let pointer = self.create_entry_block_alloca(ty, "closure")?;
+        let function_pointer = unsafe { self.builder.build_struct_gep(pointer, 0, "fn") };
+        self.builder.build_store(function_pointer, function);
+        let env_pointer = unsafe { self.builder.build_struct_gep(pointer, 1, "env") };
+        self.builder.build_store(env_pointer, env);
+
+        Ok(self.builder.build_load(pointer, "closure"))
+    }
+
+    /// Works out the type of an expression without compiling it, which is needed to declare
+    /// a lambda whose return type is neither written nor expected. `locals` holds the
+    /// variables declared inside the lambda.
+    fn type_of(
+        &self,
+        expression: &Expression,
+        locals: &mut Vec<HashMap<String, Type>>,
+    ) -> Result<Option<Type>, String> {
+        let ty = match expression {
+            Expression::Invocation(name, args) => match self.local_type(name, locals) {
+                Some(ty) => return function_return_type(&ty),
+                None => return self.invocation_type(name, args, locals),
+            },
+            Expression::Call(callee, _) => {
+                let ty = self
+                    .type_of(callee, locals)?
+                    .ok_or("expected a function, found no value")?;
+                return function_return_type(&ty);
+            }
+            Expression::StringLiteral(_) => Type::String,
+            Expression::IntegerLiteral(_) => Type::Int,
+            Expression::BooleanLiteral(_) => Type::Bool,
+            Expression::Null => return Err("cannot infer the type of `null`".into()),
+            Expression::Error => return Err(SYNTAX_ERROR.into()),
+            Expression::Variable(name) => match self.local_type(name, locals) {
+                Some(ty) => ty,
+                None => self
+                    .signatures
+                    .get(&self.resolve_function(name))
+                    .cloned()
+                    .or_else(|| stdlib::signature(name))
+                    .map(|signature| {
+                        Type::Function(signature.parameters, signature.return_type.map(Box::new))
+                    })
+                    .ok_or_else(|| format!("cannot find value `{}` in this scope", name))?,
+            },
+            Expression::FieldAccess(object, field) => {
+                let ty = self.type_of(object, locals)?;
+                ty.as_ref()
+                    .and_then(|ty| match ty {
+                        Type::Named(name) => self.structs.get(name),
+                        _ => None,
+                    })
+                    .and_then(|definition| {
+                        definition
+                            .fields
+                            .iter()
+                            .find(|(name, _)| name == field)
+                            .map(|(_, ty)| ty.clone())
+                    })
+                    .ok_or_else(|| match ty {
+                        Some(ty) => format!("type `{}` has no field `{}`", ty, field),
+                        None => format!("no value has a field `{}`", field),
+                    })?
+            }
+            Expression::StructLiteral(name, _) => Type::Named(name.clone()),
+            Expression::Array(elements) => match elements.first() {
+                Some(first) => match self.type_of(first, locals)? {
+                    Some(ty) => Type::Array(Box::new(ty)),
+                    None => return Ok(None),
+                },
+                None => return Err("cannot infer the type of an empty array".into()),
+            },
+            Expression::Index(array, _) => match self.type_of(array, locals)? {
+                Some(Type::Array(element_type)) => *element_type,
+                Some(ty) => return Err(format!("cannot index into a value of type `{}`", ty)),
+                None => return Ok(None),
+            },
+            Expression::Variant(enum_name, _, _) => match self.module_call(expression) {
+                Some((name, args)) => return self.invocation_type(&name, args, locals),
+                None => Type::Named(enum_name.clone()),
+            },
+            Expression::Match(scrutinee, arms) => {
+                let scrutinee_type = match self.type_of(scrutinee, locals)? {
+                    Some(ty) => ty,
+                    None => return Ok(None),
+                };
+                for arm in arms {
+                    if let MatchArmBody::Expression(body) = &arm.body {
+                        locals.push(
+                            patterns::bindings(&arm.pattern, &scrutinee_type, &self.enums)
+                                .into_iter()
+                                .collect(),
+                        );
+                        let ty = self.type_of(body, locals);
+                        locals.pop();
+                        if let Some(ty) = ty? {
+                            return Ok(Some(ty));
+                        }
+                    }
+                }
+                return Ok(None);
+            }
+            Expression::Lambda(lambda) => {
+                let mut parameters = HashMap::new();
+                let mut parameter_types = vec![];
+                for (name, ty) in &lambda.parameters {
+                    let ty = ty
+                        .as_ref()
+                        .map(|ty| self.resolve(ty))
+                        .ok_or_else(|| format!("cannot infer the type of parameter `{}`", name))?;
+                    parameters.insert(name.clone(), ty.clone());
+                    parameter_types.push(ty);
+                }
+                let return_type = match (&lambda.return_type, &lambda.body) {
+                    (Some(ty), _) => Some(self.resolve(ty)),
+                    (None, LambdaBody::Expression(body)) => {
+                        locals.push(parameters);
+                        let ty = self.type_of(body, locals);
+                        locals.pop();
+                        ty?
+                    }
+                    (None, LambdaBody::Block(_)) => None,
+                };
+                Type::Function(parameter_types, return_type.map(Box::new))
+            }
+            Expression::Unary(_, operand) => return self.type_of(operand, locals),
+            Expression::Binary(lhs, operator, rhs) => match operator {
+                BinaryOperator::Add
+                | BinaryOperator::Subtract
+                | BinaryOperator::Multiply
+                | BinaryOperator::Divide
+                | BinaryOperator::Remainder => match **lhs {
+                    Expression::Null => return self.type_of(rhs, locals),
+                    _ => return self.type_of(lhs, locals),
+                },
+                _ => Type::Bool,
+            },
+        };
+
+        Ok(Some(ty))
+    }
+
+    /// The return type of a call of a function of the program.
+    fn invocation_type(
+        &self,
+        name: &str,
+        args: &[Expression],
+        locals: &mut Vec<HashMap<String, Type>>,
+    ) -> Result<Option<Type>, String> {
+        let name = &self.resolve_function(name);
+        if self.generics.contains_key(name) {
+            let arg_types = args
+                .iter()
+                .map(|arg| self.type_of(arg, locals).ok().and_then(|ty| ty))
+                .collect::<Vec<_>>();
+            return self.generic_return_type(name, &arg_types);
+        }
+        if self.is_builtin(name, "len") {
+            return Ok(Some(Type::Int));
+        }
+        if self.is_builtin(name, "panic")
+            || self.is_builtin(name, "assert")
+            || self.is_builtin(name, "assert_eq")
+        {
+            return Ok(None);
+        }
+
+        self.signatures
+            .get(name)
+            .cloned()
+            .or_else(|| stdlib::signature(name))
+            .map(|signature| signature.return_type)
+            .ok_or_else(|| format!("cannot find function `{}`", name))
+    }
+
+    fn local_type(&self, name: &str, locals: &[HashMap<String, Type>]) -> Option<Type> {
+        locals
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+            .or_else(|| self.lookup(name).ok().map(|variable| variable.ty.clone()))
+    }
+}
+
+fn function_return_type(ty: &Type) -> Result<Option<Type>, String> {
+    match ty {
+        Type::Function(_, return_type) => Ok(return_type.as_ref().map(|ty| (**ty).clone())),
+        _ => Err(format!("expected a function, found `{}`", ty)),
+    }
+}
+
+/// The names used in a lambda that are not declared inside it, in order of first use.
+fn free_variables(lambda: &Lambda) -> Vec<String> {
+    let mut bound = lambda
+        .parameters
+        .iter()
+        .map(|(name, _)| name.clone())
+        .collect::<Vec<_>>();
+    let mut free = vec![];
+    match &lambda.body {
+        LambdaBody::Expression(body) => expression_free_variables(body, &mut bound, &mut free),
+        LambdaBody::Block(body) => block_free_variables(body, &mut bound, &mut free),
+    }
+    free
+}
+
+fn use_name(name: &str, bound: &[String], free: &mut Vec<String>) {
+    if !bound.iter().any(|bound| bound == name) && !free.iter().any(|free| free == name) {
+        free.push(name.into());
+    }
+}
+
+fn expression_free_variables(
+    expression: &Expression,
+    bound: &mut Vec<String>,
+    free: &mut Vec<String>,
+) {
+    match expression {
+        Expression::Invocation(name, args) => {
+            use_name(name, bound, free);
+            for arg in args {
+                expression_free_variables(arg, bound, free);
+            }
+        }
+        Expression::Call(callee, args) => {
+            expression_free_variables(callee, bound, free);
+            for arg in args {
+                expression_free_variables(arg, bound, free);
+            }
+        }
+        Expression::Variable(name) => use_name(name, bound, free),
+        Expression::FieldAccess(object, _) => expression_free_variables(object, bound, free),
+        Expression::StructLiteral(_, fields) => {
+            for (_, field) in fields {
+                expression_free_variables(field, bound, free);
+            }
+        }
+        Expression::Variant(_, _, args) | Expression::Array(args) => {
+            for arg in args {
+                expression_free_variables(arg, bound, free);
+            }
+        }
+        Expression::Index(array, index) => {
+            expression_free_variables(array, bound, free);
+            expression_free_variables(index, bound, free);
+        }
+        Expression::Match(scrutinee, arms) => {
+            expression_free_variables(scrutinee, bound, free);
+            for arm in arms {
+                let len = bound.len();
+                pattern_names(&arm.pattern, bound);
+                match &arm.body {
+                    MatchArmBody::Expression(body) => expression_free_variables(body, bound, free),
+                    MatchArmBody::Block(body) => block_free_variables(body, bound, free),
+                }
+                bound.truncate(len);
+            }
+        }
+        Expression::Lambda(lambda) => {
+            let len = bound.len();
+            bound.extend(lambda.parameters.iter().map(|(name, _)| name.clone()));
+            match &lambda.body {
+                LambdaBody::Expression(body) => expression_free_variables(body, bound, free),
+                LambdaBody::Block(body) => block_free_variables(body, bound, free),
+            }
+            bound.truncate(len);
+        }
+        Expression::Unary(_, operand) => expression_free_variables(operand, bound, free),
+        Expression::Binary(lhs, _, rhs) => {
+            expression_free_variables(lhs, bound, free);
+            expression_free_variables(rhs, bound, free);
+        }
+        Expression::StringLiteral(_)
+        | Expression::IntegerLiteral(_)
+        | Expression::BooleanLiteral(_)
+        | Expression::Null
+        | Expression::Error => {}
+    }
+}
+
+fn block_free_variables(statements: &[Statement], bound: &mut Vec<String>, free: &mut Vec<String>) {
+    let len = bound.len();
+    for statement in statements {
+        match &statement.kind {
+            StatementKind::Let(name, _, expression) => {
+                expression_free_variables(expression, bound, free);
+                bound.push(name.clone());
+            }
+            StatementKind::Assignment(place, expression) => {
+                expression_free_variables(place, bound, free);
+                expression_free_variables(expression, bound, free);
+            }
+            StatementKind::Expression(expression) | StatementKind::Return(Some(expression)) => {
+                expression_free_variables(expression, bound, free)
+            }
+            StatementKind::Return(None) | StatementKind::Error => {}
+            StatementKind::If(condition, then, otherwise) => {
+                expression_free_variables(condition, bound, free);
+                block_free_variables(then, bound, free);
+                block_free_variables(otherwise, bound, free);
+            }
+            StatementKind::While(condition, body) => {
+                expression_free_variables(condition, bound, free);
+                block_free_variables(body, bound, free);
+            }
+        }
+    }
+    bound.truncate(len);
+}
+
+fn pattern_names(pattern: &Pattern, names: &mut Vec<String>) {
+    match pattern {
+        Pattern::Binding(name) => names.push(name.clone()),
+        Pattern::Variant(_, _, fields) => {
+            for field in fields {
+                pattern_names(field, names);
+            }
+        }
+        _ => {}
+    }
+}
@@ -1,18 +1,38 @@
 pub mod stdio {
     use inkwell::{
-        context::Context, module::Linkage, module::Module, values::FunctionValue, AddressSpace,
+        context::Context,
+        module::Linkage,
+        module::Module,
+        values::{FunctionValue, GlobalValue},
+        AddressSpace,
     };
 
     pub static PRINTF: &str = "printf";
     pub static FPRINTF: &str = "fprintf";
     pub static FFLUSH: &str = "fflush";
     pub static FOPEN: &str = "fopen";
+    pub static FCLOSE: &str = "fclose";
+    pub static PERROR: &str = "perror";
+    pub static GETLINE: &str = "getline";
+    pub static GETDELIM: &str = "getdelim";
+    pub static SCANF: &str = "scanf";
+    pub static STDIN: &str = "stdin";
+    pub static STDOUT: &str = "stdout";
+    pub static STDERR: &str = "stderr";
 
     pub enum Features {
         PRINTF,
         FPRINTF,
         FFLUSH,
         FOPEN,
+        FCLOSE,
+        PERROR,
+        GETLINE,
+        GETDELIM,
+        SCANF,
+        STDIN,
+        STDOUT,
+        STDERR,
     }
 
     pub fn add(features: &[Features], context: &Context, module: &Module) {
@@ -30,6 +50,30 @@ pub mod stdio {
                 Features::FOPEN => {
                     add_fopen(context, module);
                 }
+                Features::FCLOSE => {
+                    add_fclose(context, module);
+                }
+                Features::PERROR => {
+                    add_perror(context, module);
+                }
+                Features::GETLINE => {
+                    add_getline(context, module);
+                }
+                Features::GETDELIM => {
+                    add_getdelim(context, module);
+                }
+                Features::SCANF => {
+                    add_scanf(context, module);
+                }
+                Features::STDIN => {
+                    add_stdin(context, module);
+                }
+                Features::STDOUT => {
+                    add_stdout(context, module);
+                }
+                Features::STDERR => {
+                    add_stderr(context, module);
+                }
             }
         }
     }
@@ -77,51 +121,179 @@ pub mod stdio {
         )
     }
 
-    pub mod mock {
-        use super::super::super::util;
-        use super::*;
-        use inkwell::builder::Builder;
-
-        /// Adds a mock implementation of printf, which is just an adapter for fprintf.
-        #[allow(dead_code)]
-        pub fn add_printf(
-            file_name: &str,
-            context: &Context,
-            module: &Module,
-            builder: &Builder,
-        ) -> FunctionValue {
-            let printf = module.add_function(
-                PRINTF,
-                context.i32_type().fn_type(
-                    &[context.i8_type().ptr_type(AddressSpace::Generic).into()],
-                    true,
-                ),
-                None,
-            );
-            let block = context.append_basic_block(&printf, "entry");
-            builder.position_at_end(&block);
-            let file_name = util::alloc_string(file_name, "file_name", &context, &builder);
-            let mode = util::alloc_string("a", "mode", &context, &builder);
-            let file = builder
-                .build_call(
-                    module.get_function(FOPEN).unwrap(),
-                    &[file_name.into(), mode.into()],
-                    "file",
-                )
-                .try_as_basic_value()
-                .left()
-                .unwrap();
-
-            let mut fprintf_args = vec![file.into()];
-            fprintf_args.extend(printf.get_params());
-            builder.build_call(
-                module.get_function(FPRINTF).unwrap(),
-                &fprintf_args,
-                FPRINTF,
-            );
-            builder.build_call(module.get_function(FFLUSH).unwrap(), &[file.into()], FFLUSH);
-            builder.build_return(Some(&context.i32_type().const_int(0, false)));
-            printf
+    fn add_fclose(context: &Context, module: &Module) -> FunctionValue {
+        let file_ptr = context.i8_type().ptr_type(AddressSpace::Generic);
+        module.add_function(
+            FCLOSE,
+            context.i32_type().fn_type(&[file_ptr.into()], false),
+            Some(Linkage::External),
+        )
+    }
+
+    fn add_perror(context: &Context, module: &Module) -> FunctionValue {
+        let const_char_ptr = context.i8_type().ptr_type(AddressSpace::Generic);
+        module.add_function(
+            PERROR,
+            context.void_type().fn_type(&[const_char_ptr.into()], false),
+            Some(Linkage::External),
+        )
+    }
+
+    fn add_getline(context: &Context, module: &Module) -> FunctionValue {
+        let char_ptr = context.i8_type().ptr_type(AddressSpace::Generic);
+        let file_ptr = context.i8_type().ptr_type(AddressSpace::Generic);
+        module.add_function(
+            GETLINE,
+            context.i64_type().fn_type(
+                &[
+                    char_ptr.ptr_type(AddressSpace::Generic).into(),
+                    context.i64_type().ptr_type(AddressSpace::Generic).into(),
+                    file_ptr.into(),
+                ],
+                false,
+            ),
+            Some(Linkage::External),
+        )
+    }
+
+    fn add_getdelim(context: &Context, module: &Module) -> FunctionValue {
+        let char_ptr = context.i8_type().ptr_type(AddressSpace::Generic);
+        let file_ptr = context.i8_type().ptr_type(AddressSpace::Generic);
+        module.add_function(
+            GETDELIM,
+            context.i64_type().fn_type(
+                &[
+                    char_ptr.ptr_type(AddressSpace::Generic).into(),
+                    context.i64_type().ptr_type(AddressSpace::Generic).into(),
+                    context.i32_type().into(),
+                    file_ptr.into(),
+                ],
+                false,
+            ),
+            Some(Linkage::External),
+        )
+    }
+
+    fn add_scanf(context: &Context, module: &Module) -> FunctionValue {
+        let const_char_ptr = context.i8_type().ptr_type(AddressSpace::Generic);
+        module.add_function(
+            SCANF,
+            context.i32_type().fn_type(&[const_char_ptr.into()], true),
+            Some(Linkage::External),
+        )
+    }
+
+    /// Declares the `FILE *` of the standard input.
+    fn add_stdin(context: &Context, module: &Module) -> GlobalValue {
+        let stdin = module.add_global(
+            context.i8_type().ptr_type(AddressSpace::Generic),
+            None,
+            STDIN,
+        );
+        stdin.set_linkage(Linkage::External);
+        stdin
+    }
+
+    /// Declares the `FILE *` of the standard output.
+    fn add_stdout(context: &Context, module: &Module) -> GlobalValue {
+        let stdout = module.add_global(
+            context.i8_type().ptr_type(AddressSpace::Generic),
+            None,
+            STDOUT,
+        );
+        stdout.set_linkage(Linkage::External);
+        stdout
+    }
+
+    /// Declares the `FILE *` of the standard error.
+    fn add_stderr(context: &Context, module: &Module) -> GlobalValue {
+        let stderr = module.add_global(
+            context.i8_type().ptr_type(AddressSpace::Generic),
+            None,
+            STDERR,
+        );
+        stderr.set_linkage(Linkage::External);
+        stderr
+    }
+}
+
+pub mod stdlib {
+    use inkwell::{
+        context::Context, module::Linkage, module::Module, values::FunctionValue, AddressSpace,
+    };
+
+    pub static MALLOC: &str = "malloc";
+    pub static EXIT: &str = "exit";
+
+    pub enum Features {
+        MALLOC,
+        EXIT,
+    }
+
+    pub fn add(features: &[Features], context: &Context, module: &Module) {
+        for feature in features {
+            match feature {
+                Features::MALLOC => {
+                    add_malloc(context, module);
+                }
+                Features::EXIT => {
+                    add_exit(context, module);
+                }
+            }
+        }
+    }
+
+    fn add_malloc(context: &Context, module: &Module) -> FunctionValue {
+        module.add_function(
+            MALLOC,
+            context
+                .i8_type()
+                .ptr_type(AddressSpace::Generic)
+                .fn_type(&[context.i64_type().into()], false),
+            Some(Linkage::External),
+        )
+    }
+
+    fn add_exit(context: &Context, module: &Module) -> FunctionValue {
+        module.add_function(
+            EXIT,
+            context
+                .void_type()
+                .fn_type(&[context.i32_type().into()], false),
+            Some(Linkage::External),
+        )
+    }
+}
+
+pub mod string {
+    use inkwell::{
+        context::Context, module::Linkage, module::Module, values::FunctionValue, AddressSpace,
+    };
+
+    pub static STRCMP: &str = "strcmp";
+
+    pub enum Features {
+        STRCMP,
+    }
+
+    pub fn add(features: &[Features], context: &Context, module: &Module) {
+        for feature in features {
+            match feature {
+                Features::STRCMP => {
+                    add_strcmp(context, module);
+                }
+            }
         }
     }
+
+    fn add_strcmp(context: &Context, module: &Module) -> FunctionValue {
+        let const_char_ptr = context.i8_type().ptr_type(AddressSpace::Generic);
+        module.add_function(
+            STRCMP,
+            context
+                .i32_type()
+                .fn_type(&[const_char_ptr.into(), const_char_ptr.into()], false),
+            Some(Linkage::External),
+        )
+    }
 }
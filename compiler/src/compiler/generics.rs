@@ -0,0 +1,273 @@
+//! Generic functions.
+//!
+//! A generic function is compiled once for every combination of type arguments it is called
+//! with. The type arguments are inferred from the arguments of each call, and the compiled
+//! instances are named after them, such as `FAANG_max<int>`.
+use inkwell::{
+    module::Linkage,
+    types::BasicType,
+    values::{BasicValueEnum, FunctionValue},
+};
+use std::collections::HashMap;
+
+use super::{check_type, Compiler};
+use crate::ast::{Expression, Type};
+
+impl<'a> Compiler<'a> {
+    pub(super) fn compile_generic_invocation(
+        &mut self,
+        name: &str,
+        args: &[Expression],
+        tail_call: bool,
+    ) -> Result<Option<(BasicValueEnum, Type)>, String> {
+        let function = &self.generics[name];
+        let type_parameters = function.type_parameters.clone();
+        let parameters = function
+            .parameters
+            .iter()
+            .map(|(_, ty)| ty.clone())
+            .collect::<Vec<_>>();
+        let return_type = function.return_type.clone();
+
+        if args.len() != parameters.len() {
+            return Err(format!(
+                "function `{}` takes {} argument(s) but {} were supplied",
+                name,
+                parameters.len(),
+                args.len()
+            ));
+        }
+
+        // Lambdas are compiled after the other arguments, so that the types of their
+        // parameters can be inferred from the types of the other arguments.
+        let mut type_arguments = HashMap::new();
+        let mut values = vec![None; args.len()];
+        for lambdas in &[false, true] {
+            for (index, (arg, parameter_type)) in args.iter().zip(&parameters).enumerate() {
+                let expected = substitute(parameter_type, &type_arguments);
+                let (value, value_type) = match arg {
+                    Expression::Lambda(_) if !lambdas => continue,
+                    Expression::Lambda(lambda) => match &expected {
+                        Type::Function(parameters, return_type)
+                            if !parameters.iter().any(|ty| mentions(ty, &type_parameters)) =>
+                        {
+                            let return_type = match return_type {
+                                Some(ty) if mentions(ty, &type_parameters) => None,
+                                return_type => Some(return_type.as_ref().map(|ty| &**ty)),
+                            };
+                            self.compile_lambda(lambda, Some(parameters), return_type)?
+                        }
+                        _ => self.compile_lambda(lambda, None, None)?,
+                    },
+                    _ if *lambdas => continue,
+                    _ if mentions(&expected, &type_parameters) => {
+                        self.compile_expression(arg, None)?
+                    }
+                    _ => self.compile_expression(arg, Some(&expected))?,
+                };
+                unify(
+                    parameter_type,
+                    &value_type,
+                    &type_parameters,
+                    &mut type_arguments,
+                )?;
+                values[index] = Some(value);
+            }
+        }
+
+        for type_parameter in &type_parameters {
+            if !type_arguments.contains_key(type_parameter) {
+                return Err(format!(
+                    "cannot infer type parameter `{}` of function `{}`",
+                    type_parameter, name
+                ));
+            }
+        }
+
+        let fcn = self.instantiate(name, &type_parameters, type_arguments.clone())?;
+        let values = values.into_iter().map(Option::unwrap).collect::<Vec<_>>();
+        let return_type = return_type.map(|ty| substitute(&ty, &type_arguments));
+        Ok(self.build_call(fcn, &values, return_type, tail_call))
+    }
+
+    /// Works out the return type of a call of a generic function from the types of its
+    /// arguments, as `type_of` does for other expressions.
+    pub(super) fn generic_return_type(
+        &self,
+        name: &str,
+        arg_types: &[Option<Type>],
+    ) -> Result<Option<Type>, String> {
+        let function = &self.generics[name];
+        let mut type_arguments = HashMap::new();
+        for ((_, parameter_type), arg_type) in function.parameters.iter().zip(arg_types) {
+            if let Some(arg_type) = arg_type {
+                unify(
+                    parameter_type,
+                    arg_type,
+                    &function.type_parameters,
+                    &mut type_arguments,
+                )?;
+            }
+        }
+
+        match &function.return_type {
+            Some(ty) if mentions(&substitute(ty, &type_arguments), &function.type_parameters) => {
+                Err(format!(
+                    "cannot infer the return type of function `{}`",
+                    name
+                ))
+            }
+            return_type => Ok(return_type
+                .as_ref()
+                .map(|ty| substitute(ty, &type_arguments))),
+        }
+    }
+
+    /// Declares the instance of a generic function for the type arguments, if it has not
+    /// been declared yet. Its body is compiled once the current function is finished.
+    fn instantiate(
+        &mut self,
+        name: &str,
+        type_parameters: &[String],
+        type_arguments: HashMap<String, Type>,
+    ) -> Result<FunctionValue, String> {
+        let fcn_name = format!(
+            "FAANG_{}<{}>",
+            name,
+            type_parameters
+                .iter()
+                .map(|type_parameter| type_arguments[type_parameter].to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        if let Some(fcn) = self.module.get_function(&fcn_name) {
+            return Ok(fcn);
+        }
+
+        let function = &self.generics[name];
+        let parameter_types = function
+            .parameters
+            .iter()
+            .map(|(_, ty)| self.llvm_type(&substitute(ty, &type_arguments)))
+            .collect::<Result<Vec<_>, _>>()?;
+        let fn_type = match &function.return_type {
+            Some(ty) => self
+                .llvm_type(&substitute(ty, &type_arguments))?
+                .fn_type(&parameter_types, false),
+            None => self.context.void_type().fn_type(&parameter_types, false),
+        };
+        let fcn = self
+            .module
+            .add_function(&fcn_name, fn_type, Some(Linkage::LinkOnceODR));
+        self.instances.push((name.into(), type_arguments, fcn));
+
+        Ok(fcn)
+    }
+
+    /// Compiles the bodies of the instances of generic functions, including the instances
+    /// that are only called from other instances.
+    pub(super) fn compile_instances(&mut self) -> Result<(), String> {
+        while let Some((name, type_arguments, fcn)) = self.instances.pop() {
+            let function = self.generics[&name].clone();
+            // The body refers to functions by the names used in the module defining it.
+            let module = match name.rfind("::") {
+                Some(index) => name[..index].into(),
+                None => String::new(),
+            };
+            let current_module = std::mem::replace(&mut self.current_module, module);
+            self.type_arguments = type_arguments;
+            let result = self.compile_function(&function, fcn);
+            self.type_arguments = HashMap::new();
+            self.current_module = current_module;
+            result?;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the type parameters of the function being compiled with its type arguments.
+    pub(super) fn resolve(&self, ty: &Type) -> Type {
+        substitute(ty, &self.type_arguments)
+    }
+}
+
+fn substitute(ty: &Type, type_arguments: &HashMap<String, Type>) -> Type {
+    match ty {
+        Type::Named(name) => type_arguments
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| ty.clone()),
+        Type::Function(parameters, return_type) => Type::Function(
+            parameters
+                .iter()
+                .map(|ty| substitute(ty, type_arguments))
+                .collect(),
+            return_type
+                .as_ref()
+                .map(|ty| Box::new(substitute(ty, type_arguments))),
+        ),
+        Type::Array(element_type) => {
+            Type::Array(Box::new(substitute(element_type, type_arguments)))
+        }
+        _ => ty.clone(),
+    }
+}
+
+/// Whether a type refers to any of the type parameters.
+fn mentions(ty: &Type, type_parameters: &[String]) -> bool {
+    match ty {
+        Type::Named(name) => type_parameters.contains(name),
+        Type::Function(parameters, return_type) => {
+            parameters.iter().any(|ty| mentions(ty, type_parameters))
+                || return_type.iter().any(|ty| mentions(ty, type_parameters))
+        }
+        Type::Array(element_type) => mentions(element_type, type_parameters),
+        _ => false,
+    }
+}
+
+/// Infers the type arguments that make the parameter type equal to the argument type.
+fn unify(
+    parameter_type: &Type,
+    arg_type: &Type,
+    type_parameters: &[String],
+    type_arguments: &mut HashMap<String, Type>,
+) -> Result<(), String> {
+    match (parameter_type, arg_type) {
+        (Type::Named(name), _) if type_parameters.contains(name) => {
+            match type_arguments.get(name) {
+                Some(ty) => check_type(ty, arg_type),
+                None => {
+                    type_arguments.insert(name.clone(), arg_type.clone());
+                    Ok(())
+                }
+            }
+        }
+        (
+            Type::Function(parameters, return_type),
+            Type::Function(arg_parameters, arg_return_type),
+        ) if parameters.len() == arg_parameters.len()
+            && return_type.is_some() == arg_return_type.is_some() =>
+        {
+            for (parameter, arg_parameter) in parameters.iter().zip(arg_parameters) {
+                unify(parameter, arg_parameter, type_parameters, type_arguments)?;
+            }
+            if let (Some(return_type), Some(arg_return_type)) = (return_type, arg_return_type) {
+                unify(
+                    return_type,
+                    arg_return_type,
+                    type_parameters,
+                    type_arguments,
+                )?;
+            }
+            Ok(())
+        }
+        (Type::Array(element_type), Type::Array(arg_element_type)) => unify(
+            element_type,
+            arg_element_type,
+            type_parameters,
+            type_arguments,
+        ),
+        _ => check_type(&substitute(parameter_type, type_arguments), arg_type),
+    }
+}
@@ -1,20 +1,108 @@
+use codespan::Span;
 use inkwell::{
+    basic_block::BasicBlock,
     builder::Builder,
     context::Context,
     module::Module,
-    types::StructType,
-    values::{BasicValueEnum, PointerValue},
-    AddressSpace,
+    types::{BasicType, BasicTypeEnum, StructType},
+    values::{
+        BasicValue, BasicValueEnum, FunctionValue, InstructionOpcode, IntValue, PointerValue,
+    },
+    AddressSpace, IntPredicate,
 };
+use std::collections::HashMap;
 
+use super::ast::{
+    self, BinaryOperator, Expression, MatchArm, MatchArmBody, Pattern, Statement, StatementKind,
+    Type, UnaryOperator,
+};
+
+mod arrays;
+mod closures;
 pub mod external;
+mod generics;
+mod panics;
+mod patterns;
 pub mod stdlib;
-mod util;
+
+/// The error for the `Error` nodes that the parser leaves in place of what it could not parse.
+/// Programs with syntax errors are rejected before they are compiled, so this is only seen
+/// by code that compiles a partial tree.
+static SYNTAX_ERROR: &str = "cannot compile code that failed to parse";
+
+/// A `test` block compiled by `Compiler::compile_tests`.
+#[derive(Clone, Debug)]
+pub struct TestFunction {
+    pub name: String,
+    /// The module the test is in, which is empty for the file being compiled.
+    pub module: String,
+    /// The LLVM function that runs the test, which takes and returns nothing.
+    pub function: String,
+}
+
+/// The FAANG level signature of a callable function.
+#[derive(Clone, Debug)]
+pub struct Signature {
+    pub parameters: Vec<Type>,
+    pub return_type: Option<Type>,
+}
+
+struct StructDefinition {
+    llvm_type: StructType,
+    fields: Vec<(String, Type)>,
+}
+
+struct EnumDefinition {
+    /// The prefix shared by every variant, which holds the tag.
+    llvm_type: StructType,
+    variants: Vec<VariantDefinition>,
+}
+
+/// A variant is stored as its tag followed by its fields. The tag is the index of the variant.
+struct VariantDefinition {
+    name: String,
+    fields: Vec<Type>,
+    llvm_type: StructType,
+}
+
+/// The source file of a module, used to report where panics happen.
+struct SourceFile {
+    name: String,
+    /// The byte offset of the start of every line.
+    line_starts: Vec<usize>,
+}
+
+struct Variable {
+    pointer: PointerValue,
+    ty: Type,
+}
 
 pub struct Compiler<'a> {
     context: &'a Context,
     builder: &'a Builder,
     module: &'a Module,
+    structs: HashMap<String, StructDefinition>,
+    enums: HashMap<String, EnumDefinition>,
+    signatures: HashMap<String, Signature>,
+    generics: HashMap<String, ast::Function>,
+    /// Instances of generic functions whose bodies have not been compiled yet.
+    instances: Vec<(String, HashMap<String, Type>, FunctionValue)>,
+    /// The type arguments of the instance of a generic function being compiled.
+    type_arguments: HashMap<String, Type>,
+    scopes: Vec<HashMap<String, Variable>>,
+    function: Option<FunctionValue>,
+    return_type: Option<Type>,
+    in_main: bool,
+    /// The modules of the program by name, with the functions each one brings into scope
+    /// with `use`. The file being compiled is the module with an empty name.
+    modules: HashMap<String, HashMap<String, String>>,
+    /// The module whose functions are being compiled.
+    current_module: String,
+    sources: HashMap<String, SourceFile>,
+    /// The span of the statement being compiled.
+    span: Span,
+    /// The compiled `test` blocks, if they are being compiled.
+    tests: Option<Vec<TestFunction>>,
 }
 
 impl<'a> Compiler<'a> {
@@ -23,52 +111,1520 @@ impl<'a> Compiler<'a> {
             module,
             context,
             builder,
+            structs: HashMap::new(),
+            enums: HashMap::new(),
+            signatures: HashMap::new(),
+            generics: HashMap::new(),
+            instances: vec![],
+            type_arguments: HashMap::new(),
+            scopes: vec![],
+            function: None,
+            return_type: None,
+            in_main: false,
+            modules: HashMap::new(),
+            current_module: String::new(),
+            sources: HashMap::new(),
+            span: Span::initial(),
+            tests: None,
         }
     }
 
-    pub fn compile(&mut self, program: &super::ast::Expression) -> Result<&Module, String> {
-        let main_fcn =
-            self.module
-                .add_function("main", self.context.i32_type().fn_type(&[], false), None);
+    /// Registers the source file of a module, so that panics in it report the file and line
+    /// they happen at. The file being compiled is the module with an empty name.
+    pub fn add_source(&mut self, module: &str, file_name: &str, source: &str) {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        self.sources.insert(
+            module.to_string(),
+            SourceFile {
+                name: file_name.to_string(),
+                line_starts,
+            },
+        );
+    }
+
+    /// The module and the span of the statement being compiled. After an error, this is the
+    /// statement that failed to compile.
+    pub fn position(&self) -> (&str, Span) {
+        (&self.current_module, self.span)
+    }
+
+    pub fn compile(&mut self, program: &ast::Program) -> Result<&Module, String> {
+        self.compile_modules(program, &[])
+    }
+
+    /// Compiles a program together with the modules it imports into a single LLVM module.
+    ///
+    /// The functions of an imported module are named after it, such as `utils::helper`, so
+    /// modules may define functions with the same name. Types are shared by every module.
+    pub fn compile_modules(
+        &mut self,
+        program: &ast::Program,
+        imports: &[(String, ast::Program)],
+    ) -> Result<&Module, String> {
+        self.compile_program(program, imports, None)
+    }
+
+    /// Compiles the functions of a single module of a program, and `main` if it is the file
+    /// being compiled, which has an empty name. The functions of the other modules are only
+    /// declared, so the LLVM modules of every module can be compiled separately and linked.
+    ///
+    /// Functions that every module may define, such as the instances of generic functions,
+    /// have `linkonce_odr` linkage so that linking keeps one of them.
+    pub fn compile_module(
+        &mut self,
+        name: &str,
+        program: &ast::Program,
+        imports: &[(String, ast::Program)],
+    ) -> Result<&Module, String> {
+        self.compile_program(program, imports, Some(name))
+    }
+
+    /// Compiles a program like `compile_modules`, along with the `test` blocks of every
+    /// module, which are left out otherwise.
+    pub fn compile_tests(
+        &mut self,
+        program: &ast::Program,
+        imports: &[(String, ast::Program)],
+    ) -> Result<Vec<TestFunction>, String> {
+        self.tests = Some(vec![]);
+        self.compile_program(program, imports, None)?;
+        Ok(self.tests.take().unwrap_or_default())
+    }
+
+    fn compile_program(
+        &mut self,
+        program: &ast::Program,
+        imports: &[(String, ast::Program)],
+        only: Option<&str>,
+    ) -> Result<&Module, String> {
+        let mut modules = vec![("", program)];
+        modules.extend(
+            imports
+                .iter()
+                .map(|(name, program)| (name.as_str(), program)),
+        );
+
+        self.declare_types(modules.iter().map(|(_, program)| *program))?;
+
+        for (name, program) in &modules {
+            if self.modules.contains_key(*name) {
+                return Err(format!("module `{}` is imported more than once", name));
+            }
+            let uses = program
+                .items
+                .iter()
+                .filter_map(|item| match item {
+                    ast::Item::Use(declaration) => Some((
+                        declaration.name.clone(),
+                        format!("{}::{}", declaration.module, declaration.name),
+                    )),
+                    _ => None,
+                })
+                .collect();
+            self.modules.insert(name.to_string(), uses);
+        }
+
+        // Every function is declared before any body is compiled, so that functions may call
+        // functions defined later in the program, including each other.
+        for (name, program) in &modules {
+            self.current_module = name.to_string();
+            for item in &program.items {
+                if let ast::Item::Function(function) = item {
+                    self.declare_function(function)?;
+                }
+            }
+        }
+        for (_, program) in &modules {
+            for item in &program.items {
+                if let ast::Item::Use(declaration) = item {
+                    self.check_use(declaration)?;
+                }
+            }
+        }
+
+        for (name, program) in &modules {
+            if matches!(only, Some(only) if only != *name) {
+                continue;
+            }
+            self.current_module = name.to_string();
+            for item in &program.items {
+                match item {
+                    ast::Item::Function(function) if function.type_parameters.is_empty() => {
+                        let fcn = self
+                            .module
+                            .get_function(&format!("FAANG_{}", self.qualify(&function.name)))
+                            .expect("functions are declared before they are compiled");
+                        self.compile_function(function, fcn)?;
+                    }
+                    ast::Item::Test(test) if self.tests.is_some() => {
+                        self.compile_test(test)?;
+                    }
+                    ast::Item::Statement(_) if !name.is_empty() => {
+                        return Err(format!(
+                            "module `{}` has a top level statement, which is only allowed in \
+                             the file being compiled",
+                            name
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        self.current_module = String::new();
+
+        if matches!(only, Some(only) if !only.is_empty()) {
+            self.compile_instances()?;
+            return Ok(&self.module);
+        }
+
+        let main_fcn = self.module.add_function(
+            "main",
+            self.context.i32_type().fn_type(
+                &[
+                    self.context.i32_type().into(),
+                    self.context
+                        .i8_type()
+                        .ptr_type(AddressSpace::Generic)
+                        .ptr_type(AddressSpace::Generic)
+                        .into(),
+                ],
+                false,
+            ),
+            None,
+        );
         let block = self.context.append_basic_block(&main_fcn, "entry");
         self.builder.position_at_end(&block);
 
-        match program {
-            super::ast::Expression::Invocation(name, args) => {
-                let fcn_name = format!("FAANG_{}", name);
-                let fcn = self
-                    .module
-                    .get_function(&fcn_name)
-                    .expect(&format!("function {} should exist", name));
-                self.builder.build_call(
-                    fcn,
-                    &args
+        // The arguments are kept for `args`, when the standard library provides it.
+        if let (Some(argc), Some(argv)) = (
+            self.module.get_global(stdlib::ARGC),
+            self.module.get_global(stdlib::ARGV),
+        ) {
+            self.builder
+                .build_store(argc.as_pointer_value(), main_fcn.get_nth_param(0).unwrap());
+            self.builder
+                .build_store(argv.as_pointer_value(), main_fcn.get_nth_param(1).unwrap());
+        }
+        self.function = Some(main_fcn);
+        self.return_type = None;
+        self.in_main = true;
+
+        let statements = program
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                ast::Item::Statement(statement) => Some(statement),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        self.compile_statements(statements)?;
+
+        if !self.is_terminated() {
+            self.builder
+                .build_return(Some(&self.context.i32_type().const_int(0, false)));
+        }
+
+        self.compile_instances()?;
+
+        Ok(&self.module)
+    }
+
+    fn declare_types<'p, I>(&mut self, programs: I) -> Result<(), String>
+    where
+        I: Iterator<Item = &'p ast::Program> + Clone,
+    {
+        // Every type is declared before any body is set, so that fields may refer to
+        // types declared later in the program, or to the type itself.
+        for item in programs.clone().flat_map(|program| &program.items) {
+            let name = match item {
+                ast::Item::Struct(declaration) => &declaration.name,
+                ast::Item::Enum(declaration) => &declaration.name,
+                _ => continue,
+            };
+            if self.structs.contains_key(name) || self.enums.contains_key(name) {
+                return Err(format!("type `{}` is defined more than once", name));
+            }
+
+            match item {
+                ast::Item::Struct(declaration) => self.declare_struct(declaration)?,
+                ast::Item::Enum(declaration) => self.declare_enum(declaration)?,
+                _ => unreachable!(),
+            }
+        }
+
+        for item in programs.flat_map(|program| &program.items) {
+            match item {
+                ast::Item::Struct(declaration) => {
+                    let field_types = declaration
+                        .fields
                         .iter()
-                        .map(|arg| self.expression_to_basic_value_enum(arg).unwrap())
-                        .collect::<Vec<_>>(),
-                    "val",
-                );
+                        .map(|(_, ty)| self.llvm_type(ty))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    self.structs[&declaration.name]
+                        .llvm_type
+                        .set_body(&field_types, false);
+                }
+                ast::Item::Enum(declaration) => {
+                    let tag_type = self.context.i32_type().into();
+                    let definition = &self.enums[&declaration.name];
+                    definition.llvm_type.set_body(&[tag_type], false);
+                    for variant in &definition.variants {
+                        let mut field_types = vec![tag_type];
+                        for ty in &variant.fields {
+                            field_types.push(self.llvm_type(ty)?);
+                        }
+                        variant.llvm_type.set_body(&field_types, false);
+                    }
+                }
+                _ => {}
             }
-            super::ast::Expression::StringLiteral(lit) => {
-                return Err("not implemented".into());
+        }
+
+        Ok(())
+    }
+
+    fn declare_struct(&mut self, declaration: &ast::Struct) -> Result<(), String> {
+        for (index, (field, _)) in declaration.fields.iter().enumerate() {
+            if declaration.fields[..index]
+                .iter()
+                .any(|(name, _)| name == field)
+            {
+                return Err(format!(
+                    "field `{}` is declared more than once in struct `{}`",
+                    field, declaration.name
+                ));
             }
         }
 
-        self.builder
-            .build_return(Some(&self.context.i32_type().const_int(0, false)));
+        let llvm_type = self.context.opaque_struct_type(&declaration.name);
+        self.structs.insert(
+            declaration.name.clone(),
+            StructDefinition {
+                llvm_type,
+                fields: declaration.fields.clone(),
+            },
+        );
 
-        Ok(&self.module)
+        Ok(())
+    }
+
+    fn declare_enum(&mut self, declaration: &ast::Enum) -> Result<(), String> {
+        let mut variants = vec![];
+        for (index, variant) in declaration.variants.iter().enumerate() {
+            if declaration.variants[..index]
+                .iter()
+                .any(|other| other.name == variant.name)
+            {
+                return Err(format!(
+                    "variant `{}` is declared more than once in enum `{}`",
+                    variant.name, declaration.name
+                ));
+            }
+            variants.push(VariantDefinition {
+                name: variant.name.clone(),
+                fields: variant.fields.clone(),
+                llvm_type: self
+                    .context
+                    .opaque_struct_type(&format!("{}::{}", declaration.name, variant.name)),
+            });
+        }
+
+        let llvm_type = self.context.opaque_struct_type(&declaration.name);
+        self.enums.insert(
+            declaration.name.clone(),
+            EnumDefinition {
+                llvm_type,
+                variants,
+            },
+        );
+
+        Ok(())
+    }
+
+    fn declare_function(&mut self, function: &ast::Function) -> Result<(), String> {
+        let name = self.qualify(&function.name);
+        let fcn_name = format!("FAANG_{}", name);
+        if self.module.get_function(&fcn_name).is_some() || self.generics.contains_key(&name) {
+            return Err(format!("function `{}` is defined more than once", name));
+        }
+
+        // Generic functions are only compiled when they are called.
+        if !function.type_parameters.is_empty() {
+            self.generics.insert(name, function.clone());
+            return Ok(());
+        }
+
+        let parameter_types = function
+            .parameters
+            .iter()
+            .map(|(_, ty)| self.llvm_type(ty))
+            .collect::<Result<Vec<_>, _>>()?;
+        let fn_type = match &function.return_type {
+            Some(ty) => self.llvm_type(ty)?.fn_type(&parameter_types, false),
+            None => self.context.void_type().fn_type(&parameter_types, false),
+        };
+        self.module.add_function(&fcn_name, fn_type, None);
+        self.signatures.insert(
+            name,
+            Signature {
+                parameters: function
+                    .parameters
+                    .iter()
+                    .map(|(_, ty)| ty.clone())
+                    .collect(),
+                return_type: function.return_type.clone(),
+            },
+        );
+
+        Ok(())
+    }
+
+    fn check_use(&self, declaration: &ast::Use) -> Result<(), String> {
+        if !self.modules.contains_key(&declaration.module) {
+            return Err(format!("cannot find module `{}`", declaration.module));
+        }
+        let name = format!("{}::{}", declaration.module, declaration.name);
+        if !self.signatures.contains_key(&name) && !self.generics.contains_key(&name) {
+            return Err(format!(
+                "cannot find function `{}` in module `{}`",
+                declaration.name, declaration.module
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Qualifies the name of a function of the current module with the name of the module.
+    fn qualify(&self, name: &str) -> String {
+        if self.current_module.is_empty() {
+            name.into()
+        } else {
+            format!("{}::{}", self.current_module, name)
+        }
+    }
+
+    /// Finds the qualified name of a function called by name from the current module. The
+    /// functions of the module come first, then the ones it brings into scope with `use`.
+    fn resolve_function(&self, name: &str) -> String {
+        let qualified = self.qualify(name);
+        if self.signatures.contains_key(&qualified) || self.generics.contains_key(&qualified) {
+            return qualified;
+        }
+
+        self.modules
+            .get(&self.current_module)
+            .and_then(|uses| uses.get(name))
+            .cloned()
+            .unwrap_or_else(|| name.into())
+    }
+
+    /// `utils::helper(args)` parses like a variant of an enum, and is a call of a function of
+    /// the module `utils` if there is no enum with that name.
+    fn module_call<'e>(&self, expression: &'e Expression) -> Option<(String, &'e [Expression])> {
+        match expression {
+            Expression::Variant(module, name, args)
+                if !self.enums.contains_key(module) && self.modules.contains_key(module) =>
+            {
+                Some((format!("{}::{}", module, name), args))
+            }
+            _ => None,
+        }
+    }
+
+    fn compile_function(
+        &mut self,
+        function: &ast::Function,
+        fcn: FunctionValue,
+    ) -> Result<(), String> {
+        let block = self.context.append_basic_block(&fcn, "entry");
+        self.builder.position_at_end(&block);
+        self.function = Some(fcn);
+        self.return_type = function.return_type.as_ref().map(|ty| self.resolve(ty));
+        self.in_main = false;
+
+        let mut parameters = HashMap::new();
+        for ((name, ty), value) in function.parameters.iter().zip(fcn.get_params()) {
+            let ty = self.resolve(ty);
+            let pointer = self.create_entry_block_alloca(&ty, name)?;
+            self.builder.build_store(pointer, value);
+            parameters.insert(name.clone(), Variable { pointer, ty });
+        }
+        self.scopes.push(parameters);
+        let result = self.compile_statements(&function.body);
+        self.scopes.pop();
+        result?;
+
+        self.finish_function(&format!("function `{}`", function.name), &function.body)
+    }
+
+    fn compile_test(&mut self, test: &ast::Test) -> Result<(), String> {
+        let tests = self.tests.as_mut().unwrap();
+        let function = format!("FAANG_test.{}", tests.len());
+        tests.push(TestFunction {
+            name: test.name.clone(),
+            module: self.current_module.clone(),
+            function: function.clone(),
+        });
+
+        let fcn = self.module.add_function(
+            &function,
+            self.context.void_type().fn_type(&[], false),
+            None,
+        );
+        let block = self.context.append_basic_block(&fcn, "entry");
+        self.builder.position_at_end(&block);
+        self.function = Some(fcn);
+        self.return_type = None;
+        self.in_main = false;
+
+        self.compile_statements(&test.body)?;
+        self.finish_function(&format!("test `{}`", test.name), &test.body)
+    }
+
+    /// Terminates the body of a function that does not end in a `return`.
+    fn finish_function(&self, description: &str, body: &[Statement]) -> Result<(), String> {
+        if !self.is_terminated() {
+            match self.return_type {
+                None => {
+                    self.builder.build_return(None);
+                }
+                // The last block is an empty merge block that no branch jumps to.
+                Some(_) if self.always_returns(body) => {
+                    self.builder.build_unreachable();
+                }
+                Some(_) => {
+                    return Err(format!(
+                        "{} does not return a value on every path",
+                        description
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn compile_statements<'s, I>(&mut self, statements: I) -> Result<(), String>
+    where
+        I: IntoIterator<Item = &'s Statement>,
+    {
+        self.scopes.push(HashMap::new());
+        let mut result = Ok(());
+        for statement in statements {
+            // Anything after a `return` is unreachable, and LLVM does not allow
+            // instructions after a block's terminator.
+            if self.is_terminated() {
+                break;
+            }
+            result = self.compile_statement(statement);
+            if result.is_err() {
+                break;
+            }
+        }
+        self.scopes.pop();
+
+        result
+    }
+
+    fn compile_statement(&mut self, statement: &Statement) -> Result<(), String> {
+        let outer_span = std::mem::replace(&mut self.span, statement.span);
+        self.compile_statement_kind(&statement.kind)?;
+        self.span = outer_span;
+
+        Ok(())
+    }
+
+    fn compile_statement_kind(&mut self, kind: &StatementKind) -> Result<(), String> {
+        match kind {
+            StatementKind::Let(name, ty, expression) => {
+                let ty = ty.as_ref().map(|ty| self.resolve(ty));
+                let (value, value_type) = self.compile_expression(expression, ty.as_ref())?;
+                let ty = match ty {
+                    Some(ty) => {
+                        check_type(&ty, &value_type)?;
+                        ty
+                    }
+                    None => value_type,
+                };
+                let pointer = self.create_entry_block_alloca(&ty, name)?;
+                self.builder.build_store(pointer, value);
+                self.scopes
+                    .last_mut()
+                    .unwrap()
+                    .insert(name.clone(), Variable { pointer, ty });
+            }
+            StatementKind::Assignment(place, expression) => {
+                let (pointer, ty) = self.compile_place(place)?;
+                let (value, value_type) = self.compile_expression(expression, Some(&ty))?;
+                check_type(&ty, &value_type)?;
+                self.builder.build_store(pointer, value);
+            }
+            StatementKind::Expression(expression) => {
+                self.compile_expression_statement(expression)?;
+            }
+            StatementKind::Return(expression) => {
+                self.compile_return(expression.as_ref())?;
+            }
+            StatementKind::If(condition, then, otherwise) => {
+                let condition = self.compile_condition(condition)?;
+                let fcn = self.function.unwrap();
+                let then_block = self.context.append_basic_block(&fcn, "then");
+                let else_block = self.context.append_basic_block(&fcn, "else");
+                let merge_block = self.context.append_basic_block(&fcn, "merge");
+                self.builder
+                    .build_conditional_branch(condition, &then_block, &else_block);
+
+                self.builder.position_at_end(&then_block);
+                self.compile_statements(then)?;
+                if !self.is_terminated() {
+                    self.builder.build_unconditional_branch(&merge_block);
+                }
+
+                self.builder.position_at_end(&else_block);
+                self.compile_statements(otherwise)?;
+                if !self.is_terminated() {
+                    self.builder.build_unconditional_branch(&merge_block);
+                }
+
+                self.builder.position_at_end(&merge_block);
+            }
+            StatementKind::While(condition, body) => {
+                let fcn = self.function.unwrap();
+                let condition_block = self.context.append_basic_block(&fcn, "while");
+                let body_block = self.context.append_basic_block(&fcn, "body");
+                let end_block = self.context.append_basic_block(&fcn, "end");
+                self.builder.build_unconditional_branch(&condition_block);
+
+                self.builder.position_at_end(&condition_block);
+                let condition = self.compile_condition(condition)?;
+                self.builder
+                    .build_conditional_branch(condition, &body_block, &end_block);
+
+                self.builder.position_at_end(&body_block);
+                self.compile_statements(body)?;
+                if !self.is_terminated() {
+                    self.builder.build_unconditional_branch(&condition_block);
+                }
+
+                self.builder.position_at_end(&end_block);
+            }
+            StatementKind::Error => return Err(SYNTAX_ERROR.into()),
+        }
+
+        Ok(())
+    }
+
+    /// Compiles an expression whose value is unused, which may be a call to a function
+    /// without a return value or a `match` whose arms have no value.
+    fn compile_expression_statement(&mut self, expression: &Expression) -> Result<(), String> {
+        if let Some((name, args)) = self.module_call(expression) {
+            self.compile_invocation(&name, args, false)?;
+            return Ok(());
+        }
+
+        match expression {
+            Expression::Invocation(name, args) => {
+                self.compile_invocation(name, args, false)?;
+                // Nothing runs after a call that ends the program.
+                if self.ends_program(name) {
+                    self.builder.build_unreachable();
+                }
+            }
+            Expression::Call(callee, args) => {
+                let (closure, ty) = self.compile_expression(callee, None)?;
+                self.compile_closure_call("function", closure, &ty, args, false)?;
+            }
+            Expression::Match(scrutinee, arms) => {
+                self.compile_match(scrutinee, arms, None, false)?;
+            }
+            _ => {
+                self.compile_expression(expression, None)?;
+            }
+        }
+
+        Ok(())
     }
 
-    fn expression_to_basic_value_enum(
+    fn compile_return(&mut self, expression: Option<&Expression>) -> Result<(), String> {
+        match (expression, self.return_type.clone()) {
+            (Some(expression), Some(return_type)) => {
+                let call = match expression {
+                    Expression::Invocation(name, args) => Some((name.clone(), &args[..])),
+                    _ => self.module_call(expression),
+                };
+                let (value, value_type) = match call {
+                    Some((name, args)) => self
+                        .compile_invocation(&name, args, true)?
+                        .ok_or_else(|| format!("function `{}` does not return a value", name))?,
+                    None => self.compile_expression(expression, Some(&return_type))?,
+                };
+                check_type(&return_type, &value_type)?;
+                self.builder.build_return(Some(&value));
+            }
+            (None, None) if self.in_main => {
+                self.builder
+                    .build_return(Some(&self.context.i32_type().const_int(0, false)));
+            }
+            (None, None) => {
+                self.builder.build_return(None);
+            }
+            (Some(_), None) => {
+                return Err("cannot return a value from a function without a return type".into());
+            }
+            (None, Some(return_type)) => {
+                return Err(format!("expected a return value of type `{}`", return_type));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compiles a call. A call in tail position is marked as a tail call, so that LLVM can
+    /// turn it into a jump and deep recursion does not overflow the stack.
+    fn compile_invocation(
+        &mut self,
+        name: &str,
+        args: &[Expression],
+        tail_call: bool,
+    ) -> Result<Option<(BasicValueEnum, Type)>, String> {
+        // A variable holding a function value shadows the functions of the program.
+        if let Ok(variable) = self.lookup(name) {
+            let ty = variable.ty.clone();
+            let closure = self.builder.build_load(variable.pointer, name);
+            return self.compile_closure_call(
+                &format!("function `{}`", name),
+                closure,
+                &ty,
+                args,
+                tail_call,
+            );
+        }
+
+        let name = &self.resolve_function(name);
+        if self.generics.contains_key(name) {
+            return self.compile_generic_invocation(name, args, tail_call);
+        }
+        if self.is_builtin(name, "len") {
+            return Ok(Some((self.compile_len(args)?.into(), Type::Int)));
+        }
+        if self.is_builtin(name, "panic") {
+            self.compile_panic(args)?;
+            return Ok(None);
+        }
+        if self.is_builtin(name, "assert") {
+            self.compile_assert(args)?;
+            return Ok(None);
+        }
+        if self.is_builtin(name, "assert_eq") {
+            self.compile_assert_eq(args)?;
+            return Ok(None);
+        }
+
+        let fcn_name = format!("FAANG_{}", name);
+        let fcn = self
+            .module
+            .get_function(&fcn_name)
+            .ok_or_else(|| format!("cannot find function `{}`", name))?;
+        let signature = self
+            .signatures
+            .get(name)
+            .cloned()
+            .or_else(|| stdlib::signature(name))
+            .ok_or_else(|| format!("cannot find function `{}`", name))?;
+
+        let values =
+            self.compile_arguments(&format!("function `{}`", name), args, &signature.parameters)?;
+
+        Ok(self.build_call(fcn, &values, signature.return_type, tail_call))
+    }
+
+    fn build_call(
         &self,
-        expression: &super::ast::Expression,
-    ) -> Result<BasicValueEnum, String> {
+        fcn: FunctionValue,
+        values: &[BasicValueEnum],
+        return_type: Option<Type>,
+        tail_call: bool,
+    ) -> Option<(BasicValueEnum, Type)> {
+        // Calls to functions without a return value produce no value to name.
+        let call_name = if return_type.is_some() { "val" } else { "" };
+        let call = self.builder.build_call(fcn, values, call_name);
+        call.set_tail_call(tail_call && can_tail_call(values));
+
+        return_type.map(|return_type| {
+            let value = call.try_as_basic_value().left().unwrap();
+            (value, return_type)
+        })
+    }
+
+    fn compile_arguments(
+        &mut self,
+        description: &str,
+        args: &[Expression],
+        parameters: &[Type],
+    ) -> Result<Vec<BasicValueEnum>, String> {
+        if args.len() != parameters.len() {
+            return Err(format!(
+                "{} takes {} argument(s) but {} were supplied",
+                description,
+                parameters.len(),
+                args.len()
+            ));
+        }
+
+        let mut values = vec![];
+        for (arg, parameter_type) in args.iter().zip(parameters) {
+            let (value, value_type) = self.compile_expression(arg, Some(parameter_type))?;
+            check_type(parameter_type, &value_type)?;
+            values.push(value);
+        }
+
+        Ok(values)
+    }
+
+    fn compile_expression(
+        &mut self,
+        expression: &Expression,
+        expected: Option<&Type>,
+    ) -> Result<(BasicValueEnum, Type), String> {
         match expression {
-            super::ast::Expression::Invocation(name, args) => Err("not impelemented".into()),
-            super::ast::Expression::StringLiteral(lit) => {
-                Ok(util::alloc_string(&lit, "str", self.context, self.builder).into())
+            Expression::Invocation(name, args) => self
+                .compile_invocation(name, args, false)?
+                .ok_or_else(|| format!("function `{}` does not return a value", name)),
+            Expression::StringLiteral(lit) => Ok((
+                self.builder
+                    .build_global_string_ptr(lit, "str")
+                    .as_pointer_value()
+                    .into(),
+                Type::String,
+            )),
+            Expression::IntegerLiteral(value) => Ok((
+                self.context
+                    .i64_type()
+                    .const_int(*value as u64, true)
+                    .into(),
+                Type::Int,
+            )),
+            Expression::BooleanLiteral(value) => Ok((
+                self.context
+                    .bool_type()
+                    .const_int(*value as u64, false)
+                    .into(),
+                Type::Bool,
+            )),
+            Expression::Error => Err(SYNTAX_ERROR.into()),
+            Expression::Null => match expected {
+                Some(ty @ Type::Named(name)) if self.structs.contains_key(name) => Ok((
+                    self.llvm_type(ty)?.into_pointer_type().const_null().into(),
+                    ty.clone(),
+                )),
+                _ => Err("cannot infer the type of `null`".into()),
+            },
+            Expression::Variable(name) => match self.lookup(name) {
+                Ok(variable) => Ok((
+                    self.builder.build_load(variable.pointer, name),
+                    variable.ty.clone(),
+                )),
+                Err(_) => self.compile_function_value(name),
+            },
+            Expression::Call(callee, args) => {
+                let (closure, ty) = self.compile_expression(callee, None)?;
+                self.compile_closure_call("function", closure, &ty, args, false)?
+                    .ok_or_else(|| "function does not return a value".into())
+            }
+            Expression::Lambda(lambda) => match expected {
+                Some(Type::Function(parameters, return_type)) => self.compile_lambda(
+                    lambda,
+                    Some(parameters),
+                    Some(return_type.as_ref().map(|ty| &**ty)),
+                ),
+                _ => self.compile_lambda(lambda, None, None),
+            },
+            Expression::FieldAccess(_, field) => {
+                let (pointer, ty) = self.compile_place(expression)?;
+                Ok((self.builder.build_load(pointer, field), ty))
+            }
+            Expression::StructLiteral(name, fields) => self.compile_struct_literal(name, fields),
+            Expression::Array(elements) => self.compile_array(elements, expected),
+            Expression::Index(_, _) => {
+                let (pointer, ty) = self.compile_place(expression)?;
+                Ok((self.builder.build_load(pointer, "element"), ty))
+            }
+            Expression::Variant(enum_name, variant, args) => match self.module_call(expression) {
+                Some((name, args)) => self
+                    .compile_invocation(&name, args, false)?
+                    .ok_or_else(|| format!("function `{}` does not return a value", name)),
+                None => self.compile_variant(enum_name, variant, args),
+            },
+            Expression::Match(scrutinee, arms) => self
+                .compile_match(scrutinee, arms, expected, true)?
+                .ok_or_else(|| "no arm of the `match` produces a value".into()),
+            Expression::Unary(operator, operand) => {
+                let (value, ty) = self.compile_expression(operand, None)?;
+                match (operator, &ty) {
+                    (UnaryOperator::Negate, Type::Int) => Ok((
+                        self.builder
+                            .build_int_neg(value.into_int_value(), "neg")
+                            .into(),
+                        ty,
+                    )),
+                    (UnaryOperator::Not, Type::Bool) => Ok((
+                        self.builder.build_not(value.into_int_value(), "not").into(),
+                        ty,
+                    )),
+                    (UnaryOperator::Negate, _) => {
+                        Err(format!("cannot negate a value of type `{}`", ty))
+                    }
+                    (UnaryOperator::Not, _) => {
+                        Err(format!("cannot apply `!` to a value of type `{}`", ty))
+                    }
+                }
+            }
+            Expression::Binary(lhs, operator, rhs) => self.compile_binary(lhs, *operator, rhs),
+        }
+    }
+
+    fn compile_binary(
+        &mut self,
+        lhs: &Expression,
+        operator: BinaryOperator,
+        rhs: &Expression,
+    ) -> Result<(BasicValueEnum, Type), String> {
+        match operator {
+            BinaryOperator::And | BinaryOperator::Or => {
+                return self.compile_short_circuit(lhs, operator, rhs)
+            }
+            _ => {}
+        }
+
+        // `null` takes its type from the other operand, so it is compiled last.
+        let (lhs, lhs_type, rhs, rhs_type) = match lhs {
+            Expression::Null => {
+                let (rhs, rhs_type) = self.compile_expression(rhs, None)?;
+                let (lhs, lhs_type) = self.compile_expression(lhs, Some(&rhs_type))?;
+                (lhs, lhs_type, rhs, rhs_type)
+            }
+            _ => {
+                let (lhs, lhs_type) = self.compile_expression(lhs, None)?;
+                let (rhs, rhs_type) = self.compile_expression(rhs, Some(&lhs_type))?;
+                (lhs, lhs_type, rhs, rhs_type)
+            }
+        };
+        check_type(&lhs_type, &rhs_type)?;
+
+        match (operator, &lhs_type) {
+            // Structs are compared by identity.
+            (BinaryOperator::Equal, Type::Named(name))
+            | (BinaryOperator::NotEqual, Type::Named(name))
+                if self.structs.contains_key(name) =>
+            {
+                let lhs = self.builder.build_ptr_to_int(
+                    lhs.into_pointer_value(),
+                    self.context.i64_type(),
+                    "lhs",
+                );
+                let rhs = self.builder.build_ptr_to_int(
+                    rhs.into_pointer_value(),
+                    self.context.i64_type(),
+                    "rhs",
+                );
+                Ok((self.compare(operator, lhs, rhs).into(), Type::Bool))
+            }
+            (BinaryOperator::Equal, Type::Int)
+            | (BinaryOperator::Equal, Type::Bool)
+            | (BinaryOperator::NotEqual, Type::Int)
+            | (BinaryOperator::NotEqual, Type::Bool)
+            | (BinaryOperator::LessThan, Type::Int)
+            | (BinaryOperator::LessThanOrEqual, Type::Int)
+            | (BinaryOperator::GreaterThan, Type::Int)
+            | (BinaryOperator::GreaterThanOrEqual, Type::Int) => Ok((
+                self.compare(operator, lhs.into_int_value(), rhs.into_int_value())
+                    .into(),
+                Type::Bool,
+            )),
+            (BinaryOperator::Add, Type::Int) => Ok((
+                self.builder
+                    .build_int_add(lhs.into_int_value(), rhs.into_int_value(), "add")
+                    .into(),
+                Type::Int,
+            )),
+            (BinaryOperator::Subtract, Type::Int) => Ok((
+                self.builder
+                    .build_int_sub(lhs.into_int_value(), rhs.into_int_value(), "sub")
+                    .into(),
+                Type::Int,
+            )),
+            (BinaryOperator::Multiply, Type::Int) => Ok((
+                self.builder
+                    .build_int_mul(lhs.into_int_value(), rhs.into_int_value(), "mul")
+                    .into(),
+                Type::Int,
+            )),
+            (BinaryOperator::Divide, Type::Int) => Ok((
+                self.builder
+                    .build_int_signed_div(lhs.into_int_value(), rhs.into_int_value(), "div")
+                    .into(),
+                Type::Int,
+            )),
+            (BinaryOperator::Remainder, Type::Int) => Ok((
+                self.builder
+                    .build_int_signed_rem(lhs.into_int_value(), rhs.into_int_value(), "rem")
+                    .into(),
+                Type::Int,
+            )),
+            _ => Err(format!(
+                "operator `{}` cannot be applied to values of type `{}`",
+                operator, lhs_type
+            )),
+        }
+    }
+
+    fn compare(&self, operator: BinaryOperator, lhs: IntValue, rhs: IntValue) -> IntValue {
+        let predicate = match operator {
+            BinaryOperator::Equal => IntPredicate::EQ,
+            BinaryOperator::NotEqual => IntPredicate::NE,
+            BinaryOperator::LessThan => IntPredicate::SLT,
+            BinaryOperator::LessThanOrEqual => IntPredicate::SLE,
+            BinaryOperator::GreaterThan => IntPredicate::SGT,
+            BinaryOperator::GreaterThanOrEqual => IntPredicate::SGE,
+            _ => unreachable!("{:?} is not a comparison", operator),
+        };
+        self.builder.build_int_compare(predicate, lhs, rhs, "cmp")
+    }
+
+    fn compile_short_circuit(
+        &mut self,
+        lhs: &Expression,
+        operator: BinaryOperator,
+        rhs: &Expression,
+    ) -> Result<(BasicValueEnum, Type), String> {
+        let fcn = self.function.unwrap();
+        let lhs_value = self.compile_condition(lhs)?;
+        let lhs_block = self.builder.get_insert_block().unwrap();
+        let rhs_block = self.context.append_basic_block(&fcn, "rhs");
+        let merge_block = self.context.append_basic_block(&fcn, "merge");
+        match operator {
+            BinaryOperator::And => {
+                self.builder
+                    .build_conditional_branch(lhs_value, &rhs_block, &merge_block)
+            }
+            _ => self
+                .builder
+                .build_conditional_branch(lhs_value, &merge_block, &rhs_block),
+        };
+
+        self.builder.position_at_end(&rhs_block);
+        let rhs_value = self.compile_condition(rhs)?;
+        let rhs_block = self.builder.get_insert_block().unwrap();
+        self.builder.build_unconditional_branch(&merge_block);
+
+        // The left hand side is only used as the result when it decided the outcome.
+        self.builder.position_at_end(&merge_block);
+        let phi = self.builder.build_phi(self.context.bool_type(), "cond");
+        phi.add_incoming(&[(&lhs_value, &lhs_block), (&rhs_value, &rhs_block)]);
+
+        Ok((phi.as_basic_value(), Type::Bool))
+    }
+
+    fn compile_condition(&mut self, condition: &Expression) -> Result<IntValue, String> {
+        let (value, ty) = self.compile_expression(condition, Some(&Type::Bool))?;
+        check_type(&Type::Bool, &ty)?;
+        Ok(value.into_int_value())
+    }
+
+    /// Compiles an expression that can be assigned to, returning a pointer to its storage.
+    fn compile_place(&mut self, place: &Expression) -> Result<(PointerValue, Type), String> {
+        match place {
+            Expression::Variable(name) => {
+                let variable = self.lookup(name)?;
+                Ok((variable.pointer, variable.ty.clone()))
+            }
+            Expression::FieldAccess(object, field) => {
+                let (value, ty) = self.compile_expression(object, None)?;
+                let definition = match &ty {
+                    Type::Named(name) => self.structs.get(name),
+                    _ => None,
+                }
+                .ok_or_else(|| format!("type `{}` has no field `{}`", ty, field))?;
+                let index = definition
+                    .fields
+                    .iter()
+                    .position(|(name, _)| name == field)
+                    .ok_or_else(|| format!("type `{}` has no field `{}`", ty, field))?;
+                let field_type = definition.fields[index].1.clone();
+                let pointer = unsafe {
+                    self.builder
+                        .build_struct_gep(value.into_pointer_value(), index as u32, field)
+                };
+
+                Ok((pointer, field_type))
+            }
+            Expression::Index(array, index) => self.compile_element_place(array, index),
+            _ => Err("invalid left hand side of assignment".into()),
+        }
+    }
+
+    fn compile_struct_literal(
+        &mut self,
+        name: &str,
+        fields: &[(String, Expression)],
+    ) -> Result<(BasicValueEnum, Type), String> {
+        let (llvm_type, declared_fields) = match self.structs.get(name) {
+            Some(definition) => (definition.llvm_type, definition.fields.clone()),
+            None => return Err(format!("cannot find struct `{}`", name)),
+        };
+
+        for (index, (field, _)) in fields.iter().enumerate() {
+            if !declared_fields.iter().any(|(name, _)| name == field) {
+                return Err(format!("struct `{}` has no field `{}`", name, field));
+            }
+            if fields[..index].iter().any(|(name, _)| name == field) {
+                return Err(format!("field `{}` is specified more than once", field));
+            }
+        }
+        for (field, _) in &declared_fields {
+            if !fields.iter().any(|(name, _)| name == field) {
+                return Err(format!("missing field `{}` in `{}`", field, name));
+            }
+        }
+
+        let pointer = self.build_malloc(llvm_type, name);
+        for (field, expression) in fields {
+            let index = declared_fields
+                .iter()
+                .position(|(name, _)| name == field)
+                .unwrap();
+            let field_type = &declared_fields[index].1;
+            let (value, value_type) = self.compile_expression(expression, Some(field_type))?;
+            check_type(field_type, &value_type)?;
+            let field_pointer =
+                unsafe { self.builder.build_struct_gep(pointer, index as u32, field) };
+            self.builder.build_store(field_pointer, value);
+        }
+
+        Ok((pointer.into(), Type::Named(name.into())))
+    }
+
+    fn compile_variant(
+        &mut self,
+        enum_name: &str,
+        variant: &str,
+        args: &[Expression],
+    ) -> Result<(BasicValueEnum, Type), String> {
+        let definition = self
+            .enums
+            .get(enum_name)
+            .ok_or_else(|| format!("cannot find enum `{}`", enum_name))?;
+        let tag = definition
+            .variants
+            .iter()
+            .position(|definition| definition.name == variant)
+            .ok_or_else(|| format!("enum `{}` has no variant `{}`", enum_name, variant))?;
+        let header_type = definition.llvm_type;
+        let variant_type = definition.variants[tag].llvm_type;
+        let field_types = definition.variants[tag].fields.clone();
+
+        if args.len() != field_types.len() {
+            return Err(format!(
+                "variant `{}::{}` takes {} field(s) but {} were supplied",
+                enum_name,
+                variant,
+                field_types.len(),
+                args.len()
+            ));
+        }
+
+        let pointer = self.build_malloc(variant_type, variant);
+        let tag_pointer = unsafe { self.builder.build_struct_gep(pointer, 0, "tag") };
+        self.builder.build_store(
+            tag_pointer,
+            self.context.i32_type().const_int(tag as u64, false),
+        );
+        for (index, (arg, field_type)) in args.iter().zip(&field_types).enumerate() {
+            let (value, value_type) = self.compile_expression(arg, Some(field_type))?;
+            check_type(field_type, &value_type)?;
+            let field_pointer = unsafe {
+                self.builder
+                    .build_struct_gep(pointer, index as u32 + 1, "field")
+            };
+            self.builder.build_store(field_pointer, value);
+        }
+
+        let pointer = self.builder.build_pointer_cast(
+            pointer,
+            header_type.ptr_type(AddressSpace::Generic),
+            enum_name,
+        );
+        Ok((pointer.into(), Type::Named(enum_name.into())))
+    }
+
+    /// Compiles a `match`, returning its value if `want_value` is set.
+    ///
+    /// The arms are tried in order. Each arm tests its pattern and jumps to the next arm
+    /// on the first test that fails.
+    fn compile_match(
+        &mut self,
+        scrutinee: &Expression,
+        arms: &[MatchArm],
+        expected: Option<&Type>,
+        want_value: bool,
+    ) -> Result<Option<(BasicValueEnum, Type)>, String> {
+        let (value, ty) = self.compile_expression(scrutinee, None)?;
+        for arm in arms {
+            patterns::check(&arm.pattern, &ty, &self.enums)?;
+        }
+        let patterns = arms.iter().map(|arm| &arm.pattern).collect::<Vec<_>>();
+        if let Some(witness) = patterns::missing(&patterns, &ty, &self.enums) {
+            return Err(format!(
+                "non-exhaustive patterns: `{}` not covered",
+                witness
+            ));
+        }
+
+        let fcn = self.function.unwrap();
+        let merge_block = self.context.append_basic_block(&fcn, "match_end");
+        let mut result_type = expected.cloned();
+        let mut incoming = vec![];
+        for arm in arms {
+            let next_block = self.context.append_basic_block(&fcn, "next_arm");
+            self.scopes.push(HashMap::new());
+            let result = self.compile_arm(
+                arm,
+                value,
+                &ty,
+                &next_block,
+                result_type.as_ref(),
+                want_value,
+            );
+            self.scopes.pop();
+
+            if let Some((value, value_type)) = result? {
+                match &result_type {
+                    Some(result_type) => check_type(result_type, &value_type)?,
+                    None => result_type = Some(value_type),
+                }
+                incoming.push((value, self.builder.get_insert_block().unwrap()));
+            }
+            if !self.is_terminated() {
+                self.builder.build_unconditional_branch(&merge_block);
+            }
+            self.builder.position_at_end(&next_block);
+        }
+        // The patterns are exhaustive, so the last arm always matches.
+        self.builder.build_unreachable();
+
+        self.builder.position_at_end(&merge_block);
+        if !want_value || incoming.is_empty() {
+            return Ok(None);
+        }
+        let result_type = result_type.unwrap();
+        let phi = self
+            .builder
+            .build_phi(self.llvm_type(&result_type)?, "match");
+        let incoming = incoming
+            .iter()
+            .map(|(value, block)| (value as &dyn BasicValue, block))
+            .collect::<Vec<_>>();
+        phi.add_incoming(&incoming);
+
+        Ok(Some((phi.as_basic_value(), result_type)))
+    }
+
+    fn compile_arm(
+        &mut self,
+        arm: &MatchArm,
+        value: BasicValueEnum,
+        ty: &Type,
+        fail: &BasicBlock,
+        expected: Option<&Type>,
+        want_value: bool,
+    ) -> Result<Option<(BasicValueEnum, Type)>, String> {
+        self.compile_pattern(&arm.pattern, value, ty, fail)?;
+        match &arm.body {
+            MatchArmBody::Expression(expression) if want_value => {
+                self.compile_expression(expression, expected).map(Some)
+            }
+            MatchArmBody::Expression(expression) => {
+                self.compile_expression_statement(expression)?;
+                Ok(None)
+            }
+            MatchArmBody::Block(statements) => {
+                self.compile_statements(statements)?;
+                if want_value && !self.is_terminated() {
+                    return Err(
+                        "a block arm of a `match` used as a value must return from the function"
+                            .into(),
+                    );
+                }
+                Ok(None)
+            }
+        }
+    }
+
+    /// Emits the tests for a pattern, branching to `fail` if the value does not match,
+    /// and declares its bindings in the current scope.
+    fn compile_pattern(
+        &mut self,
+        pattern: &Pattern,
+        value: BasicValueEnum,
+        ty: &Type,
+        fail: &BasicBlock,
+    ) -> Result<(), String> {
+        match pattern {
+            Pattern::Wildcard => {}
+            Pattern::Binding(name) => {
+                if self.scopes.last().unwrap().contains_key(name) {
+                    return Err(format!(
+                        "identifier `{}` is bound more than once in the same pattern",
+                        name
+                    ));
+                }
+                let pointer = self.create_entry_block_alloca(ty, name)?;
+                self.builder.build_store(pointer, value);
+                self.scopes.last_mut().unwrap().insert(
+                    name.clone(),
+                    Variable {
+                        pointer,
+                        ty: ty.clone(),
+                    },
+                );
+            }
+            Pattern::IntegerLiteral(literal) => {
+                let literal = self.context.i64_type().const_int(*literal as u64, true);
+                let condition =
+                    self.compare(BinaryOperator::Equal, value.into_int_value(), literal);
+                self.branch_on_match(condition, fail);
+            }
+            Pattern::BooleanLiteral(literal) => {
+                let literal = self.context.bool_type().const_int(*literal as u64, false);
+                let condition =
+                    self.compare(BinaryOperator::Equal, value.into_int_value(), literal);
+                self.branch_on_match(condition, fail);
+            }
+            Pattern::Variant(enum_name, variant, fields) => {
+                let definition = &self.enums[enum_name];
+                let tag = definition
+                    .variants
+                    .iter()
+                    .position(|definition| &definition.name == variant)
+                    .unwrap();
+                let variant_type = definition.variants[tag].llvm_type;
+                let field_types = definition.variants[tag].fields.clone();
+
+                let tag_pointer = unsafe {
+                    self.builder
+                        .build_struct_gep(value.into_pointer_value(), 0, "tag")
+                };
+                let actual_tag = self.builder.build_load(tag_pointer, "tag").into_int_value();
+                let expected_tag = self.context.i32_type().const_int(tag as u64, false);
+                let condition = self.compare(BinaryOperator::Equal, actual_tag, expected_tag);
+                self.branch_on_match(condition, fail);
+
+                let pointer = self.builder.build_pointer_cast(
+                    value.into_pointer_value(),
+                    variant_type.ptr_type(AddressSpace::Generic),
+                    variant,
+                );
+                for (index, (field, field_type)) in fields.iter().zip(&field_types).enumerate() {
+                    if *field == Pattern::Wildcard {
+                        continue;
+                    }
+                    let field_pointer = unsafe {
+                        self.builder
+                            .build_struct_gep(pointer, index as u32 + 1, "field")
+                    };
+                    let field_value = self.builder.build_load(field_pointer, "field");
+                    self.compile_pattern(field, field_value, field_type, fail)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Continues in a new block if `condition` holds, and jumps to `fail` otherwise.
+    fn branch_on_match(&self, condition: IntValue, fail: &BasicBlock) {
+        let matched_block = self
+            .context
+            .append_basic_block(&self.function.unwrap(), "matched");
+        self.builder
+            .build_conditional_branch(condition, &matched_block, fail);
+        self.builder.position_at_end(&matched_block);
+    }
+
+    fn build_malloc(&self, ty: StructType, name: &str) -> PointerValue {
+        let size = ty.size_of().expect("struct bodies are set before use");
+        let memory = self
+            .builder
+            .build_call(
+                self.module
+                    .get_function(external::stdlib::MALLOC)
+                    .expect("malloc should be declared"),
+                &[size.into()],
+                name,
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap();
+
+        self.builder.build_pointer_cast(
+            memory.into_pointer_value(),
+            ty.ptr_type(AddressSpace::Generic),
+            name,
+        )
+    }
+
+    fn llvm_type(&self, ty: &Type) -> Result<BasicTypeEnum, String> {
+        match ty {
+            Type::Int => Ok(self.context.i64_type().into()),
+            Type::Bool => Ok(self.context.bool_type().into()),
+            Type::String | Type::File => Ok(self
+                .context
+                .i8_type()
+                .ptr_type(AddressSpace::Generic)
+                .into()),
+            Type::Named(name) => self
+                .structs
+                .get(name)
+                .map(|definition| definition.llvm_type)
+                .or_else(|| self.enums.get(name).map(|definition| definition.llvm_type))
+                .map(|llvm_type| llvm_type.ptr_type(AddressSpace::Generic).into())
+                .ok_or_else(|| format!("cannot find type `{}`", name)),
+            Type::Array(element_type) => Ok(self
+                .array_type(element_type)?
+                .ptr_type(AddressSpace::Generic)
+                .into()),
+            Type::Function(parameters, return_type) => {
+                let fn_type = self.closure_fn_type(
+                    parameters,
+                    return_type.as_ref().map(|return_type| &**return_type),
+                )?;
+                Ok(self
+                    .context
+                    .struct_type(
+                        &[
+                            fn_type.ptr_type(AddressSpace::Generic).into(),
+                            self.context
+                                .i8_type()
+                                .ptr_type(AddressSpace::Generic)
+                                .into(),
+                        ],
+                        false,
+                    )
+                    .into())
             }
         }
     }
+
+    /// Locals are allocated in the entry block so that loops do not grow the stack.
+    fn create_entry_block_alloca(&self, ty: &Type, name: &str) -> Result<PointerValue, String> {
+        let builder = self.context.create_builder();
+        let entry = self.function.unwrap().get_first_basic_block().unwrap();
+        match entry.get_first_instruction() {
+            Some(instruction) => builder.position_before(&instruction),
+            None => builder.position_at_end(&entry),
+        }
+
+        Ok(builder.build_alloca(self.llvm_type(ty)?, name))
+    }
+
+    /// Whether a resolved function name refers to a builtin that the compiler handles itself,
+    /// rather than a function of the program with the same name.
+    fn is_builtin(&self, name: &str, builtin: &str) -> bool {
+        name == builtin && !self.signatures.contains_key(name)
+    }
+
+    /// Whether calling a function by name from the current module calls the runtime's
+    /// `panic` or `exit`, rather than a function value or a function of the program.
+    fn ends_program(&self, name: &str) -> bool {
+        if self.lookup(name).is_ok() {
+            return false;
+        }
+        let name = &self.resolve_function(name);
+        self.is_builtin(name, "panic") || self.is_builtin(name, "exit")
+    }
+
+    /// Whether every path through the statements ends in a `return` or in a call that ends
+    /// the program.
+    fn always_returns(&self, statements: &[Statement]) -> bool {
+        statements.iter().any(|statement| match &statement.kind {
+            StatementKind::Return(_) => true,
+            StatementKind::Expression(Expression::Invocation(name, _)) => self.ends_program(name),
+            StatementKind::If(_, then, otherwise) => {
+                self.always_returns(then) && self.always_returns(otherwise)
+            }
+            StatementKind::Expression(Expression::Match(_, arms)) => {
+                arms.iter().all(|arm| match &arm.body {
+                    MatchArmBody::Block(statements) => self.always_returns(statements),
+                    MatchArmBody::Expression(_) => false,
+                })
+            }
+            _ => false,
+        })
+    }
+
+    fn lookup(&self, name: &str) -> Result<&Variable, String> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .ok_or_else(|| format!("cannot find value `{}` in this scope", name))
+    }
+
+    fn is_terminated(&self) -> bool {
+        self.builder
+            .get_insert_block()
+            .and_then(|block| block.get_terminator())
+            .is_some()
+    }
+}
+
+fn check_type(expected: &Type, actual: &Type) -> Result<(), String> {
+    if expected == actual {
+        Ok(())
+    } else {
+        Err(format!(
+            "mismatched types: expected `{}`, found `{}`",
+            expected, actual
+        ))
+    }
+}
+
+/// Whether a call with the arguments may be marked as a tail call, which LLVM assumes does
+/// not access the frame of the caller.
+fn can_tail_call(values: &[BasicValueEnum]) -> bool {
+    values.iter().all(|value| match value {
+        BasicValueEnum::PointerValue(pointer) => !may_point_into_frame(*pointer),
+        _ => true,
+    })
+}
+
+/// Whether a pointer may point into the frame of the function it is used in, tracing it back
+/// through casts and element pointers to the object it points into. Only the locals from
+/// `create_entry_block_alloca` are in the frame, and their addresses are never stored or
+/// passed to a call, so pointers that are loaded, extracted from a value or returned by a
+/// call point elsewhere. Any other pointer might not.
+fn may_point_into_frame(pointer: PointerValue) -> bool {
+    let instruction = match pointer.as_instruction_value() {
+        // Globals, constants and parameters.
+        None => return false,
+        Some(instruction) => instruction,
+    };
+    match instruction.get_opcode() {
+        InstructionOpcode::Alloca => true,
+        InstructionOpcode::BitCast | InstructionOpcode::GetElementPtr => {
+            let object = instruction
+                .get_operand(0)
+                .and_then(|operand| operand.left());
+            match object {
+                Some(BasicValueEnum::PointerValue(object)) => may_point_into_frame(object),
+                _ => true,
+            }
+        }
+        InstructionOpcode::Load | InstructionOpcode::ExtractValue | InstructionOpcode::Call => {
+            false
+        }
+        _ => true,
+    }
 }
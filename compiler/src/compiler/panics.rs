@@ -0,0 +1,223 @@
+//! Panics, which end the program with a message and the place in the source where they
+//! happened.
+use inkwell::{
+    values::{BasicValueEnum, PointerValue},
+    IntPredicate,
+};
+
+use super::{check_type, external, stdlib, Compiler};
+use crate::ast::{Expression, Type};
+
+impl<'a> Compiler<'a> {
+    /// Compiles `panic(message)`.
+    pub(super) fn compile_panic(&mut self, args: &[Expression]) -> Result<(), String> {
+        let message = match args {
+            [message] => message,
+            _ => {
+                return Err(format!(
+                    "function `panic` takes 1 argument(s) but {} were supplied",
+                    args.len()
+                ))
+            }
+        };
+        let message = self.compile_message(message)?;
+        self.build_panic(message);
+
+        Ok(())
+    }
+
+    /// Compiles `assert(condition, message)`, which panics if the condition is false.
+    pub(super) fn compile_assert(&mut self, args: &[Expression]) -> Result<(), String> {
+        let (condition, message) = match args {
+            [condition, message] => (condition, message),
+            _ => {
+                return Err(format!(
+                    "function `assert` takes 2 argument(s) but {} were supplied",
+                    args.len()
+                ))
+            }
+        };
+        let condition = self.compile_condition(condition)?;
+
+        let fcn = self.function.unwrap();
+        let fail_block = self.context.append_basic_block(&fcn, "assert_failed");
+        let pass_block = self.context.append_basic_block(&fcn, "assert_passed");
+        self.builder
+            .build_conditional_branch(condition, &pass_block, &fail_block);
+
+        // The message is only evaluated when the assertion fails.
+        self.builder.position_at_end(&fail_block);
+        let message = self.compile_message(message)?;
+        self.build_panic(message);
+        self.builder.build_unreachable();
+
+        self.builder.position_at_end(&pass_block);
+        Ok(())
+    }
+
+    /// Compiles `assert_eq(left, right)`, which panics with both values if they differ.
+    /// Integers, booleans and strings can be compared.
+    pub(super) fn compile_assert_eq(&mut self, args: &[Expression]) -> Result<(), String> {
+        let (left, right) = match args {
+            [left, right] => (left, right),
+            _ => {
+                return Err(format!(
+                    "function `assert_eq` takes 2 argument(s) but {} were supplied",
+                    args.len()
+                ))
+            }
+        };
+        let (left, ty) = self.compile_expression(left, None)?;
+        let (right, right_type) = self.compile_expression(right, Some(&ty))?;
+        check_type(&ty, &right_type)?;
+
+        let (equal, format) = match ty {
+            Type::Int | Type::Bool => (
+                self.builder.build_int_compare(
+                    IntPredicate::EQ,
+                    left.into_int_value(),
+                    right.into_int_value(),
+                    "equal",
+                ),
+                if ty == Type::Int { "%ld" } else { "%s" },
+            ),
+            Type::String => {
+                let order = self
+                    .builder
+                    .build_call(
+                        self.module
+                            .get_function(external::string::STRCMP)
+                            .expect("strcmp should be declared"),
+                        &[left, right],
+                        "order",
+                    )
+                    .try_as_basic_value()
+                    .left()
+                    .unwrap()
+                    .into_int_value();
+                (
+                    self.builder.build_int_compare(
+                        IntPredicate::EQ,
+                        order,
+                        self.context.i32_type().const_zero(),
+                        "equal",
+                    ),
+                    "\"%s\"",
+                )
+            }
+            ty => {
+                return Err(format!(
+                    "cannot compare values of type `{}` with `assert_eq`",
+                    ty
+                ))
+            }
+        };
+
+        let fcn = self.function.unwrap();
+        let fail_block = self.context.append_basic_block(&fcn, "assert_failed");
+        let pass_block = self.context.append_basic_block(&fcn, "assert_passed");
+        self.builder
+            .build_conditional_branch(equal, &pass_block, &fail_block);
+
+        self.builder.position_at_end(&fail_block);
+        let (left, right) = match ty {
+            Type::Bool => (self.bool_name(left), self.bool_name(right)),
+            _ => (left, right),
+        };
+        let format = self.builder.build_global_string_ptr(
+            &format!(
+                "panicked at 'assertion failed: `left == right`\n  left: {}\n right: {}', %s\n",
+                format, format
+            ),
+            "format",
+        );
+        let location = self
+            .builder
+            .build_global_string_ptr(&self.location(), "location");
+        let stderr = stdlib::load_output(
+            self.module,
+            self.builder,
+            stdlib::STDERR,
+            external::stdio::STDERR,
+        );
+        self.builder.build_call(
+            self.module
+                .get_function(external::stdio::FPRINTF)
+                .expect("fprintf should be declared"),
+            &[
+                stderr.into(),
+                format.as_pointer_value().into(),
+                left,
+                right,
+                location.as_pointer_value().into(),
+            ],
+            external::stdio::FPRINTF,
+        );
+        self.builder.build_call(
+            self.module
+                .get_function(external::stdlib::EXIT)
+                .expect("exit should be declared"),
+            &[self
+                .context
+                .i32_type()
+                .const_int(stdlib::PANIC_STATUS, false)
+                .into()],
+            "",
+        );
+        self.builder.build_unreachable();
+
+        self.builder.position_at_end(&pass_block);
+        Ok(())
+    }
+
+    /// `"true"` or `"false"`, for printing a boolean.
+    fn bool_name(&self, value: BasicValueEnum) -> BasicValueEnum {
+        let true_name = self.builder.build_global_string_ptr("true", "true");
+        let false_name = self.builder.build_global_string_ptr("false", "false");
+        self.builder.build_select(
+            value.into_int_value(),
+            true_name.as_pointer_value(),
+            false_name.as_pointer_value(),
+            "bool",
+        )
+    }
+
+    fn compile_message(&mut self, message: &Expression) -> Result<PointerValue, String> {
+        let (message, ty) = self.compile_expression(message, Some(&Type::String))?;
+        check_type(&Type::String, &ty)?;
+        Ok(message.into_pointer_value())
+    }
+
+    fn build_panic(&self, message: PointerValue) {
+        let location = self
+            .builder
+            .build_global_string_ptr(&self.location(), "location");
+        self.builder.build_call(
+            self.module
+                .get_function(stdlib::PANIC)
+                .expect("panic should be declared"),
+            &[message.into(), location.as_pointer_value().into()],
+            "",
+        );
+    }
+
+    /// Where the statement being compiled is, as `file:line:column`.
+    fn location(&self) -> String {
+        let source = match self.sources.get(&self.current_module) {
+            Some(source) => source,
+            None if self.current_module.is_empty() => return "<main>".into(),
+            None => return format!("<module {}>", self.current_module),
+        };
+        let offset = self.span.start().to_usize();
+        let line = match source.line_starts.binary_search(&offset) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        };
+        format!(
+            "{}:{}:{}",
+            source.name,
+            line + 1,
+            offset - source.line_starts[line] + 1
+        )
+    }
+}
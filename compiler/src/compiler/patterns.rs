@@ -0,0 +1,231 @@
+//! Type and exhaustiveness checking of `match` patterns.
+//!
+//! Exhaustiveness is decided with the usefulness algorithm from Maranget's
+//! "Warnings for pattern matching", which also produces an example of a value
+//! that is not covered.
+use std::collections::HashMap;
+
+use super::{check_type, EnumDefinition};
+use crate::ast::{Pattern, Type};
+
+/// Checks that a pattern can match values of the given type.
+pub fn check(
+    pattern: &Pattern,
+    ty: &Type,
+    enums: &HashMap<String, EnumDefinition>,
+) -> Result<(), String> {
+    match pattern {
+        Pattern::Wildcard | Pattern::Binding(_) => Ok(()),
+        Pattern::IntegerLiteral(_) => check_type(ty, &Type::Int),
+        Pattern::BooleanLiteral(_) => check_type(ty, &Type::Bool),
+        Pattern::Variant(enum_name, variant, fields) => {
+            check_type(ty, &Type::Named(enum_name.clone()))?;
+            let definition = enums
+                .get(enum_name)
+                .ok_or_else(|| format!("cannot find enum `{}`", enum_name))?;
+            let variant_definition = definition
+                .variants
+                .iter()
+                .find(|definition| &definition.name == variant)
+                .ok_or_else(|| format!("enum `{}` has no variant `{}`", enum_name, variant))?;
+            if fields.len() != variant_definition.fields.len() {
+                return Err(format!(
+                    "variant `{}::{}` has {} field(s) but the pattern has {}",
+                    enum_name,
+                    variant,
+                    variant_definition.fields.len(),
+                    fields.len()
+                ));
+            }
+
+            fields
+                .iter()
+                .zip(&variant_definition.fields)
+                .try_for_each(|(field, field_type)| check(field, field_type, enums))
+        }
+    }
+}
+
+/// Returns a pattern for values of the given type that none of the patterns match.
+///
+/// The patterns must have been checked against the type first.
+pub fn missing(
+    patterns: &[&Pattern],
+    ty: &Type,
+    enums: &HashMap<String, EnumDefinition>,
+) -> Option<Pattern> {
+    let rows = patterns
+        .iter()
+        .map(|pattern| vec![(*pattern).clone()])
+        .collect();
+    witness(rows, std::slice::from_ref(ty), enums).map(|mut witness| witness.remove(0))
+}
+
+#[derive(PartialEq)]
+enum Constructor {
+    Bool(bool),
+    Variant(String),
+}
+
+/// Finds values for a row of the given types that none of the rows match.
+fn witness(
+    rows: Vec<Vec<Pattern>>,
+    types: &[Type],
+    enums: &HashMap<String, EnumDefinition>,
+) -> Option<Vec<Pattern>> {
+    if types.is_empty() {
+        return if rows.is_empty() { Some(vec![]) } else { None };
+    }
+
+    let constructors = constructors(&types[0], enums);
+    let used = rows
+        .iter()
+        .filter_map(|row| constructor(&row[0]))
+        .collect::<Vec<_>>();
+
+    if let Some(constructors) = &constructors {
+        if constructors
+            .iter()
+            .all(|(constructor, _)| used.contains(constructor))
+        {
+            // Every constructor appears, so a missing value must be missing for one of them.
+            for (constructor, field_types) in constructors {
+                let mut specialized_types = field_types.clone();
+                specialized_types.extend_from_slice(&types[1..]);
+                let rows = specialize(&rows, constructor, field_types.len());
+                if let Some(mut witness) = witness(rows, &specialized_types, enums) {
+                    let rest = witness.split_off(field_types.len());
+                    let mut result = vec![build(&types[0], constructor, witness)];
+                    result.extend(rest);
+                    return Some(result);
+                }
+            }
+            return None;
+        }
+    }
+
+    // Otherwise any value not built by a constructor in the first column is only matched by
+    // the rows starting with a wildcard.
+    let rows = rows
+        .into_iter()
+        .filter(|row| is_wildcard(&row[0]))
+        .map(|row| row[1..].to_vec())
+        .collect();
+    let rest = witness(rows, &types[1..], enums)?;
+    let head = constructors
+        .and_then(|constructors| {
+            constructors
+                .into_iter()
+                .find(|(constructor, _)| !used.contains(constructor))
+        })
+        .map(|(constructor, field_types)| {
+            build(
+                &types[0],
+                &constructor,
+                vec![Pattern::Wildcard; field_types.len()],
+            )
+        })
+        .unwrap_or(Pattern::Wildcard);
+    let mut result = vec![head];
+    result.extend(rest);
+    Some(result)
+}
+
+/// The constructors of a type, with the types of their fields, if there are finitely many.
+fn constructors(
+    ty: &Type,
+    enums: &HashMap<String, EnumDefinition>,
+) -> Option<Vec<(Constructor, Vec<Type>)>> {
+    match ty {
+        Type::Bool => Some(vec![
+            (Constructor::Bool(true), vec![]),
+            (Constructor::Bool(false), vec![]),
+        ]),
+        Type::Named(name) => enums.get(name).map(|definition| {
+            definition
+                .variants
+                .iter()
+                .map(|variant| {
+                    (
+                        Constructor::Variant(variant.name.clone()),
+                        variant.fields.clone(),
+                    )
+                })
+                .collect()
+        }),
+        _ => None,
+    }
+}
+
+fn constructor(pattern: &Pattern) -> Option<Constructor> {
+    match pattern {
+        Pattern::BooleanLiteral(value) => Some(Constructor::Bool(*value)),
+        Pattern::Variant(_, variant, _) => Some(Constructor::Variant(variant.clone())),
+        _ => None,
+    }
+}
+
+fn is_wildcard(pattern: &Pattern) -> bool {
+    matches!(pattern, Pattern::Wildcard | Pattern::Binding(_))
+}
+
+/// Keeps the rows that can match the constructor, replacing their first pattern with
+/// the patterns for its fields.
+fn specialize(rows: &[Vec<Pattern>], constructor: &Constructor, arity: usize) -> Vec<Vec<Pattern>> {
+    rows.iter()
+        .filter_map(|row| {
+            let mut fields = match &row[0] {
+                pattern if is_wildcard(pattern) => vec![Pattern::Wildcard; arity],
+                Pattern::Variant(_, variant, fields)
+                    if *constructor == Constructor::Variant(variant.clone()) =>
+                {
+                    fields.clone()
+                }
+                Pattern::BooleanLiteral(value) if *constructor == Constructor::Bool(*value) => {
+                    vec![]
+                }
+                _ => return None,
+            };
+            fields.extend_from_slice(&row[1..]);
+            Some(fields)
+        })
+        .collect()
+}
+
+fn build(ty: &Type, constructor: &Constructor, fields: Vec<Pattern>) -> Pattern {
+    match (ty, constructor) {
+        (_, Constructor::Bool(value)) => Pattern::BooleanLiteral(*value),
+        (Type::Named(enum_name), Constructor::Variant(variant)) => {
+            Pattern::Variant(enum_name.clone(), variant.clone(), fields)
+        }
+        _ => unreachable!("variants only construct enums"),
+    }
+}
+
+/// The variables bound by a pattern for values of the given type, with their types.
+pub fn bindings(
+    pattern: &Pattern,
+    ty: &Type,
+    enums: &HashMap<String, EnumDefinition>,
+) -> Vec<(String, Type)> {
+    match pattern {
+        Pattern::Binding(name) => vec![(name.clone(), ty.clone())],
+        Pattern::Variant(enum_name, variant, fields) => enums
+            .get(enum_name)
+            .and_then(|definition| {
+                definition
+                    .variants
+                    .iter()
+                    .find(|definition| &definition.name == variant)
+            })
+            .map(|definition| {
+                fields
+                    .iter()
+                    .zip(&definition.fields)
+                    .flat_map(|(field, field_type)| bindings(field, field_type, enums))
+                    .collect()
+            })
+            .unwrap_or_default(),
+        _ => vec![],
+    }
+}
@@ -1,22 +1,185 @@
 pub static PRINTLN: &str = "FAANG_println";
+pub static ARGS: &str = "FAANG_args";
+pub static READ_LINE: &str = "FAANG_read_line";
+pub static READ_INT: &str = "FAANG_read_int";
+pub static READ_ALL: &str = "FAANG_read_all";
+pub static OPEN: &str = "FAANG_open";
+pub static WRITE: &str = "FAANG_write";
+pub static READ_TO_STRING: &str = "FAANG_read_to_string";
+pub static CLOSE: &str = "FAANG_close";
+pub static EXIT: &str = "FAANG_exit";
+pub static PANIC: &str = "FAANG_panic";
+/// The exit status of a program that panics.
+pub const PANIC_STATUS: u64 = 101;
+/// The arguments of `main`, which it stores for `args`.
+pub static ARGC: &str = "FAANG_argc";
+pub static ARGV: &str = "FAANG_argv";
+/// The streams that the output of a program is written to, see `output::Sink`.
+pub static STDOUT: &str = "FAANG_stdout";
+pub static STDERR: &str = "FAANG_stderr";
 
-use super::util;
-use inkwell::{builder::Builder, context::Context, module::Module, AddressSpace};
+use super::external::{self, stdio};
+use super::Signature;
+use crate::ast::Type;
+use inkwell::{
+    builder::Builder,
+    context::Context,
+    module::{Linkage, Module},
+    values::{FunctionValue, IntValue, PointerValue},
+    AddressSpace, IntPredicate,
+};
 
 pub enum Features {
+    /// Requires `stdout` and `stderr`.
+    OUTPUT,
+    /// Requires `OUTPUT` and `fprintf`.
     PRINTLN,
+    /// Requires `malloc`.
+    ARGS,
+    /// Requires `getline` and `stdin`.
+    READLINE,
+    /// Requires `scanf`.
+    READINT,
+    /// Requires `getdelim` and `stdin`.
+    READALL,
+    /// Requires `fopen`, `perror` and `exit`.
+    OPEN,
+    /// Requires `fprintf`.
+    WRITE,
+    /// Requires `getdelim`.
+    READTOSTRING,
+    /// Requires `fclose`.
+    CLOSE,
+    /// Requires `exit`.
+    EXIT,
+    /// Requires `OUTPUT`, `fprintf` and `exit`.
+    PANIC,
 }
 
 pub fn add(features: &[Features], context: &Context, module: &Module, builder: &Builder) {
     for feature in features {
         match feature {
+            Features::OUTPUT => {
+                add_output(context, module);
+            }
             Features::PRINTLN => {
                 add_println(context, module, builder);
             }
+            Features::ARGS => {
+                add_args(context, module, builder);
+            }
+            Features::READLINE => {
+                add_read_line(context, module, builder);
+            }
+            Features::READINT => {
+                add_read_int(context, module, builder);
+            }
+            Features::READALL => {
+                add_read_all(context, module, builder);
+            }
+            Features::OPEN => {
+                add_open(context, module, builder);
+            }
+            Features::WRITE => {
+                add_write(context, module, builder);
+            }
+            Features::READTOSTRING => {
+                add_read_to_string(context, module, builder);
+            }
+            Features::CLOSE => {
+                add_close(context, module, builder);
+            }
+            Features::EXIT => {
+                add_exit(context, module, builder);
+            }
+            Features::PANIC => {
+                add_panic(context, module, builder);
+            }
         }
     }
 }
 
+/// The signature of a standard library function, by its FAANG name.
+pub fn signature(name: &str) -> Option<Signature> {
+    match name {
+        "println" => Some(Signature {
+            parameters: vec![Type::String],
+            return_type: None,
+        }),
+        "args" => Some(Signature {
+            parameters: vec![],
+            return_type: Some(Type::Array(Box::new(Type::String))),
+        }),
+        "read_line" | "read_all" => Some(Signature {
+            parameters: vec![],
+            return_type: Some(Type::String),
+        }),
+        "read_int" => Some(Signature {
+            parameters: vec![],
+            return_type: Some(Type::Int),
+        }),
+        "open" => Some(Signature {
+            parameters: vec![Type::String, Type::String],
+            return_type: Some(Type::File),
+        }),
+        "write" => Some(Signature {
+            parameters: vec![Type::File, Type::String],
+            return_type: None,
+        }),
+        "read_to_string" => Some(Signature {
+            parameters: vec![Type::File],
+            return_type: Some(Type::String),
+        }),
+        "close" => Some(Signature {
+            parameters: vec![Type::File],
+            return_type: None,
+        }),
+        "exit" => Some(Signature {
+            parameters: vec![Type::Int],
+            return_type: None,
+        }),
+        _ => None,
+    }
+}
+
+/// Adds the streams that output is written to, which are null until a host installs a sink
+/// into them.
+fn add_output(context: &Context, module: &Module) {
+    let file_ptr = context.i8_type().ptr_type(AddressSpace::Generic);
+    for name in &[STDOUT, STDERR] {
+        // Like the arguments, every module defines the streams.
+        let stream = module.add_global(file_ptr, None, name);
+        stream.set_linkage(Linkage::Common);
+        stream.set_initializer(&file_ptr.const_null());
+    }
+}
+
+/// Loads the stream that output to `stream`, `STDOUT` or `STDERR`, is written to, which is
+/// the standard stream `fallback` of the C library unless a sink is installed.
+pub(super) fn load_output(
+    module: &Module,
+    builder: &Builder,
+    stream: &str,
+    fallback: &str,
+) -> PointerValue {
+    let sink = builder
+        .build_load(
+            module.get_global(stream).unwrap().as_pointer_value(),
+            stream,
+        )
+        .into_pointer_value();
+    let fallback = builder
+        .build_load(
+            module.get_global(fallback).unwrap().as_pointer_value(),
+            fallback,
+        )
+        .into_pointer_value();
+    let is_null = builder.build_is_null(sink, "is_null");
+    builder
+        .build_select(is_null, fallback, sink, stream)
+        .into_pointer_value()
+}
+
 fn add_println(context: &Context, module: &Module, builder: &Builder) {
     let println_fcn = module.add_function(
         PRINTLN,
@@ -24,28 +187,442 @@ fn add_println(context: &Context, module: &Module, builder: &Builder) {
             &[context.i8_type().ptr_type(AddressSpace::Generic).into()],
             false,
         ),
-        None,
+        Some(Linkage::LinkOnceODR),
     );
     let block = context.append_basic_block(&println_fcn, "entry");
     builder.position_at_end(&block);
 
-    let text = println_fcn.get_nth_param(0).unwrap();
+    let stdout = load_output(module, builder, STDOUT, stdio::STDOUT);
+    let format = builder
+        .build_global_string_ptr("%s\n", "format")
+        .as_pointer_value();
     builder.build_call(
-        module.get_function(super::external::stdio::PRINTF).unwrap(),
-        &[text
-            .as_pointer_value()
-            .const_cast(context.i8_type().ptr_type(AddressSpace::Generic))
-            .into()],
-        super::external::stdio::PRINTF,
+        module.get_function(stdio::FPRINTF).unwrap(),
+        &[
+            stdout.into(),
+            format.into(),
+            println_fcn.get_nth_param(0).unwrap(),
+        ],
+        stdio::FPRINTF,
+    );
+    builder.build_return(None);
+}
+
+/// Adds `args`, which returns the command line arguments without the name of the program.
+/// The array points into `argv`.
+fn add_args(context: &Context, module: &Module, builder: &Builder) {
+    let char_ptr_ptr = context
+        .i8_type()
+        .ptr_type(AddressSpace::Generic)
+        .ptr_type(AddressSpace::Generic);
+    // Every module that calls `args` defines the arguments, and linking keeps one of them.
+    let argc = module.add_global(context.i32_type(), None, ARGC);
+    argc.set_linkage(Linkage::Common);
+    argc.set_initializer(&context.i32_type().const_int(0, false));
+    let argv = module.add_global(char_ptr_ptr, None, ARGV);
+    argv.set_linkage(Linkage::Common);
+    argv.set_initializer(&char_ptr_ptr.const_null());
+
+    let array_type = context.struct_type(&[context.i64_type().into(), char_ptr_ptr.into()], false);
+    let args_fcn = module.add_function(
+        ARGS,
+        array_type
+            .ptr_type(AddressSpace::Generic)
+            .fn_type(&[], false),
+        Some(Linkage::LinkOnceODR),
+    );
+    let block = context.append_basic_block(&args_fcn, "entry");
+    builder.position_at_end(&block);
+
+    let argc = builder
+        .build_load(argc.as_pointer_value(), "argc")
+        .into_int_value();
+    let argc = builder.build_int_s_extend(argc, context.i64_type(), "argc");
+    let one = context.i64_type().const_int(1, false);
+    let has_name = builder.build_int_compare(
+        IntPredicate::SGT,
+        argc,
+        context.i64_type().const_zero(),
+        "has_name",
     );
+    let length = builder
+        .build_select(
+            has_name,
+            builder.build_int_sub(argc, one, "length"),
+            context.i64_type().const_zero(),
+            "length",
+        )
+        .into_int_value();
+    let argv = builder
+        .build_load(argv.as_pointer_value(), "argv")
+        .into_pointer_value();
+    let elements = unsafe { builder.build_in_bounds_gep(argv, &[one], "elements") };
 
-    let new_line = util::alloc_string("\n", "format", &context, &builder);
+    let memory = builder
+        .build_call(
+            module.get_function(external::stdlib::MALLOC).unwrap(),
+            &[array_type.size_of().unwrap().into()],
+            "array",
+        )
+        .try_as_basic_value()
+        .left()
+        .unwrap();
+    let array = builder.build_pointer_cast(
+        memory.into_pointer_value(),
+        array_type.ptr_type(AddressSpace::Generic),
+        "array",
+    );
+    let length_ptr = unsafe { builder.build_struct_gep(array, 0, "length") };
+    builder.build_store(length_ptr, length);
+    let elements_ptr = unsafe { builder.build_struct_gep(array, 1, "elements") };
+    builder.build_store(elements_ptr, elements);
+    builder.build_return(Some(&array));
+}
+
+/// Adds `read_line`, which reads a line of the standard input without its line break. It
+/// returns an empty string at the end of the input.
+fn add_read_line(context: &Context, module: &Module, builder: &Builder) {
+    let read_line_fcn = add_reader(context, module, READ_LINE);
+    let block = context.append_basic_block(&read_line_fcn, "entry");
+    builder.position_at_end(&block);
+
+    let stdin = load_stdin(module, builder);
+    let (line, length) = build_read(context, builder, stdin, |line, capacity, stdin| {
+        builder
+            .build_call(
+                module.get_function(stdio::GETLINE).unwrap(),
+                &[line.into(), capacity.into(), stdin.into()],
+                "length",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value()
+    });
+    build_return_read(context, builder, read_line_fcn, line, length, true);
+}
+
+/// Adds `read_all`, which reads the rest of the standard input.
+fn add_read_all(context: &Context, module: &Module, builder: &Builder) {
+    let read_all_fcn = add_reader(context, module, READ_ALL);
+    let block = context.append_basic_block(&read_all_fcn, "entry");
+    builder.position_at_end(&block);
+
+    let stdin = load_stdin(module, builder);
+    let (text, length) = build_read_all(context, module, builder, stdin);
+    build_return_read(context, builder, read_all_fcn, text, length, false);
+}
+
+/// Adds `read_int`, which reads an integer from the standard input. It returns `0` if the
+/// input does not start with one.
+fn add_read_int(context: &Context, module: &Module, builder: &Builder) {
+    let read_int_fcn = module.add_function(
+        READ_INT,
+        context.i64_type().fn_type(&[], false),
+        Some(Linkage::LinkOnceODR),
+    );
+    let block = context.append_basic_block(&read_int_fcn, "entry");
+    builder.position_at_end(&block);
+
+    let value = builder.build_alloca(context.i64_type(), "value");
+    builder.build_store(value, context.i64_type().const_zero());
+    let format = builder
+        .build_global_string_ptr("%ld", "format")
+        .as_pointer_value();
     builder.build_call(
-        module.get_function(super::external::stdio::PRINTF).unwrap(),
-        &[new_line
-            .const_cast(context.i8_type().ptr_type(AddressSpace::Generic))
-            .into()],
-        super::external::stdio::PRINTF,
+        module.get_function(stdio::SCANF).unwrap(),
+        &[format.into(), value.into()],
+        stdio::SCANF,
+    );
+    let value = builder.build_load(value, "value");
+    builder.build_return(Some(&value));
+}
+
+/// Adds `open`, which opens a file with a `fopen` mode such as `"r"` or `"w"`. If the file
+/// cannot be opened, the program prints why and exits with status `1`.
+fn add_open(context: &Context, module: &Module, builder: &Builder) {
+    let char_ptr = context.i8_type().ptr_type(AddressSpace::Generic);
+    let open_fcn = module.add_function(
+        OPEN,
+        char_ptr.fn_type(&[char_ptr.into(), char_ptr.into()], false),
+        Some(Linkage::LinkOnceODR),
+    );
+    let block = context.append_basic_block(&open_fcn, "entry");
+    builder.position_at_end(&block);
+
+    let path = open_fcn.get_nth_param(0).unwrap();
+    let mode = open_fcn.get_nth_param(1).unwrap();
+    let file = builder
+        .build_call(
+            module.get_function(stdio::FOPEN).unwrap(),
+            &[path, mode],
+            "file",
+        )
+        .try_as_basic_value()
+        .left()
+        .unwrap()
+        .into_pointer_value();
+
+    let opened = context.append_basic_block(&open_fcn, "opened");
+    let failed = context.append_basic_block(&open_fcn, "failed");
+    let is_null = builder.build_is_null(file, "is_null");
+    builder.build_conditional_branch(is_null, &failed, &opened);
+
+    builder.position_at_end(&failed);
+    builder.build_call(module.get_function(stdio::PERROR).unwrap(), &[path], "");
+    builder.build_call(
+        module.get_function(external::stdlib::EXIT).unwrap(),
+        &[context.i32_type().const_int(1, false).into()],
+        "",
+    );
+    builder.build_unreachable();
+
+    builder.position_at_end(&opened);
+    builder.build_return(Some(&file));
+}
+
+/// Adds `write`, which writes text to a file.
+fn add_write(context: &Context, module: &Module, builder: &Builder) {
+    let char_ptr = context.i8_type().ptr_type(AddressSpace::Generic);
+    let write_fcn = module.add_function(
+        WRITE,
+        context
+            .void_type()
+            .fn_type(&[char_ptr.into(), char_ptr.into()], false),
+        Some(Linkage::LinkOnceODR),
+    );
+    let block = context.append_basic_block(&write_fcn, "entry");
+    builder.position_at_end(&block);
+
+    let format = builder
+        .build_global_string_ptr("%s", "format")
+        .as_pointer_value();
+    builder.build_call(
+        module.get_function(stdio::FPRINTF).unwrap(),
+        &[
+            write_fcn.get_nth_param(0).unwrap(),
+            format.into(),
+            write_fcn.get_nth_param(1).unwrap(),
+        ],
+        stdio::FPRINTF,
+    );
+    builder.build_return(None);
+}
+
+/// Adds `read_to_string`, which reads the rest of a file.
+fn add_read_to_string(context: &Context, module: &Module, builder: &Builder) {
+    let char_ptr = context.i8_type().ptr_type(AddressSpace::Generic);
+    let read_to_string_fcn = module.add_function(
+        READ_TO_STRING,
+        char_ptr.fn_type(&[char_ptr.into()], false),
+        Some(Linkage::LinkOnceODR),
+    );
+    let block = context.append_basic_block(&read_to_string_fcn, "entry");
+    builder.position_at_end(&block);
+
+    let file = read_to_string_fcn
+        .get_nth_param(0)
+        .unwrap()
+        .into_pointer_value();
+    let (text, length) = build_read_all(context, module, builder, file);
+    build_return_read(context, builder, read_to_string_fcn, text, length, false);
+}
+
+/// Adds `close`, which closes a file, writing out what was written to it.
+fn add_close(context: &Context, module: &Module, builder: &Builder) {
+    let char_ptr = context.i8_type().ptr_type(AddressSpace::Generic);
+    let close_fcn = module.add_function(
+        CLOSE,
+        context.void_type().fn_type(&[char_ptr.into()], false),
+        Some(Linkage::LinkOnceODR),
+    );
+    let block = context.append_basic_block(&close_fcn, "entry");
+    builder.position_at_end(&block);
+
+    builder.build_call(
+        module.get_function(stdio::FCLOSE).unwrap(),
+        &[close_fcn.get_nth_param(0).unwrap()],
+        stdio::FCLOSE,
     );
     builder.build_return(None);
 }
+
+/// Adds `exit`, which ends the program with an exit status.
+fn add_exit(context: &Context, module: &Module, builder: &Builder) {
+    let exit_fcn = module.add_function(
+        EXIT,
+        context
+            .void_type()
+            .fn_type(&[context.i64_type().into()], false),
+        Some(Linkage::LinkOnceODR),
+    );
+    let block = context.append_basic_block(&exit_fcn, "entry");
+    builder.position_at_end(&block);
+
+    let code = builder.build_int_truncate(
+        exit_fcn.get_nth_param(0).unwrap().into_int_value(),
+        context.i32_type(),
+        "code",
+    );
+    builder.build_call(
+        module.get_function(external::stdlib::EXIT).unwrap(),
+        &[code.into()],
+        "",
+    );
+    builder.build_unreachable();
+}
+
+/// Adds the function that `panic` and `assert` call, which prints the message and where the
+/// panic happened to the standard error, and ends the program with `PANIC_STATUS`.
+fn add_panic(context: &Context, module: &Module, builder: &Builder) {
+    let char_ptr = context.i8_type().ptr_type(AddressSpace::Generic);
+    let panic_fcn = module.add_function(
+        PANIC,
+        context
+            .void_type()
+            .fn_type(&[char_ptr.into(), char_ptr.into()], false),
+        Some(Linkage::LinkOnceODR),
+    );
+    let block = context.append_basic_block(&panic_fcn, "entry");
+    builder.position_at_end(&block);
+
+    let stderr = load_output(module, builder, STDERR, stdio::STDERR);
+    let format = builder
+        .build_global_string_ptr("panicked at '%s', %s\n", "format")
+        .as_pointer_value();
+    builder.build_call(
+        module.get_function(stdio::FPRINTF).unwrap(),
+        &[
+            stderr.into(),
+            format.into(),
+            panic_fcn.get_nth_param(0).unwrap(),
+            panic_fcn.get_nth_param(1).unwrap(),
+        ],
+        stdio::FPRINTF,
+    );
+    builder.build_call(
+        module.get_function(external::stdlib::EXIT).unwrap(),
+        &[context.i32_type().const_int(PANIC_STATUS, false).into()],
+        "",
+    );
+    builder.build_unreachable();
+}
+
+fn add_reader(context: &Context, module: &Module, name: &str) -> FunctionValue {
+    module.add_function(
+        name,
+        context
+            .i8_type()
+            .ptr_type(AddressSpace::Generic)
+            .fn_type(&[], false),
+        Some(Linkage::LinkOnceODR),
+    )
+}
+
+fn load_stdin(module: &Module, builder: &Builder) -> PointerValue {
+    builder
+        .build_load(
+            module.get_global(stdio::STDIN).unwrap().as_pointer_value(),
+            stdio::STDIN,
+        )
+        .into_pointer_value()
+}
+
+/// Reads from `stream` into a buffer allocated by `read`, which is `getline` or `getdelim`,
+/// returning the buffer and the number of characters read, which is negative at the end of
+/// the input.
+fn build_read<F>(
+    context: &Context,
+    builder: &Builder,
+    stream: PointerValue,
+    read: F,
+) -> (PointerValue, IntValue)
+where
+    F: Fn(PointerValue, PointerValue, PointerValue) -> IntValue,
+{
+    let char_ptr = context.i8_type().ptr_type(AddressSpace::Generic);
+    let buffer = builder.build_alloca(char_ptr, "buffer");
+    builder.build_store(buffer, char_ptr.const_null());
+    let capacity = builder.build_alloca(context.i64_type(), "capacity");
+    builder.build_store(capacity, context.i64_type().const_zero());
+
+    let length = read(buffer, capacity, stream);
+    let buffer = builder.build_load(buffer, "buffer").into_pointer_value();
+    (buffer, length)
+}
+
+/// Reads the rest of `stream` with `build_read`.
+fn build_read_all(
+    context: &Context,
+    module: &Module,
+    builder: &Builder,
+    stream: PointerValue,
+) -> (PointerValue, IntValue) {
+    // Text does not contain NUL characters, so reading up to one reads everything.
+    build_read(context, builder, stream, |text, capacity, stream| {
+        builder
+            .build_call(
+                module.get_function(stdio::GETDELIM).unwrap(),
+                &[
+                    text.into(),
+                    capacity.into(),
+                    context.i32_type().const_zero().into(),
+                    stream.into(),
+                ],
+                "length",
+            )
+            .try_as_basic_value()
+            .left()
+            .unwrap()
+            .into_int_value()
+    })
+}
+
+/// Returns the buffer read by `build_read`, or an empty string at the end of the input,
+/// removing a trailing line break if `trim_line_break` is set.
+fn build_return_read(
+    context: &Context,
+    builder: &Builder,
+    fcn: FunctionValue,
+    buffer: PointerValue,
+    length: IntValue,
+    trim_line_break: bool,
+) {
+    let read = context.append_basic_block(&fcn, "read");
+    let end = context.append_basic_block(&fcn, "end");
+    let is_end = builder.build_int_compare(
+        IntPredicate::SLE,
+        length,
+        context.i64_type().const_zero(),
+        "is_end",
+    );
+    builder.build_conditional_branch(is_end, &end, &read);
+
+    builder.position_at_end(&end);
+    let empty = builder.build_global_string_ptr("", "empty");
+    builder.build_return(Some(&empty.as_pointer_value()));
+
+    builder.position_at_end(&read);
+    if trim_line_break {
+        let trim = context.append_basic_block(&fcn, "trim");
+        let done = context.append_basic_block(&fcn, "done");
+        let last_index =
+            builder.build_int_sub(length, context.i64_type().const_int(1, false), "last");
+        let last = unsafe { builder.build_in_bounds_gep(buffer, &[last_index], "last") };
+        let last_char = builder.build_load(last, "last").into_int_value();
+        let is_line_break = builder.build_int_compare(
+            IntPredicate::EQ,
+            last_char,
+            context.i8_type().const_int(u64::from(b'\n'), false),
+            "is_line_break",
+        );
+        builder.build_conditional_branch(is_line_break, &trim, &done);
+
+        builder.position_at_end(&trim);
+        builder.build_store(last, context.i8_type().const_zero());
+        builder.build_unconditional_branch(&done);
+
+        builder.position_at_end(&done);
+    }
+    builder.build_return(Some(&buffer));
+}
@@ -0,0 +1,53 @@
+//! The codes that diagnostics are reported with, which `faang explain` describes.
+pub const UNRECOGNIZED_TOKEN: &str = "E0001";
+pub const UNEXPECTED_EOF: &str = "E0002";
+pub const UNEXPECTED_CHARACTER: &str = "E0003";
+pub const UNTERMINATED_STRING: &str = "E0004";
+pub const UNTERMINATED_COMMENT: &str = "E0005";
+pub const UNKNOWN_ESCAPE: &str = "E0006";
+pub const INVALID_UNICODE_ESCAPE: &str = "E0007";
+pub const INTEGER_TOO_LARGE: &str = "E0008";
+pub const DUPLICATE_MODULE: &str = "E0009";
+pub const IMPORT_OF_ENTRY: &str = "E0010";
+pub const UNREADABLE_IMPORT: &str = "E0011";
+pub const TYPE_ERROR: &str = "E0012";
+
+static EXPLANATIONS: &[(&str, &str)] = &[
+    (UNRECOGNIZED_TOKEN, include_str!("error_codes/E0001.md")),
+    (UNEXPECTED_EOF, include_str!("error_codes/E0002.md")),
+    (UNEXPECTED_CHARACTER, include_str!("error_codes/E0003.md")),
+    (UNTERMINATED_STRING, include_str!("error_codes/E0004.md")),
+    (UNTERMINATED_COMMENT, include_str!("error_codes/E0005.md")),
+    (UNKNOWN_ESCAPE, include_str!("error_codes/E0006.md")),
+    (INVALID_UNICODE_ESCAPE, include_str!("error_codes/E0007.md")),
+    (INTEGER_TOO_LARGE, include_str!("error_codes/E0008.md")),
+    (DUPLICATE_MODULE, include_str!("error_codes/E0009.md")),
+    (IMPORT_OF_ENTRY, include_str!("error_codes/E0010.md")),
+    (UNREADABLE_IMPORT, include_str!("error_codes/E0011.md")),
+    (TYPE_ERROR, include_str!("error_codes/E0012.md")),
+];
+
+/// The description of the error with a code, with examples in Markdown. The code is matched
+/// regardless of case.
+pub fn explain(code: &str) -> Option<&'static str> {
+    EXPLANATIONS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(code))
+        .map(|(_, explanation)| *explanation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn explains_every_code() {
+        for (index, (code, explanation)) in EXPLANATIONS.iter().enumerate() {
+            assert_eq!(*code, format!("E{:04}", index + 1));
+            assert_eq!(explain(code), Some(*explanation));
+            assert!(explanation.contains("```faang"), "{} has no example", code);
+        }
+        assert_eq!(explain("e0001"), explain(UNRECOGNIZED_TOKEN));
+        assert_eq!(explain("E9999"), None);
+    }
+}
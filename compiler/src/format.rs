@@ -0,0 +1,897 @@
+//! The canonical formatting of source files, which `faang fmt` writes.
+//!
+//! The formatter prints the syntax tree of a file rather than editing its text, so the layout
+//! of the source does not matter: blocks are indented by four spaces, every simple statement
+//! ends with a `;` and parentheses are only kept where they are needed. Blank lines between
+//! statements and items are kept, but never more than one in a row.
+//!
+//! Comments are not part of the syntax tree, so they are put back by their position in the
+//! source. A comment after a statement on the same line stays at the end of that line. Any
+//! other comment is printed on its own line before the first statement or item that starts
+//! after it, or before the closing brace of the block containing it. Comments inside an
+//! expression are therefore moved after its statement.
+use codespan::{FileId, Span};
+use codespan_reporting::diagnostic::Diagnostic;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::ast::{
+    Attribute, BinaryOperator, Enum, Expression, Function, Item, Lambda, LambdaBody, MatchArmBody,
+    Program, Statement, StatementKind, Struct, UnaryOperator,
+};
+use crate::lexer::{Lexer, Spanned, Token};
+
+const INDENT: &str = "    ";
+
+// The precedence of expressions, from the loosest to the tightest binding. An operand with a
+// lower precedence than its operator allows is parenthesized.
+const LAMBDA: u8 = 0;
+const OR: u8 = 1;
+const AND: u8 = 2;
+const COMPARISON: u8 = 3;
+const SUM: u8 = 4;
+const PRODUCT: u8 = 5;
+const UNARY: u8 = 6;
+const POSTFIX: u8 = 7;
+const PRIMARY: u8 = 8;
+
+/// Parses and formats a source file, failing with the diagnostics of its syntax errors.
+pub fn format_source(source: &str, file_id: FileId) -> Result<String, Vec<Diagnostic>> {
+    let program = crate::parse(source, file_id)?;
+    Ok(format(source, &program))
+}
+
+/// Formats a program parsed from `source`, whose comments are kept.
+pub fn format(source: &str, program: &Program) -> String {
+    let mut printer = Printer::new(source);
+    printer.program(program);
+    printer.out
+}
+
+/// Formats an expression on its own.
+pub fn format_expression(expression: &Expression) -> String {
+    let mut printer = Printer::new("");
+    printer.expression(expression, LAMBDA, Position::Anywhere);
+    printer.out
+}
+
+/// Where an expression is, which restricts the expressions that may be there without
+/// parentheses.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Position {
+    Anywhere,
+    /// The condition of an `if` or a `while`, or the scrutinee of a `match`, which is followed
+    /// by a `{` that must not be mistaken for the start of a struct literal.
+    Condition,
+    /// The start of an expression statement or of an assignment, which must not start with a
+    /// `match` since it would be parsed as a `match` statement.
+    Statement,
+}
+
+impl Position {
+    /// The position of the operands of an expression after its start, which may only start
+    /// with a `match` at the start of the expression.
+    fn after_start(self) -> Position {
+        match self {
+            Position::Statement => Position::Anywhere,
+            position => position,
+        }
+    }
+}
+
+struct Printer<'a> {
+    source: &'a str,
+    /// The tokens of the source, without its comments.
+    tokens: Vec<Spanned<Token<'a>>>,
+    /// The offset of the `{` matching each `}`.
+    open_braces: HashMap<usize, usize>,
+    /// The comments that are not printed yet, with the next one last.
+    comments: Vec<Spanned<&'a str>>,
+    out: String,
+    indent: usize,
+    /// The end of the last statement, item or comment printed, after which the source is
+    /// searched for a blank line before the next one.
+    last: usize,
+    /// Whether nothing was printed yet in the current block, which does not start with a blank
+    /// line.
+    block_start: bool,
+}
+
+impl<'a> Printer<'a> {
+    fn new(source: &'a str) -> Printer<'a> {
+        let mut tokens = vec![];
+        let mut comments = vec![];
+        for (start, token, end) in Lexer::with_comments(source).filter_map(Result::ok) {
+            match token {
+                Token::Comment(text) => comments.push((start, text, end)),
+                token => tokens.push((start, token, end)),
+            }
+        }
+        comments.reverse();
+
+        let mut open_braces = HashMap::new();
+        let mut open = vec![];
+        for (start, token, _) in &tokens {
+            match token {
+                Token::LeftBrace => open.push(*start),
+                Token::RightBrace => {
+                    if let Some(open) = open.pop() {
+                        open_braces.insert(*start, open);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Printer {
+            source,
+            tokens,
+            open_braces,
+            comments,
+            out: String::new(),
+            indent: 0,
+            last: 0,
+            block_start: true,
+        }
+    }
+
+    fn program(&mut self, program: &Program) {
+        for item in &program.items {
+            match item {
+                Item::Statement(statement) => self.statement(statement),
+                Item::Struct(declaration) => self.struct_declaration(declaration),
+                Item::Enum(declaration) => self.enum_declaration(declaration),
+                Item::Function(function) => self.function(function),
+                Item::Import(import) => {
+                    self.start_item(import.span);
+                    write!(self.out, "import {};", string_literal(&import.path)).unwrap();
+                    self.end_item(import.span);
+                }
+                Item::Use(declaration) => {
+                    self.start_item(declaration.span);
+                    write!(
+                        self.out,
+                        "use {}::{};",
+                        declaration.module, declaration.name
+                    )
+                    .unwrap();
+                    self.end_item(declaration.span);
+                }
+                Item::Test(test) => {
+                    self.start_item(test.span);
+                    write!(self.out, "test {} ", string_literal(&test.name)).unwrap();
+                    self.block(&test.body, self.closing_brace(test.span));
+                    self.end_item(test.span);
+                }
+            }
+        }
+        self.comments_before(usize::MAX);
+    }
+
+    fn struct_declaration(&mut self, declaration: &Struct) {
+        self.start_item(declaration.span);
+        write!(self.out, "struct {} ", declaration.name).unwrap();
+        let fields = declaration
+            .fields
+            .iter()
+            .map(|(name, ty)| format!("{}: {}", name, ty))
+            .collect::<Vec<_>>();
+        self.lines(&fields, self.closing_brace(declaration.span));
+        self.end_item(declaration.span);
+    }
+
+    fn enum_declaration(&mut self, declaration: &Enum) {
+        self.start_item(declaration.span);
+        write!(self.out, "enum {} ", declaration.name).unwrap();
+        let variants = declaration
+            .variants
+            .iter()
+            .map(|variant| {
+                if variant.fields.is_empty() {
+                    return variant.name.clone();
+                }
+                let fields = variant
+                    .fields
+                    .iter()
+                    .map(|field| field.to_string())
+                    .collect::<Vec<_>>();
+                format!("{}({})", variant.name, fields.join(", "))
+            })
+            .collect::<Vec<_>>();
+        self.lines(&variants, self.closing_brace(declaration.span));
+        self.end_item(declaration.span);
+    }
+
+    fn function(&mut self, function: &Function) {
+        self.start_item(function.span);
+        if let Some(doc) = &function.doc {
+            for line in doc.split('\n') {
+                if line.is_empty() {
+                    self.out.push_str("///\n");
+                } else {
+                    writeln!(self.out, "/// {}", line).unwrap();
+                }
+                self.write_indent();
+            }
+        }
+        for attribute in &function.attributes {
+            self.attribute(attribute);
+            self.out.push('\n');
+            self.write_indent();
+        }
+
+        write!(self.out, "func {}", function.name).unwrap();
+        if !function.type_parameters.is_empty() {
+            write!(self.out, "<{}>", function.type_parameters.join(", ")).unwrap();
+        }
+        let parameters = function
+            .parameters
+            .iter()
+            .map(|(name, ty)| format!("{}: {}", name, ty))
+            .collect::<Vec<_>>();
+        write!(self.out, "({})", parameters.join(", ")).unwrap();
+        if let Some(return_type) = &function.return_type {
+            write!(self.out, " -> {}", return_type).unwrap();
+        }
+        self.out.push(' ');
+        self.block(&function.body, self.closing_brace(function.span));
+        self.end_item(function.span);
+    }
+
+    fn attribute(&mut self, attribute: &Attribute) {
+        write!(
+            self.out,
+            "#[{}({})]",
+            attribute.name,
+            attribute.arguments.join(", ")
+        )
+        .unwrap();
+    }
+
+    /// Prints the comments before an item or a statement, and the blank line before it if
+    /// there is one in the source.
+    fn start_item(&mut self, span: Span) {
+        let start = span.start().to_usize();
+        self.comments_before(start);
+        self.separate(start);
+        self.write_indent();
+    }
+
+    /// Ends the line of an item or a statement, along with the comment after it on the line.
+    fn end_item(&mut self, span: Span) {
+        let end = span.end().to_usize();
+        self.trailing_comment(end);
+        self.out.push('\n');
+        self.last = end;
+    }
+
+    /// Prints the lines of a struct or an enum declaration between braces, each followed by a
+    /// comma.
+    fn lines(&mut self, lines: &[String], close: Option<usize>) {
+        self.out.push('{');
+        if lines.is_empty() && !self.has_comments_before(close) {
+            self.out.push('}');
+            return;
+        }
+        self.out.push('\n');
+        self.indent += 1;
+        for line in lines {
+            self.write_indent();
+            self.out.push_str(line);
+            self.out.push_str(",\n");
+        }
+        if let Some(close) = close {
+            self.block_start = lines.is_empty();
+            self.comments_before(close);
+        }
+        self.indent -= 1;
+        self.write_indent();
+        self.out.push('}');
+    }
+
+    /// Prints a block, along with the comments before its closing brace at `close`, if it
+    /// is known.
+    fn block(&mut self, statements: &[Statement], close: Option<usize>) {
+        self.out.push('{');
+        if statements.is_empty() && !self.has_comments_before(close) {
+            self.out.push('}');
+            return;
+        }
+        self.out.push('\n');
+        self.indent += 1;
+        self.block_start = true;
+        for statement in statements {
+            self.statement(statement);
+        }
+        if let Some(close) = close {
+            self.comments_before(close);
+        }
+        self.indent -= 1;
+        self.write_indent();
+        self.out.push('}');
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        self.start_item(statement.span);
+        match &statement.kind {
+            StatementKind::Let(name, ty, expression) => {
+                write!(self.out, "let {}", name).unwrap();
+                if let Some(ty) = ty {
+                    write!(self.out, ": {}", ty).unwrap();
+                }
+                self.out.push_str(" = ");
+                self.expression(expression, LAMBDA, Position::Anywhere);
+                self.out.push(';');
+            }
+            StatementKind::Assignment(place, expression) => {
+                self.expression(place, POSTFIX, Position::Statement);
+                self.out.push_str(" = ");
+                self.expression(expression, LAMBDA, Position::Anywhere);
+                self.out.push(';');
+            }
+            // A `match` statement is not followed by a `;`.
+            StatementKind::Expression(expression @ Expression::Match(..)) => {
+                self.expression(expression, LAMBDA, Position::Anywhere)
+            }
+            StatementKind::Expression(expression) => {
+                self.expression(expression, LAMBDA, Position::Statement);
+                self.out.push(';');
+            }
+            StatementKind::Return(None) => self.out.push_str("return;"),
+            StatementKind::Return(Some(expression)) => {
+                self.out.push_str("return ");
+                self.expression(expression, LAMBDA, Position::Anywhere);
+                self.out.push(';');
+            }
+            StatementKind::If(condition, then, otherwise) => {
+                self.if_statement(condition, then, otherwise, statement.span)
+            }
+            StatementKind::While(condition, body) => {
+                self.out.push_str("while ");
+                self.expression(condition, LAMBDA, Position::Condition);
+                self.out.push(' ');
+                self.block(body, self.closing_brace(statement.span));
+            }
+            // Programs with syntax errors are not formatted.
+            StatementKind::Error => {}
+        }
+        self.end_item(statement.span);
+    }
+
+    /// Prints an `if` statement, with an `else` block that only holds another `if` statement
+    /// as an `else if`.
+    fn if_statement(
+        &mut self,
+        condition: &Expression,
+        then: &[Statement],
+        otherwise: &[Statement],
+        span: Span,
+    ) {
+        self.out.push_str("if ");
+        self.expression(condition, LAMBDA, Position::Condition);
+        self.out.push(' ');
+        let then_close = if otherwise.is_empty() {
+            self.closing_brace(span)
+        } else {
+            self.then_brace(span, otherwise)
+        };
+        self.block(then, then_close);
+
+        match otherwise {
+            [] => {}
+            [Statement {
+                kind: StatementKind::If(condition, then, otherwise),
+                span,
+            }] => {
+                self.out.push_str(" else ");
+                self.if_statement(condition, then, otherwise, *span);
+            }
+            otherwise => {
+                self.out.push_str(" else ");
+                self.block(otherwise, self.closing_brace(span));
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression, precedence: u8, position: Position) {
+        let exposed = match expression {
+            Expression::StructLiteral(..) => position == Position::Condition,
+            Expression::Match(..) => position == Position::Statement,
+            _ => false,
+        };
+        if exposed || precedence_of(expression) < precedence {
+            self.out.push('(');
+            self.expression(expression, LAMBDA, Position::Anywhere);
+            self.out.push(')');
+            return;
+        }
+
+        match expression {
+            Expression::Invocation(name, args) => {
+                self.out.push_str(name);
+                self.arguments(args);
+            }
+            Expression::Call(callee, args) => {
+                self.expression(callee, POSTFIX, position);
+                self.arguments(args);
+            }
+            Expression::StringLiteral(value) => self.out.push_str(&string_literal(value)),
+            Expression::IntegerLiteral(value) => write!(self.out, "{}", value).unwrap(),
+            Expression::BooleanLiteral(value) => write!(self.out, "{}", value).unwrap(),
+            Expression::Null => self.out.push_str("null"),
+            Expression::Variable(name) => self.out.push_str(name),
+            Expression::FieldAccess(object, field) => {
+                self.expression(object, POSTFIX, position);
+                write!(self.out, ".{}", field).unwrap();
+            }
+            Expression::StructLiteral(name, fields) => {
+                write!(self.out, "{} {{", name).unwrap();
+                for (index, (field, value)) in fields.iter().enumerate() {
+                    let separator = if index == 0 { " " } else { ", " };
+                    write!(self.out, "{}{}: ", separator, field).unwrap();
+                    self.expression(value, LAMBDA, Position::Anywhere);
+                }
+                self.out
+                    .push_str(if fields.is_empty() { "}" } else { " }" });
+            }
+            Expression::Variant(enum_name, variant, args) => {
+                write!(self.out, "{}::{}", enum_name, variant).unwrap();
+                // `utils::helper()` parses like a variant of an enum, so a call of a function
+                // of a module, whose name is not capitalized like the name of an enum, keeps
+                // its parentheses.
+                if !args.is_empty() || enum_name.starts_with(char::is_lowercase) {
+                    self.arguments(args);
+                }
+            }
+            Expression::Match(scrutinee, arms) => {
+                self.out.push_str("match ");
+                self.expression(scrutinee, LAMBDA, Position::Condition);
+                self.out.push_str(" {");
+                if arms.is_empty() {
+                    self.out.push('}');
+                    return;
+                }
+                self.out.push('\n');
+                self.indent += 1;
+                self.block_start = true;
+                for arm in arms {
+                    self.start_item(arm.span);
+                    write!(self.out, "{} => ", arm.pattern).unwrap();
+                    match &arm.body {
+                        MatchArmBody::Expression(body) => {
+                            self.expression(body, LAMBDA, Position::Anywhere);
+                            self.out.push(',');
+                        }
+                        MatchArmBody::Block(body) => self.block(body, self.closing_brace(arm.span)),
+                    }
+                    self.end_item(arm.span);
+                }
+                let last_arm = arms[arms.len() - 1].span.end().to_usize();
+                if let Some(close) = self.brace_after(last_arm) {
+                    self.comments_before(close);
+                }
+                self.indent -= 1;
+                self.write_indent();
+                self.out.push('}');
+            }
+            Expression::Lambda(lambda) => self.lambda(lambda, position.after_start()),
+            Expression::Array(elements) => {
+                self.out.push('[');
+                self.expressions(elements);
+                self.out.push(']');
+            }
+            Expression::Index(array, index) => {
+                self.expression(array, POSTFIX, position);
+                self.out.push('[');
+                self.expression(index, LAMBDA, Position::Anywhere);
+                self.out.push(']');
+            }
+            Expression::Unary(operator, operand) => {
+                self.out.push(match operator {
+                    UnaryOperator::Negate => '-',
+                    UnaryOperator::Not => '!',
+                });
+                self.expression(operand, UNARY, position.after_start());
+            }
+            Expression::Binary(lhs, operator, rhs) => {
+                let precedence = binary_precedence(*operator);
+                // Comparisons do not chain, so neither of their operands may be one.
+                let (lhs_precedence, rhs_precedence) = if precedence == COMPARISON {
+                    (SUM, SUM)
+                } else {
+                    (precedence, precedence + 1)
+                };
+                self.expression(lhs, lhs_precedence, position);
+                write!(self.out, " {} ", operator).unwrap();
+                self.expression(rhs, rhs_precedence, position.after_start());
+            }
+            Expression::Error => {}
+        }
+    }
+
+    fn lambda(&mut self, lambda: &Lambda, position: Position) {
+        if lambda.parameters.is_empty() {
+            self.out.push_str("||");
+        } else {
+            let parameters = lambda
+                .parameters
+                .iter()
+                .map(|(name, ty)| match ty {
+                    Some(ty) => format!("{}: {}", name, ty),
+                    None => name.clone(),
+                })
+                .collect::<Vec<_>>();
+            write!(self.out, "|{}|", parameters.join(", ")).unwrap();
+        }
+        match &lambda.body {
+            LambdaBody::Expression(body) => {
+                self.out.push(' ');
+                self.expression(body, LAMBDA, position);
+            }
+            LambdaBody::Block(body) => {
+                if let Some(return_type) = &lambda.return_type {
+                    write!(self.out, " -> {}", return_type).unwrap();
+                }
+                self.out.push(' ');
+                self.block(body, self.closing_brace(lambda.span));
+            }
+        }
+    }
+
+    fn arguments(&mut self, args: &[Expression]) {
+        self.out.push('(');
+        self.expressions(args);
+        self.out.push(')');
+    }
+
+    fn expressions(&mut self, expressions: &[Expression]) {
+        for (index, expression) in expressions.iter().enumerate() {
+            if index > 0 {
+                self.out.push_str(", ");
+            }
+            self.expression(expression, LAMBDA, Position::Anywhere);
+        }
+    }
+
+    fn write_indent(&mut self) {
+        for _ in 0..self.indent {
+            self.out.push_str(INDENT);
+        }
+    }
+
+    /// Prints a blank line if there is one in the source between the last thing printed and
+    /// `start`, unless it would be the first line of a block.
+    fn separate(&mut self, start: usize) {
+        let blank_line =
+            matches!(self.source.get(self.last..start), Some(text) if has_blank_line(text));
+        if blank_line && !self.block_start {
+            self.out.push('\n');
+        }
+        self.block_start = false;
+    }
+
+    /// Prints the comments before `offset` that are not printed yet, each on its own lines.
+    fn comments_before(&mut self, offset: usize) {
+        while let Some(&(start, text, end)) = self.comments.last() {
+            if start >= offset {
+                break;
+            }
+            self.comments.pop();
+            self.separate(start);
+            self.write_indent();
+            self.out.push_str(text);
+            self.out.push('\n');
+            self.last = end;
+        }
+    }
+
+    fn has_comments_before(&self, offset: Option<usize>) -> bool {
+        match (self.comments.last(), offset) {
+            (Some((start, _, _)), Some(offset)) => *start < offset,
+            _ => false,
+        }
+    }
+
+    /// Prints the comment after the end of a statement, an item or a match arm, if it is on
+    /// the same line.
+    fn trailing_comment(&mut self, end: usize) {
+        let (start, text, comment_end) = match self.comments.last() {
+            Some(&comment) => comment,
+            None => return,
+        };
+        let same_line = match self.source.get(end..start) {
+            Some(between) => between
+                .chars()
+                .all(|c| c == ';' || c == ',' || (c.is_whitespace() && c != '\n')),
+            None => false,
+        };
+        if same_line {
+            self.comments.pop();
+            write!(self.out, " {}", text).unwrap();
+            self.last = comment_end;
+        }
+    }
+
+    fn token_before(&self, offset: usize) -> Option<Spanned<Token<'a>>> {
+        let index = match self
+            .tokens
+            .binary_search_by_key(&offset, |(start, _, _)| *start)
+        {
+            Ok(index) | Err(index) => index,
+        };
+        index.checked_sub(1).map(|index| self.tokens[index])
+    }
+
+    /// The offset of the first `}` after `offset`, which closes a `match` after its last arm.
+    fn brace_after(&self, offset: usize) -> Option<usize> {
+        let index = match self
+            .tokens
+            .binary_search_by_key(&offset, |(start, _, _)| *start)
+        {
+            Ok(index) | Err(index) => index,
+        };
+        self.tokens[index..]
+            .iter()
+            .find(|(_, token, _)| matches!(token, Token::RightBrace))
+            .map(|(start, _, _)| *start)
+    }
+
+    /// The offset of the `}` at the end of a declaration, a statement, a lambda or a match
+    /// arm.
+    fn closing_brace(&self, span: Span) -> Option<usize> {
+        let close = span.end().to_usize().checked_sub(1)?;
+        if self.open_braces.contains_key(&close) {
+            Some(close)
+        } else {
+            None
+        }
+    }
+
+    /// The offset of the `}` before the `else` of an `if` statement. The `else` comes either
+    /// before the `if` of an `else if`, or before the block at the end of the statement.
+    fn then_brace(&self, span: Span, otherwise: &[Statement]) -> Option<usize> {
+        let is_else = |offset| matches!(self.token_before(offset), Some((_, Token::Else, _)));
+        let after_else = match otherwise {
+            [statement] if is_else(statement.span.start().to_usize()) => {
+                statement.span.start().to_usize()
+            }
+            _ => *self.open_braces.get(&self.closing_brace(span)?)?,
+        };
+        match self.token_before(after_else) {
+            Some((else_start, Token::Else, _)) => match self.token_before(else_start) {
+                Some((close, Token::RightBrace, _)) => Some(close),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+fn precedence_of(expression: &Expression) -> u8 {
+    match expression {
+        Expression::Lambda(_) => LAMBDA,
+        Expression::Binary(_, operator, _) => binary_precedence(*operator),
+        Expression::Unary(..) => UNARY,
+        Expression::Invocation(..)
+        | Expression::Call(..)
+        | Expression::FieldAccess(..)
+        | Expression::Index(..) => POSTFIX,
+        Expression::Variant(_, _, args) if !args.is_empty() => POSTFIX,
+        _ => PRIMARY,
+    }
+}
+
+fn binary_precedence(operator: BinaryOperator) -> u8 {
+    match operator {
+        BinaryOperator::Or => OR,
+        BinaryOperator::And => AND,
+        BinaryOperator::Equal
+        | BinaryOperator::NotEqual
+        | BinaryOperator::LessThan
+        | BinaryOperator::LessThanOrEqual
+        | BinaryOperator::GreaterThan
+        | BinaryOperator::GreaterThanOrEqual => COMPARISON,
+        BinaryOperator::Add | BinaryOperator::Subtract => SUM,
+        BinaryOperator::Multiply | BinaryOperator::Divide | BinaryOperator::Remainder => PRODUCT,
+    }
+}
+
+/// A string literal with the value, escaping the characters that cannot be in it as they are.
+fn string_literal(value: &str) -> String {
+    let mut literal = String::with_capacity(value.len() + 2);
+    literal.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            '\0' => literal.push_str("\\0"),
+            c if c.is_control() => write!(literal, "\\u{{{:x}}}", c as u32).unwrap(),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Whether a piece of source text has a line with nothing but whitespace.
+fn has_blank_line(text: &str) -> bool {
+    let lines = text.split('\n').collect::<Vec<_>>();
+    lines.len() > 2
+        && lines[1..lines.len() - 1]
+            .iter()
+            .any(|line| line.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::ProgramParser;
+    use pretty_assertions::assert_eq;
+
+    fn format_str(source: &str) -> String {
+        let program = ProgramParser::new()
+            .parse(&mut vec![], Lexer::new(source))
+            .unwrap();
+        let formatted = format(source, &program);
+
+        let reformatted = ProgramParser::new()
+            .parse(&mut vec![], Lexer::new(&formatted))
+            .unwrap();
+        assert_eq!(format(&formatted, &reformatted), formatted);
+        formatted
+    }
+
+    #[test]
+    fn formats_items_and_statements() {
+        let source = r#"import   "lib/utils.faang" ;use utils::helper;
+struct Point{x:int,y:int}  enum Shape {Circle(int), Square(int,int),Dot}
+/// Adds two numbers.
+///
+#[allow(unused_variables)]
+func add<T>(a:int,b:int)->int{let c:int=a+b;return c}
+test "adds"{assert(add(1,2)==3,"sum")}
+if x>1{println("big")}else if x<0{println("negative")}else{while x>0{x=x-1}}
+match shape {Shape::Circle(r)=>r,Shape::Dot=>{println("dot");},_=>0}
+let f=||1; let g=|n: int|->int{return n*2;}; let p=Point{x:1,y:2}; utils::name();"#;
+        assert_eq!(
+            format_str(source),
+            r#"import "lib/utils.faang";
+use utils::helper;
+struct Point {
+    x: int,
+    y: int,
+}
+enum Shape {
+    Circle(int),
+    Square(int, int),
+    Dot,
+}
+/// Adds two numbers.
+///
+#[allow(unused_variables)]
+func add<T>(a: int, b: int) -> int {
+    let c: int = a + b;
+    return c;
+}
+test "adds" {
+    assert(add(1, 2) == 3, "sum");
+}
+if x > 1 {
+    println("big");
+} else if x < 0 {
+    println("negative");
+} else {
+    while x > 0 {
+        x = x - 1;
+    }
+}
+match shape {
+    Shape::Circle(r) => r,
+    Shape::Dot => {
+        println("dot");
+    }
+    _ => 0,
+}
+let f = || 1;
+let g = |n: int| -> int {
+    return n * 2;
+};
+let p = Point { x: 1, y: 2 };
+utils::name();
+"#
+        );
+    }
+
+    #[test]
+    fn keeps_comments_and_blank_lines() {
+        let source = "// Header.
+
+
+/* A block
+   comment. */
+func id(n: int) -> int { // Trailing.
+
+    // Before.
+    let m = n;   // Also trailing.
+
+
+
+    return m;
+    // At the end.
+}
+println(/* inline */ \"done\")
+";
+        assert_eq!(
+            format_str(source),
+            "// Header.
+
+/* A block
+   comment. */
+func id(n: int) -> int {
+    // Trailing.
+
+    // Before.
+    let m = n; // Also trailing.
+
+    return m;
+    // At the end.
+}
+println(\"done\");
+/* inline */
+"
+        );
+    }
+
+    #[test]
+    fn keeps_comments_in_match_arms_and_lambdas() {
+        let source = "let double = |n: int| -> int {
+    return n * 2;
+    // At the end of a lambda.
+};
+match double(1) {
+    // Before an arm.
+    2 => println(\"two\"), // After an arm.
+    _ => {
+        println(\"other\");
+        // At the end of an arm.
+    }
+
+    // At the end of a match.
+}
+println(\"done\");
+";
+        assert_eq!(format_str(source), source);
+    }
+
+    #[test]
+    fn adds_needed_parentheses() {
+        let source = r#"let a = (1 + 2) * -(3 - 4) - (5 - 6);
+let b = (a == 1) == !(true || false && (true || false));
+if (Point { x: 1 }).x == 1 { }
+(match a { _ => f }).g = 1;
+(match a { _ => f })(1);
+let h = (|x| x)(1) + (|| 2)();
+let s = "quote \" backslash \\ tab \t line
+break";
+f(a[(1 + 2)].b, Option::None, -1)"#;
+        assert_eq!(
+            format_str(source),
+            r#"let a = (1 + 2) * -(3 - 4) - (5 - 6);
+let b = (a == 1) == !(true || false && (true || false));
+if (Point { x: 1 }).x == 1 {}
+(match a {
+    _ => f,
+}).g = 1;
+(match a {
+    _ => f,
+})(1);
+let h = (|x| x)(1) + (|| 2)();
+let s = "quote \" backslash \\ tab \t line\nbreak";
+f(a[1 + 2].b, Option::None, -1);
+"#
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Diagnostics as JSON objects, for editors and other tools that read the errors of the
+//! compiler.
+//!
+//! A range has the byte offsets of its start and end, along with their lines and columns,
+//! which count from 1.
+use codespan::{ByteIndex, FileId, Files, Span};
+use codespan_reporting::diagnostic::{Diagnostic, Label, Severity};
+use serde_json::{json, Value};
+
+/// A diagnostic with its severity, code, message, labels and notes. Its file and range are
+/// those of its primary label, which is the first of its labels.
+pub fn diagnostic(files: &Files, diagnostic: &Diagnostic) -> Value {
+    let primary_label = &diagnostic.primary_label;
+    let labels = std::iter::once(label(files, primary_label, true))
+        .chain(
+            diagnostic
+                .secondary_labels
+                .iter()
+                .map(|secondary_label| label(files, secondary_label, false)),
+        )
+        .collect::<Vec<_>>();
+
+    json!({
+        "severity": severity(diagnostic.severity),
+        "code": diagnostic.code,
+        "message": diagnostic.message,
+        "file": files.name(primary_label.file_id),
+        "range": range(files, primary_label.file_id, primary_label.span),
+        "labels": labels,
+        "notes": diagnostic.notes,
+    })
+}
+
+/// An error that is not about a place in a source file, in the form of a diagnostic without
+/// a file, a range or labels.
+pub fn error(message: &str) -> Value {
+    json!({
+        "severity": "error",
+        "code": null,
+        "message": message,
+        "file": null,
+        "range": null,
+        "labels": [],
+        "notes": [],
+    })
+}
+
+fn severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Bug => "bug",
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+        Severity::Note => "note",
+        Severity::Help => "help",
+    }
+}
+
+fn label(files: &Files, label: &Label, primary: bool) -> Value {
+    json!({
+        "primary": primary,
+        "message": label.message,
+        "file": files.name(label.file_id),
+        "range": range(files, label.file_id, label.span),
+    })
+}
+
+fn range(files: &Files, file_id: FileId, span: Span) -> Value {
+    json!({
+        "start": position(files, file_id, span.start()),
+        "end": position(files, file_id, span.end()),
+    })
+}
+
+fn position(files: &Files, file_id: FileId, byte: ByteIndex) -> Value {
+    match files.location(file_id, byte) {
+        Ok(location) => json!({
+            "byte": byte.to_usize(),
+            "line": location.line.0 + 1,
+            "column": location.column.0 + 1,
+        }),
+        Err(_) => json!({ "byte": byte.to_usize(), "line": null, "column": null }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn converts_diagnostics() {
+        let mut files = Files::new();
+        let file_id = files.add("main.faang", "let x = 10;\nprintln(\"a\";");
+        let diagnostic = Diagnostic::new_error(
+            "unrecognized token: ;",
+            Label::new(file_id, Span::new(23, 24), "expected `)`"),
+        )
+        .with_code("E0001")
+        .with_secondary_labels(vec![Label::new(
+            file_id,
+            Span::new(19, 20),
+            "unclosed delimiter",
+        )])
+        .with_notes(vec!["help: close the `(` with a `)`".to_string()]);
+
+        let range = json!({
+            "start": { "byte": 23, "line": 2, "column": 12 },
+            "end": { "byte": 24, "line": 2, "column": 13 },
+        });
+        assert_eq!(
+            super::diagnostic(&files, &diagnostic),
+            json!({
+                "severity": "error",
+                "code": "E0001",
+                "message": "unrecognized token: ;",
+                "file": "main.faang",
+                "range": range,
+                "labels": [
+                    {
+                        "primary": true,
+                        "message": "expected `)`",
+                        "file": "main.faang",
+                        "range": range,
+                    },
+                    {
+                        "primary": false,
+                        "message": "unclosed delimiter",
+                        "file": "main.faang",
+                        "range": {
+                            "start": { "byte": 19, "line": 2, "column": 8 },
+                            "end": { "byte": 20, "line": 2, "column": 9 },
+                        },
+                    },
+                ],
+                "notes": ["help: close the `(` with a `)`"],
+            })
+        );
+    }
+}
@@ -0,0 +1,727 @@
+//! Turning source text into the tokens the parser reads.
+//!
+//! Every token is spanned by the byte offsets of its first character and of the character
+//! after it, which is the form the parser expects.
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+use crate::error_codes;
+
+pub type Spanned<T> = (usize, T, usize);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Token<'input> {
+    Identifier(&'input str),
+    /// The digits of an integer literal, which the parser checks for overflow.
+    Integer(&'input str),
+    /// A string literal, with its quotes and escapes, which `unescape` reads the value of.
+    /// Either a `"..."` literal, which may span several lines, or a raw `r"..."` or
+    /// `r#"..."#` literal without escapes.
+    StringLiteral(&'input str),
+    /// A `//` or `/* */` comment, without the line break ending it. Only lexed by
+    /// `Lexer::with_comments`.
+    Comment(&'input str),
+    /// A `///` line documenting the function after it, without the line break ending it.
+    DocComment(&'input str),
+
+    Func,
+    Struct,
+    Let,
+    Return,
+    If,
+    Else,
+    While,
+    Enum,
+    Match,
+    Import,
+    Use,
+    Test,
+    True,
+    False,
+    Null,
+    Int,
+    Bool,
+    String,
+    File,
+
+    LeftParen,
+    RightParen,
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Comma,
+    Semicolon,
+    Colon,
+    ColonColon,
+    Dot,
+    Arrow,
+    FatArrow,
+    Underscore,
+    Pipe,
+    PipePipe,
+    AmpAmp,
+    Bang,
+    BangEqual,
+    Equal,
+    EqualEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    Hash,
+}
+
+impl<'input> Token<'input> {
+    fn keyword(text: &str) -> Option<Token<'static>> {
+        Some(match text {
+            "func" => Token::Func,
+            "struct" => Token::Struct,
+            "let" => Token::Let,
+            "return" => Token::Return,
+            "if" => Token::If,
+            "else" => Token::Else,
+            "while" => Token::While,
+            "enum" => Token::Enum,
+            "match" => Token::Match,
+            "import" => Token::Import,
+            "use" => Token::Use,
+            "test" => Token::Test,
+            "true" => Token::True,
+            "false" => Token::False,
+            "null" => Token::Null,
+            "int" => Token::Int,
+            "bool" => Token::Bool,
+            "string" => Token::String,
+            "File" => Token::File,
+            "_" => Token::Underscore,
+            _ => return None,
+        })
+    }
+
+    /// The source text of the token.
+    pub fn text(&self) -> &'input str {
+        match self {
+            Token::Identifier(text)
+            | Token::Integer(text)
+            | Token::StringLiteral(text)
+            | Token::Comment(text)
+            | Token::DocComment(text) => text,
+            Token::Func => "func",
+            Token::Struct => "struct",
+            Token::Let => "let",
+            Token::Return => "return",
+            Token::If => "if",
+            Token::Else => "else",
+            Token::While => "while",
+            Token::Enum => "enum",
+            Token::Match => "match",
+            Token::Import => "import",
+            Token::Use => "use",
+            Token::Test => "test",
+            Token::True => "true",
+            Token::False => "false",
+            Token::Null => "null",
+            Token::Int => "int",
+            Token::Bool => "bool",
+            Token::String => "string",
+            Token::File => "File",
+            Token::LeftParen => "(",
+            Token::RightParen => ")",
+            Token::LeftBrace => "{",
+            Token::RightBrace => "}",
+            Token::LeftBracket => "[",
+            Token::RightBracket => "]",
+            Token::Comma => ",",
+            Token::Semicolon => ";",
+            Token::Colon => ":",
+            Token::ColonColon => "::",
+            Token::Dot => ".",
+            Token::Arrow => "->",
+            Token::FatArrow => "=>",
+            Token::Underscore => "_",
+            Token::Pipe => "|",
+            Token::PipePipe => "||",
+            Token::AmpAmp => "&&",
+            Token::Bang => "!",
+            Token::BangEqual => "!=",
+            Token::Equal => "=",
+            Token::EqualEqual => "==",
+            Token::Less => "<",
+            Token::LessEqual => "<=",
+            Token::Greater => ">",
+            Token::GreaterEqual => ">=",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Star => "*",
+            Token::Slash => "/",
+            Token::Percent => "%",
+            Token::Hash => "#",
+        }
+    }
+}
+
+impl<'input> fmt::Display for Token<'input> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LexicalError {
+    /// A character that does not start any token.
+    UnexpectedCharacter { location: usize, character: char },
+    /// A string literal that is still open at the end of the source, spanning the quote that
+    /// opens it.
+    UnterminatedString { start: usize, end: usize },
+    /// An escape sequence in a string literal that is not one of the known escapes.
+    UnknownEscape {
+        start: usize,
+        end: usize,
+        character: char,
+    },
+    /// A `\u{...}` escape without the hex digits of a character.
+    InvalidUnicodeEscape { start: usize, end: usize },
+    /// A block comment that is still open at the end of the source.
+    UnterminatedComment { start: usize, end: usize },
+    /// An integer literal that does not fit into an `int`. Reported by the parser.
+    IntegerTooLarge { start: usize, end: usize },
+}
+
+impl LexicalError {
+    /// The code of the error, which `faang explain` describes.
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexicalError::UnexpectedCharacter { .. } => error_codes::UNEXPECTED_CHARACTER,
+            LexicalError::UnterminatedString { .. } => error_codes::UNTERMINATED_STRING,
+            LexicalError::UnterminatedComment { .. } => error_codes::UNTERMINATED_COMMENT,
+            LexicalError::UnknownEscape { .. } => error_codes::UNKNOWN_ESCAPE,
+            LexicalError::InvalidUnicodeEscape { .. } => error_codes::INVALID_UNICODE_ESCAPE,
+            LexicalError::IntegerTooLarge { .. } => error_codes::INTEGER_TOO_LARGE,
+        }
+    }
+
+    /// The byte offsets of the text the error is about.
+    pub fn span(&self) -> (usize, usize) {
+        match self {
+            LexicalError::UnexpectedCharacter {
+                location,
+                character,
+            } => (*location, location + character.len_utf8()),
+            LexicalError::UnterminatedString { start, end }
+            | LexicalError::UnknownEscape { start, end, .. }
+            | LexicalError::InvalidUnicodeEscape { start, end }
+            | LexicalError::UnterminatedComment { start, end }
+            | LexicalError::IntegerTooLarge { start, end } => (*start, *end),
+        }
+    }
+}
+
+impl fmt::Display for LexicalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LexicalError::UnexpectedCharacter { character, .. } => {
+                write!(f, "unexpected character `{}`", character.escape_debug())
+            }
+            LexicalError::UnterminatedString { .. } => write!(f, "unterminated string literal"),
+            LexicalError::UnknownEscape { character, .. } => {
+                write!(
+                    f,
+                    "unknown character escape `\\{}`",
+                    character.escape_debug()
+                )
+            }
+            LexicalError::InvalidUnicodeEscape { .. } => write!(
+                f,
+                "invalid unicode escape, expected up to 6 hex digits of a character in `\\u{{...}}`"
+            ),
+            LexicalError::UnterminatedComment { .. } => write!(f, "unterminated block comment"),
+            LexicalError::IntegerTooLarge { .. } => {
+                write!(f, "integer literal is too large for an `int`")
+            }
+        }
+    }
+}
+
+/// Reads the tokens of a source text, skipping whitespace. Comments are skipped too, unless
+/// the lexer is created with `with_comments`, but doc comments never are.
+pub struct Lexer<'input> {
+    source: &'input str,
+    chars: Peekable<CharIndices<'input>>,
+    comments: bool,
+}
+
+impl<'input> Lexer<'input> {
+    pub fn new(source: &'input str) -> Lexer<'input> {
+        Lexer {
+            source,
+            chars: source.char_indices().peekable(),
+            comments: false,
+        }
+    }
+
+    /// A lexer that also produces `Token::Comment`s, for tools that keep them.
+    pub fn with_comments(source: &'input str) -> Lexer<'input> {
+        Lexer {
+            comments: true,
+            ..Lexer::new(source)
+        }
+    }
+
+    /// The offset of the next character, or of the end of the source.
+    fn offset(&mut self) -> usize {
+        self.chars
+            .peek()
+            .map_or(self.source.len(), |(offset, _)| *offset)
+    }
+
+    /// Consumes characters while `predicate` holds, returning the offset after them.
+    fn take_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> usize {
+        while let Some((_, c)) = self.chars.peek() {
+            if !predicate(*c) {
+                break;
+            }
+            self.chars.next();
+        }
+        self.offset()
+    }
+
+    /// Whether there is a next character and it satisfies `predicate`.
+    fn next_is<F: Fn(char) -> bool>(&mut self, predicate: F) -> bool {
+        matches!(self.chars.peek(), Some((_, c)) if predicate(*c))
+    }
+
+    /// Consumes `next` if it is the next character.
+    fn eat(&mut self, next: char) -> bool {
+        match self.chars.peek() {
+            Some((_, c)) if *c == next => {
+                self.chars.next();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Consumes a block comment after its opening `/*`, along with the comments nested in it.
+    fn block_comment(&mut self, start: usize) -> Result<usize, LexicalError> {
+        let mut depth = 1;
+        while let Some((_, c)) = self.chars.next() {
+            match c {
+                '/' if self.eat('*') => depth += 1,
+                '*' if self.eat('/') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Ok(self.offset());
+                    }
+                }
+                _ => {}
+            }
+        }
+        Err(LexicalError::UnterminatedComment {
+            start,
+            end: self.source.len(),
+        })
+    }
+
+    fn string(&mut self, start: usize) -> Result<Spanned<Token<'input>>, LexicalError> {
+        loop {
+            match self.chars.next() {
+                Some((end, '"')) => {
+                    let end = end + 1;
+                    return Ok((start, Token::StringLiteral(&self.source[start..end]), end));
+                }
+                Some((_, '\\')) => {
+                    self.chars.next();
+                }
+                Some(_) => {}
+                None => {
+                    return Err(LexicalError::UnterminatedString {
+                        start,
+                        end: start + 1,
+                    })
+                }
+            }
+        }
+    }
+
+    /// Lexes a raw string literal after its `r`. It is closed by a quote followed by as many
+    /// `#`s as the quote that opens it is preceded by.
+    fn raw_string(&mut self, start: usize) -> Result<Spanned<Token<'input>>, LexicalError> {
+        let hashes = self.take_while(|c| c == '#') - start - 1;
+        if !self.eat('"') {
+            return Err(LexicalError::UnexpectedCharacter {
+                location: start + 1,
+                character: '#',
+            });
+        }
+        let opening_end = self.offset();
+
+        while let Some((_, c)) = self.chars.next() {
+            if c == '"' {
+                let mut closing_hashes = 0;
+                while closing_hashes < hashes && self.eat('#') {
+                    closing_hashes += 1;
+                }
+                if closing_hashes == hashes {
+                    let end = self.offset();
+                    return Ok((start, Token::StringLiteral(&self.source[start..end]), end));
+                }
+            }
+        }
+        Err(LexicalError::UnterminatedString {
+            start,
+            end: opening_end,
+        })
+    }
+}
+
+impl<'input> Iterator for Lexer<'input> {
+    type Item = Result<Spanned<Token<'input>>, LexicalError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (start, c) = self.chars.next()?;
+            let token = match c {
+                c if c.is_whitespace() => continue,
+                '"' => return Some(self.string(start)),
+                'r' if self.next_is(|c| c == '"' || c == '#') => {
+                    return Some(self.raw_string(start))
+                }
+                c if c == '_' || c.is_ascii_alphabetic() => {
+                    let end = self.take_while(|c| c == '_' || c.is_ascii_alphanumeric());
+                    let text = &self.source[start..end];
+                    let token = Token::keyword(text).unwrap_or(Token::Identifier(text));
+                    return Some(Ok((start, token, end)));
+                }
+                c if c.is_ascii_digit() => {
+                    let end = self.take_while(|c| c.is_ascii_digit());
+                    return Some(Ok((start, Token::Integer(&self.source[start..end]), end)));
+                }
+                '/' if self.eat('/') => {
+                    let end = self.take_while(|c| c != '\n' && c != '\r');
+                    let text = &self.source[start..end];
+                    // Like in Rust, a line starting with four slashes is a plain comment.
+                    if text.starts_with("///") && !text.starts_with("////") {
+                        Token::DocComment(text)
+                    } else if self.comments {
+                        Token::Comment(text)
+                    } else {
+                        continue;
+                    }
+                }
+                '/' if self.eat('*') => match self.block_comment(start) {
+                    Err(err) => return Some(Err(err)),
+                    Ok(end) if self.comments => Token::Comment(&self.source[start..end]),
+                    Ok(_) => continue,
+                },
+                '(' => Token::LeftParen,
+                ')' => Token::RightParen,
+                '{' => Token::LeftBrace,
+                '}' => Token::RightBrace,
+                '[' => Token::LeftBracket,
+                ']' => Token::RightBracket,
+                ',' => Token::Comma,
+                ';' => Token::Semicolon,
+                '.' => Token::Dot,
+                '+' => Token::Plus,
+                '*' => Token::Star,
+                '/' => Token::Slash,
+                '%' => Token::Percent,
+                '#' => Token::Hash,
+                ':' if self.eat(':') => Token::ColonColon,
+                ':' => Token::Colon,
+                '-' if self.eat('>') => Token::Arrow,
+                '-' => Token::Minus,
+                '=' if self.eat('>') => Token::FatArrow,
+                '=' if self.eat('=') => Token::EqualEqual,
+                '=' => Token::Equal,
+                '|' if self.eat('|') => Token::PipePipe,
+                '|' => Token::Pipe,
+                '&' if self.eat('&') => Token::AmpAmp,
+                '!' if self.eat('=') => Token::BangEqual,
+                '!' => Token::Bang,
+                '<' if self.eat('=') => Token::LessEqual,
+                '<' => Token::Less,
+                '>' if self.eat('=') => Token::GreaterEqual,
+                '>' => Token::Greater,
+                character => {
+                    return Some(Err(LexicalError::UnexpectedCharacter {
+                        location: start,
+                        character,
+                    }))
+                }
+            };
+            return Some(Ok((start, token, self.offset())));
+        }
+    }
+}
+
+/// The value of a `Token::StringLiteral` that starts at `offset` in the source. Fails with
+/// the invalid escapes in it.
+pub fn unescape(literal: &str, offset: usize) -> Result<String, Vec<LexicalError>> {
+    if let Some(raw) = literal.strip_prefix('r') {
+        let hashes = raw.find('"').unwrap();
+        return Ok(raw[hashes + 1..raw.len() - hashes - 1].to_string());
+    }
+
+    let mut value = String::with_capacity(literal.len());
+    let mut errors = vec![];
+    let contents = &literal[1..literal.len() - 1];
+    let mut chars = contents.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        let start = offset + 1 + index;
+        let escaped = match chars.next() {
+            Some((_, 'n')) => '\n',
+            Some((_, 'r')) => '\r',
+            Some((_, 't')) => '\t',
+            Some((_, '0')) => '\0',
+            Some((_, '\\')) => '\\',
+            Some((_, '"')) => '"',
+            Some((_, '\'')) => '\'',
+            // A line break after a backslash is skipped along with the indentation after it.
+            Some((_, '\n')) | Some((_, '\r')) => {
+                while matches!(chars.peek(), Some((_, c)) if c.is_whitespace()) {
+                    chars.next();
+                }
+                continue;
+            }
+            Some((_, 'u')) => match unicode_escape(&mut chars) {
+                Some(escaped) => escaped,
+                None => {
+                    let end = chars.peek().map_or(contents.len(), |(end, _)| *end);
+                    errors.push(LexicalError::InvalidUnicodeEscape {
+                        start,
+                        end: offset + 1 + end,
+                    });
+                    continue;
+                }
+            },
+            Some((index, character)) => {
+                errors.push(LexicalError::UnknownEscape {
+                    start,
+                    end: offset + 1 + index + character.len_utf8(),
+                    character,
+                });
+                continue;
+            }
+            // The lexer does not let a backslash escape the closing quote.
+            None => break,
+        };
+        value.push(escaped);
+    }
+
+    if errors.is_empty() {
+        Ok(value)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Reads the `{...}` of a `\u{...}` escape, up to the `}` or the first character that cannot
+/// be a part of it.
+fn unicode_escape(chars: &mut Peekable<CharIndices>) -> Option<char> {
+    if !matches!(chars.peek(), Some((_, '{'))) {
+        return None;
+    }
+    chars.next();
+    let mut digits = String::new();
+    loop {
+        match chars.peek() {
+            Some((_, '}')) => {
+                chars.next();
+                break;
+            }
+            Some((_, c)) if c.is_ascii_alphanumeric() => {
+                digits.push(*c);
+                chars.next();
+            }
+            _ => return None,
+        }
+    }
+    if digits.len() > 6 {
+        return None;
+    }
+    u32::from_str_radix(&digits, 16)
+        .ok()
+        .and_then(std::char::from_u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn tokens(source: &str) -> Vec<Token<'_>> {
+        Lexer::with_comments(source)
+            .map(|token| token.unwrap().1)
+            .collect()
+    }
+
+    #[test]
+    fn lexes_identifiers_and_keywords() {
+        assert_eq!(
+            tokens("func f(x: int) _ _x File"),
+            vec![
+                Token::Func,
+                Token::Identifier("f"),
+                Token::LeftParen,
+                Token::Identifier("x"),
+                Token::Colon,
+                Token::Int,
+                Token::RightParen,
+                Token::Underscore,
+                Token::Identifier("_x"),
+                Token::File,
+            ]
+        );
+    }
+
+    #[test]
+    fn lexes_operators() {
+        assert_eq!(
+            tokens("a::b -> => == = != ! <= < >= > || | && - / % #"),
+            vec![
+                Token::Identifier("a"),
+                Token::ColonColon,
+                Token::Identifier("b"),
+                Token::Arrow,
+                Token::FatArrow,
+                Token::EqualEqual,
+                Token::Equal,
+                Token::BangEqual,
+                Token::Bang,
+                Token::LessEqual,
+                Token::Less,
+                Token::GreaterEqual,
+                Token::Greater,
+                Token::PipePipe,
+                Token::Pipe,
+                Token::AmpAmp,
+                Token::Minus,
+                Token::Slash,
+                Token::Percent,
+                Token::Hash,
+            ]
+        );
+    }
+
+    #[test]
+    fn lexes_literals_and_comments() {
+        let source = "let s = \"a \\\"b\\\"\"; // the end\n42";
+        assert_eq!(
+            Lexer::with_comments(source)
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap(),
+            vec![
+                (0, Token::Let, 3),
+                (4, Token::Identifier("s"), 5),
+                (6, Token::Equal, 7),
+                (8, Token::StringLiteral("\"a \\\"b\\\"\""), 17),
+                (17, Token::Semicolon, 18),
+                (19, Token::Comment("// the end"), 29),
+                (30, Token::Integer("42"), 32),
+            ]
+        );
+        assert_eq!(
+            Lexer::new(source).map(|token| token.unwrap().1).nth(5),
+            Some(Token::Integer("42"))
+        );
+    }
+
+    #[test]
+    fn lexes_raw_and_multi_line_strings() {
+        assert_eq!(
+            tokens("r\"a\\b\" r#\"say \"hi\"\"# \"one\ntwo\" rr"),
+            vec![
+                Token::StringLiteral("r\"a\\b\""),
+                Token::StringLiteral("r#\"say \"hi\"\"#"),
+                Token::StringLiteral("\"one\ntwo\""),
+                Token::Identifier("rr"),
+            ]
+        );
+    }
+
+    #[test]
+    fn unescapes_string_literals() {
+        assert_eq!(
+            unescape(r#""a\tb\\\"\u{48}\u{1F600}""#, 0),
+            Ok("a\tb\\\"H\u{1F600}".to_string())
+        );
+        assert_eq!(
+            unescape("\"one \\\n    two\"", 0),
+            Ok("one two".to_string())
+        );
+        assert_eq!(unescape(r##"r#"\q"#"##, 0), Ok(r"\q".to_string()));
+        assert_eq!(
+            unescape(r#""\q \u{D800} \u41 \u{zz}""#, 10),
+            Err(vec![
+                LexicalError::UnknownEscape {
+                    start: 11,
+                    end: 13,
+                    character: 'q',
+                },
+                LexicalError::InvalidUnicodeEscape { start: 14, end: 22 },
+                LexicalError::InvalidUnicodeEscape { start: 23, end: 25 },
+                LexicalError::InvalidUnicodeEscape { start: 28, end: 34 },
+            ])
+        );
+    }
+
+    #[test]
+    fn lexes_block_and_doc_comments() {
+        assert_eq!(
+            tokens("/* a /* nested */ comment */ x /**/ //// rule\n/// Adds.\nfunc"),
+            vec![
+                Token::Comment("/* a /* nested */ comment */"),
+                Token::Identifier("x"),
+                Token::Comment("/**/"),
+                Token::Comment("//// rule"),
+                Token::DocComment("/// Adds."),
+                Token::Func,
+            ]
+        );
+        assert_eq!(
+            Lexer::new("a /* b */ c /// d")
+                .map(|token| token.unwrap().1)
+                .collect::<Vec<_>>(),
+            vec![
+                Token::Identifier("a"),
+                Token::Identifier("c"),
+                Token::DocComment("/// d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_lexical_errors() {
+        assert_eq!(
+            Lexer::new("let x = 1 $ 2").nth(4),
+            Some(Err(LexicalError::UnexpectedCharacter {
+                location: 10,
+                character: '$',
+            }))
+        );
+        assert_eq!(
+            Lexer::new("println(\"oops)").nth(2),
+            Some(Err(LexicalError::UnterminatedString { start: 8, end: 9 }))
+        );
+        assert_eq!(
+            Lexer::new("x = r#\"a\"b\"").nth(2),
+            Some(Err(LexicalError::UnterminatedString { start: 4, end: 7 }))
+        );
+        assert_eq!(
+            Lexer::new("x /* a /* b */").nth(1),
+            Some(Err(LexicalError::UnterminatedComment { start: 2, end: 14 }))
+        );
+    }
+}
@@ -4,80 +4,262 @@ use codespan::{FileId, Span};
 use codespan_reporting::diagnostic;
 use codespan_reporting::diagnostic::Diagnostic;
 
+use lexer::Token;
+
 pub mod ast;
 pub mod compiler;
+pub mod error_codes;
+pub mod format;
+pub mod json;
+pub mod lexer;
+pub mod lints;
+pub mod manifest;
+pub mod modules;
+pub mod output;
+pub mod package;
 #[allow(dead_code)]
 pub mod parser;
+pub mod session;
+pub mod testing;
+
+pub use session::Session;
+
+pub fn parse(text: &str, file_id: FileId) -> Result<ast::Program, Vec<Diagnostic>> {
+    match parse_partial(text, file_id) {
+        (Some(program), diagnostics) if diagnostics.is_empty() => Ok(program),
+        (_, diagnostics) => Err(diagnostics),
+    }
+}
 
-pub fn parse(text: &str, file_id: FileId) -> Result<ast::Expression, Vec<Diagnostic>> {
-    let program_parser = parser::ExpressionParser::new();
+/// Parses a program like `parse`, but returns the syntax tree along with the diagnostics.
+/// The parser recovers from most syntax errors, leaving an `Error` node where each one is,
+/// so there is usually a tree even when there are errors.
+pub fn parse_partial(text: &str, file_id: FileId) -> (Option<ast::Program>, Vec<Diagnostic>) {
+    let program_parser = parser::ProgramParser::new();
     let mut errors = vec![];
-    let program_result = program_parser.parse(&mut errors, &text);
+    let program = match program_parser.parse(&mut errors, lexer::Lexer::new(text)) {
+        Ok(program) => Some(program),
+        Err(err) => {
+            errors.push(err);
+            None
+        }
+    };
     let mut diagnostics = vec![];
 
-    if errors.len() > 0 || program_result.is_err() {
-        if program_result.is_err() {
-            errors.push(program_result.unwrap_err());
+    for err in &errors {
+        match err {
+            ParseError::UnrecognizedToken { token, expected } => {
+                let (start, input, end) = token;
+                let label = diagnostic::Label::new(
+                    file_id,
+                    Span::new((*start) as u32, (*end) as u32),
+                    describe_expected(expected),
+                );
+                let diagnostic = diagnostic::Diagnostic::new_error(
+                    format!("unrecognized token: {}", input),
+                    label,
+                )
+                .with_code(error_codes::UNRECOGNIZED_TOKEN);
+                diagnostics.push(with_help(
+                    diagnostic,
+                    &text[..*start],
+                    file_id,
+                    Some(input),
+                    expected,
+                ));
+            }
+            ParseError::UnrecognizedEOF { location, expected } => {
+                let start = location;
+                let end = start;
+                let label = diagnostic::Label::new(
+                    file_id,
+                    Span::new((*start) as u32, (*end) as u32),
+                    describe_expected(expected),
+                );
+                let diagnostic = diagnostic::Diagnostic::new_error("unexpected end of file", label)
+                    .with_code(error_codes::UNEXPECTED_EOF);
+                diagnostics.push(with_help(diagnostic, text, file_id, None, expected));
+            }
+            ParseError::InvalidToken { location } => {
+                let start = location;
+                let end = start;
+                let label = diagnostic::Label::new(
+                    file_id,
+                    Span::new((*start) as u32, (*end) as u32),
+                    "invalid token",
+                );
+                let diagnostic = diagnostic::Diagnostic::new_error(format!("invalid token"), label)
+                    .with_code(error_codes::UNEXPECTED_CHARACTER);
+                diagnostics.push(diagnostic);
+            }
+            ParseError::ExtraToken { token } => {
+                let (start, input, end) = token;
+                let label = diagnostic::Label::new(
+                    file_id,
+                    Span::new((*start) as u32, (*end) as u32),
+                    "unrecognized token",
+                );
+                let diagnostic = diagnostic::Diagnostic::new_error(
+                    format!("unrecognized token: {}", input),
+                    label,
+                )
+                .with_code(error_codes::UNRECOGNIZED_TOKEN);
+                diagnostics.push(diagnostic);
+            }
+            ParseError::User { error } => diagnostics.push(lexical_error(error, file_id)),
         }
+    }
 
-        for err in &errors {
-            match err {
-                ParseError::UnrecognizedToken { token, expected } => {
-                    let (start, input, end) = token;
-                    let label = diagnostic::Label::new(
-                        file_id,
-                        Span::new((*start) as u32, (*end) as u32),
-                        format!("expected one of {} here", expected.join(", ")),
-                    );
-                    let diagnostic = diagnostic::Diagnostic::new_error(
-                        format!("unrecognized token: {}", input),
-                        label,
-                    );
-                    diagnostics.push(diagnostic);
-                }
-                ParseError::UnrecognizedEOF { location, expected } => {
-                    let start = location;
-                    let end = start;
-                    let label = diagnostic::Label::new(
-                        file_id,
-                        Span::new((*start) as u32, (*end) as u32),
-                        format!("expected one of {} here", expected.join(", ")),
-                    );
-                    let diagnostic =
-                        diagnostic::Diagnostic::new_error(format!("unrecognized EOF"), label);
-                    diagnostics.push(diagnostic);
-                }
-                ParseError::InvalidToken { location } => {
-                    let start = location;
-                    let end = start;
-                    let label = diagnostic::Label::new(
-                        file_id,
-                        Span::new((*start) as u32, (*end) as u32),
-                        "invalid token",
-                    );
-                    let diagnostic =
-                        diagnostic::Diagnostic::new_error(format!("invalid token"), label);
-                    diagnostics.push(diagnostic);
-                }
-                ParseError::ExtraToken { token } => {
-                    let (start, input, end) = token;
-                    let label = diagnostic::Label::new(
-                        file_id,
-                        Span::new((*start) as u32, (*end) as u32),
-                        "unrecognized token",
-                    );
-                    let diagnostic = diagnostic::Diagnostic::new_error(
-                        format!("unrecognized token: {}", input),
-                        label,
-                    );
-                    diagnostics.push(diagnostic);
-                }
-                _ => {}
-            }
+    (program, diagnostics)
+}
+
+/// Splits a source file into tokens, failing with the diagnostics of its lexical errors.
+pub fn tokenize(
+    text: &str,
+    file_id: FileId,
+) -> Result<Vec<lexer::Spanned<Token<'_>>>, Vec<Diagnostic>> {
+    let mut tokens = vec![];
+    let mut diagnostics = vec![];
+    for result in lexer::Lexer::new(text) {
+        match result {
+            Ok(token) => tokens.push(token),
+            Err(error) => diagnostics.push(lexical_error(&error, file_id)),
         }
+    }
 
-        return Err(diagnostics);
+    if diagnostics.is_empty() {
+        Ok(tokens)
+    } else {
+        Err(diagnostics)
+    }
+}
+
+fn lexical_error(error: &lexer::LexicalError, file_id: FileId) -> Diagnostic {
+    let (start, end) = error.span();
+    let label = diagnostic::Label::new(
+        file_id,
+        Span::new(start as u32, end as u32),
+        error.to_string(),
+    );
+    diagnostic::Diagnostic::new_error(error.to_string(), label).with_code(error.code())
+}
+
+/// Lists the terminals the parser expected by the names they are known by in the language.
+fn describe_expected(expected: &[String]) -> String {
+    let mut names = Vec::<String>::new();
+    for terminal in expected {
+        let name = match terminal.trim_matches('"') {
+            "Identifier" => "identifier".to_string(),
+            "Integer" => "integer".to_string(),
+            "StringLiteral" => "string literal".to_string(),
+            "DocComment" => "doc comment".to_string(),
+            token => format!("`{}`", token),
+        };
+        if !names.contains(&name) {
+            names.push(name);
+        }
     }
 
-    Ok(program_result.unwrap())
+    match names.split_last() {
+        None => "not expected here".to_string(),
+        Some((last, [])) => format!("expected {}", last),
+        Some((last, names)) => format!("expected one of {} or {}", names.join(", "), last),
+    }
+}
+
+fn expects(expected: &[String], token: &str) -> bool {
+    expected
+        .iter()
+        .any(|terminal| terminal.trim_matches('"') == token)
+}
+
+/// Adds a hint at the fix of a common mistake that makes the parser find `found` after
+/// `before` where it expected one of `expected`. `found` is `None` at the end of the file.
+fn with_help(
+    diagnostic: Diagnostic,
+    before: &str,
+    file_id: FileId,
+    found: Option<&Token>,
+    expected: &[String],
+) -> Diagnostic {
+    let unclosed = unclosed_delimiter(before);
+    let closing = unclosed.map(|(_, delimiter)| match delimiter {
+        Token::LeftParen => ")",
+        Token::LeftBracket => "]",
+        _ => "}",
+    });
+    let at_end = match found {
+        None
+        | Some(Token::Semicolon)
+        | Some(Token::RightParen)
+        | Some(Token::RightBracket)
+        | Some(Token::RightBrace) => true,
+        Some(_) => false,
+    };
+    if let (Some((start, delimiter)), Some(closing)) = (unclosed, closing) {
+        if at_end && expects(expected, closing) {
+            let label = diagnostic::Label::new(
+                file_id,
+                Span::new(start as u32, start as u32 + 1),
+                "unclosed delimiter",
+            );
+            return diagnostic
+                .with_secondary_labels(vec![label])
+                .with_notes(vec![format!(
+                    "help: close the `{}` with a `{}`",
+                    delimiter, closing
+                )]);
+        }
+    }
+
+    let help = match found {
+        Some(Token::DocComment(_)) => {
+            "doc comments can only document functions, use `//` for other comments"
+        }
+        // Only a function can follow a doc comment.
+        _ if expects(expected, "DocComment") && !expects(expected, "let") => {
+            "doc comments can only document functions, use `//` for other comments"
+        }
+        Some(Token::Equal) if expects(expected, "==") => "use `==` to compare values",
+        Some(_) if expects(expected, ";") && matches!(closing, None | Some("}")) => {
+            "statements are separated by `;`"
+        }
+        _ => return diagnostic,
+    };
+    diagnostic.with_notes(vec![format!("help: {}", help)])
+}
+
+/// The innermost delimiter that is opened in `before` and not closed.
+fn unclosed_delimiter(before: &str) -> Option<(usize, Token<'_>)> {
+    let mut open = vec![];
+    for (start, token, _) in lexer::Lexer::new(before).filter_map(Result::ok) {
+        match token {
+            Token::LeftParen | Token::LeftBracket | Token::LeftBrace => open.push((start, token)),
+            Token::RightParen | Token::RightBracket | Token::RightBrace => {
+                open.pop();
+            }
+            _ => {}
+        }
+    }
+    open.pop()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codespan::Files;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn keeps_the_recovered_program() {
+        let mut files = Files::new();
+        let source = "let = 3;\nlet x = 1;";
+        let file_id = files.add("main.faang", source);
+        let (program, diagnostics) = parse_partial(source, file_id);
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "unrecognized token: =");
+        assert_eq!(program.map(|program| program.items.len()), Some(2));
+        assert!(parse(source, file_id).is_err());
+    }
 }
@@ -0,0 +1,757 @@
+//! Warnings about code that compiles but is probably a mistake.
+//!
+//! Every lint has a level: allowed lints are not reported, warned ones are reported as
+//! warnings and denied ones as errors. Lints are warned about by default. Their levels are set
+//! for the whole program with the `-A`, `-W` and `-D` flags, and for a single function with
+//! the `#[allow(...)]`, `#[warn(...)]` and `#[deny(...)]` attributes, which take the names of
+//! lints or `warnings` for all of them.
+use codespan::{FileId, Files, Span};
+use codespan_reporting::diagnostic::{Diagnostic, Label, Severity};
+use std::collections::HashMap;
+
+use crate::ast::{
+    Attribute, Expression, Function, Item, LambdaBody, MatchArmBody, Pattern, Program, Statement,
+    StatementKind,
+};
+use crate::lexer::Lexer;
+use crate::modules::Module;
+
+/// The name that stands for every lint.
+pub const WARNINGS: &str = "warnings";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// A variable declared with `let` that is never read.
+    UnusedVariables,
+    /// A function that is not called or referred to outside of itself.
+    UnusedFunctions,
+    /// A statement after a `return`.
+    UnreachableCode,
+    /// A `let` of a variable with the same name as a variable in scope.
+    Shadowing,
+}
+
+impl Lint {
+    pub const ALL: [Lint; 4] = [
+        Lint::UnusedVariables,
+        Lint::UnusedFunctions,
+        Lint::UnreachableCode,
+        Lint::Shadowing,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Lint::UnusedVariables => "unused_variables",
+            Lint::UnusedFunctions => "unused_functions",
+            Lint::UnreachableCode => "unreachable_code",
+            Lint::Shadowing => "shadowing",
+        }
+    }
+}
+
+/// The lints with a name, which is either the name of a lint or `warnings`.
+fn lints_named(name: &str) -> Option<&'static [Lint]> {
+    let all: &'static [Lint] = &Lint::ALL;
+    if name == WARNINGS {
+        return Some(all);
+    }
+    let index = all.iter().position(|lint| lint.name() == name)?;
+    Some(&all[index..=index])
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Level {
+    Allow,
+    Warn,
+    Deny,
+}
+
+impl Level {
+    /// The name of the attribute setting the level.
+    fn attribute(self) -> &'static str {
+        match self {
+            Level::Allow => "allow",
+            Level::Warn => "warn",
+            Level::Deny => "deny",
+        }
+    }
+
+    /// The command line flag setting the level.
+    fn flag(self) -> &'static str {
+        match self {
+            Level::Allow => "-A",
+            Level::Warn => "-W",
+            Level::Deny => "-D",
+        }
+    }
+
+    fn from_attribute(name: &str) -> Option<Level> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+/// Where the level of a lint comes from, which its diagnostics point out.
+#[derive(Clone, Debug)]
+enum Source {
+    Default,
+    /// A command line flag, such as `-D warnings`.
+    CommandLine(String),
+    Attribute(FileId, Span),
+}
+
+/// The level of every lint.
+#[derive(Clone, Debug, Default)]
+pub struct LintLevels {
+    levels: HashMap<Lint, (Level, Source)>,
+}
+
+impl LintLevels {
+    /// Sets the level of the lints with a name, as with a command line flag. A `-` in the name
+    /// stands for a `_`, so that `-A unused-variables` works too.
+    pub fn set(&mut self, name: &str, level: Level) -> Result<(), String> {
+        let lints = lints_named(&name.replace('-', "_"))
+            .ok_or_else(|| format!("unknown lint: `{}`", name))?;
+        for lint in lints {
+            let flag = format!("{} {}", level.flag(), name);
+            self.levels
+                .insert(*lint, (level, Source::CommandLine(flag)));
+        }
+        Ok(())
+    }
+
+    pub fn level(&self, lint: Lint) -> Level {
+        self.levels
+            .get(&lint)
+            .map_or(Level::Warn, |(level, _)| *level)
+    }
+
+    /// The levels inside a function with attributes, warning about the attributes and the lints
+    /// that do not exist.
+    fn with_attributes(
+        &self,
+        file_id: FileId,
+        attributes: &[Attribute],
+        diagnostics: &mut Vec<Diagnostic>,
+    ) -> LintLevels {
+        let mut levels = self.clone();
+        for attribute in attributes {
+            let level = match Level::from_attribute(&attribute.name) {
+                Some(level) => level,
+                None => {
+                    diagnostics.push(Diagnostic::new_warning(
+                        format!("unknown attribute `{}`", attribute.name),
+                        Label::new(
+                            file_id,
+                            attribute.span,
+                            "expected `allow`, `warn` or `deny`",
+                        ),
+                    ));
+                    continue;
+                }
+            };
+            for name in &attribute.arguments {
+                match lints_named(name) {
+                    Some(lints) => {
+                        for lint in lints {
+                            levels
+                                .levels
+                                .insert(*lint, (level, Source::Attribute(file_id, attribute.span)));
+                        }
+                    }
+                    None => diagnostics.push(Diagnostic::new_warning(
+                        format!("unknown lint: `{}`", name),
+                        Label::new(file_id, attribute.span, "in this attribute"),
+                    )),
+                }
+            }
+        }
+        levels
+    }
+
+    /// Turns a warning about a lint into a diagnostic at the level of the lint, noting where
+    /// the level comes from. Allowed lints have no diagnostic.
+    fn report(&self, lint: Lint, mut diagnostic: Diagnostic) -> Option<Diagnostic> {
+        let (level, source) = self
+            .levels
+            .get(&lint)
+            .cloned()
+            .unwrap_or((Level::Warn, Source::Default));
+        match level {
+            Level::Allow => return None,
+            Level::Warn => {}
+            Level::Deny => diagnostic.severity = Severity::Error,
+        }
+        match source {
+            Source::Default => diagnostic.notes.insert(
+                0,
+                format!(
+                    "`#[{}({})]` is on by default",
+                    level.attribute(),
+                    lint.name()
+                ),
+            ),
+            Source::CommandLine(flag) => diagnostic
+                .notes
+                .insert(0, format!("requested on the command line with `{}`", flag)),
+            Source::Attribute(file_id, span) => diagnostic.secondary_labels.push(Label::new(
+                file_id,
+                span,
+                "the lint level is defined here",
+            )),
+        }
+        Some(diagnostic)
+    }
+}
+
+/// Whether any of the diagnostics is an error, such as a denied lint.
+pub fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics
+        .iter()
+        .any(|diagnostic| matches!(diagnostic.severity, Severity::Error))
+}
+
+/// Checks the loaded modules of a program for the lints that are not allowed, returning their
+/// diagnostics in the order of the modules and of the source.
+pub fn check(files: &Files, modules: &[Module], levels: &LintLevels) -> Vec<Diagnostic> {
+    let mut used = modules
+        .iter()
+        .flat_map(|module| {
+            functions(&module.program)
+                .map(move |function| (qualify(&module.name, &function.name), false))
+        })
+        .collect::<HashMap<_, _>>();
+    let mut diagnostics = vec![];
+    for module in modules {
+        let mut checker = Checker {
+            source: files.source(module.file_id),
+            file_id: module.file_id,
+            module: &module.name,
+            uses: module
+                .program
+                .items
+                .iter()
+                .filter_map(|item| match item {
+                    Item::Use(declaration) => Some((
+                        declaration.name.as_str(),
+                        qualify(&declaration.module, &declaration.name),
+                    )),
+                    _ => None,
+                })
+                .collect(),
+            levels: levels.clone(),
+            function: None,
+            scopes: vec![],
+            used: &mut used,
+            diagnostics: &mut diagnostics,
+        };
+        checker.check_program(&module.program);
+    }
+
+    for module in modules {
+        for function in functions(&module.program) {
+            if used[&qualify(&module.name, &function.name)] || function.name.starts_with('_') {
+                continue;
+            }
+            let span = name_span(files.source(module.file_id), function.span);
+            let diagnostic = Diagnostic::new_warning(
+                format!("function `{}` is never used", function.name),
+                Label::new(module.file_id, span, "never used"),
+            )
+            .with_notes(vec![format!(
+                "help: if this is intentional, prefix it with an underscore: `_{}`",
+                function.name
+            )]);
+            let levels = levels.with_attributes(module.file_id, &function.attributes, &mut vec![]);
+            diagnostics.extend(levels.report(Lint::UnusedFunctions, diagnostic));
+        }
+    }
+
+    let position = |diagnostic: &Diagnostic| {
+        let label = &diagnostic.primary_label;
+        let module = modules
+            .iter()
+            .position(|module| module.file_id == label.file_id);
+        (module, label.span.start())
+    };
+    diagnostics.sort_by_key(position);
+    diagnostics
+}
+
+fn functions(program: &Program) -> impl Iterator<Item = &Function> {
+    program.items.iter().filter_map(|item| match item {
+        Item::Function(function) => Some(function),
+        _ => None,
+    })
+}
+
+fn qualify(module: &str, name: &str) -> String {
+    if module.is_empty() {
+        name.into()
+    } else {
+        format!("{}::{}", module, name)
+    }
+}
+
+/// The span of the name declared by a `let` or a `func`, which is their second token.
+fn name_span(source: &str, span: Span) -> Span {
+    let start = span.start().to_usize();
+    match Lexer::new(&source[start..]).nth(1) {
+        Some(Ok((name_start, _, name_end))) => {
+            Span::new((start + name_start) as u32, (start + name_end) as u32)
+        }
+        _ => span,
+    }
+}
+
+/// A variable in scope.
+struct Binding {
+    name: String,
+    /// The span of the name in the `let` declaring the variable. Parameters and the bindings
+    /// of patterns have none, and are not reported when they are unused.
+    span: Option<Span>,
+    used: bool,
+}
+
+impl Binding {
+    fn new(name: &str, span: Option<Span>) -> Binding {
+        Binding {
+            name: name.into(),
+            span,
+            used: false,
+        }
+    }
+}
+
+/// Walks the functions, tests and top level statements of a module.
+struct Checker<'a> {
+    source: &'a str,
+    file_id: FileId,
+    module: &'a str,
+    /// The qualified names of the functions the module brings into scope with `use`.
+    uses: HashMap<&'a str, String>,
+    levels: LintLevels,
+    /// The qualified name of the function being checked, whose calls of itself do not make it
+    /// used.
+    function: Option<String>,
+    scopes: Vec<Vec<Binding>>,
+    /// Whether each function of the program is used, by qualified name.
+    used: &'a mut HashMap<String, bool>,
+    diagnostics: &'a mut Vec<Diagnostic>,
+}
+
+impl<'a> Checker<'a> {
+    fn check_program(&mut self, program: &Program) {
+        for item in &program.items {
+            match item {
+                Item::Function(function) => self.check_function(function),
+                Item::Test(test) => self.check_block(&test.body),
+                _ => {}
+            }
+        }
+
+        // The top level statements are the body of `main`.
+        self.check_block(program.items.iter().filter_map(|item| match item {
+            Item::Statement(statement) => Some(statement),
+            _ => None,
+        }));
+    }
+
+    fn check_function(&mut self, function: &Function) {
+        let levels =
+            self.levels
+                .with_attributes(self.file_id, &function.attributes, self.diagnostics);
+        let outer_levels = std::mem::replace(&mut self.levels, levels);
+        self.function = Some(qualify(self.module, &function.name));
+
+        let parameters = function
+            .parameters
+            .iter()
+            .map(|(name, _)| Binding::new(name, None))
+            .collect();
+        self.scopes.push(parameters);
+        self.check_block(&function.body);
+        self.pop_scope();
+
+        self.function = None;
+        self.levels = outer_levels;
+    }
+
+    fn check_block<'s, I>(&mut self, statements: I)
+    where
+        I: IntoIterator<Item = &'s Statement>,
+    {
+        self.scopes.push(vec![]);
+        let mut return_span = None;
+        for statement in statements {
+            // Only the first unreachable statement is reported.
+            if let Some(return_span) = return_span.take() {
+                let diagnostic = Diagnostic::new_warning(
+                    "unreachable statement",
+                    Label::new(self.file_id, statement.span, "unreachable statement"),
+                )
+                .with_secondary_labels(vec![Label::new(
+                    self.file_id,
+                    return_span,
+                    "any code following this `return` is unreachable",
+                )]);
+                self.report(Lint::UnreachableCode, diagnostic);
+            }
+            if let StatementKind::Return(_) = statement.kind {
+                return_span = Some(statement.span);
+            }
+            self.check_statement(statement);
+        }
+        self.pop_scope();
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match &statement.kind {
+            StatementKind::Let(name, _, expression) => {
+                self.check_expression(expression);
+                self.declare(name, name_span(self.source, statement.span));
+            }
+            StatementKind::Assignment(place, expression) => {
+                self.check_expression(expression);
+                // Assigning to a variable does not read it.
+                match place {
+                    Expression::Variable(name) if self.lookup(name).is_some() => {}
+                    place => self.check_expression(place),
+                }
+            }
+            StatementKind::Expression(expression) | StatementKind::Return(Some(expression)) => {
+                self.check_expression(expression)
+            }
+            StatementKind::Return(None) | StatementKind::Error => {}
+            StatementKind::If(condition, then, otherwise) => {
+                self.check_expression(condition);
+                self.check_block(then);
+                self.check_block(otherwise);
+            }
+            StatementKind::While(condition, body) => {
+                self.check_expression(condition);
+                self.check_block(body);
+            }
+        }
+    }
+
+    fn check_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Invocation(name, args) => {
+                self.use_name(name);
+                self.check_expressions(args);
+            }
+            Expression::Call(callee, args) => {
+                self.check_expression(callee);
+                self.check_expressions(args);
+            }
+            Expression::Variable(name) => self.use_name(name),
+            Expression::FieldAccess(object, _) => self.check_expression(object),
+            Expression::StructLiteral(_, fields) => {
+                for (_, field) in fields {
+                    self.check_expression(field);
+                }
+            }
+            // `utils::helper(args)` parses like a variant of an enum.
+            Expression::Variant(module, name, args) => {
+                self.use_function(qualify(module, name));
+                self.check_expressions(args);
+            }
+            Expression::Array(elements) => self.check_expressions(elements),
+            Expression::Index(array, index) => {
+                self.check_expression(array);
+                self.check_expression(index);
+            }
+            Expression::Match(scrutinee, arms) => {
+                self.check_expression(scrutinee);
+                for arm in arms {
+                    let mut bindings = vec![];
+                    pattern_bindings(&arm.pattern, &mut bindings);
+                    self.scopes.push(bindings);
+                    match &arm.body {
+                        MatchArmBody::Expression(body) => self.check_expression(body),
+                        MatchArmBody::Block(body) => self.check_block(body),
+                    }
+                    self.pop_scope();
+                }
+            }
+            Expression::Lambda(lambda) => {
+                let parameters = lambda
+                    .parameters
+                    .iter()
+                    .map(|(name, _)| Binding::new(name, None))
+                    .collect();
+                self.scopes.push(parameters);
+                match &lambda.body {
+                    LambdaBody::Expression(body) => self.check_expression(body),
+                    LambdaBody::Block(body) => self.check_block(body),
+                }
+                self.pop_scope();
+            }
+            Expression::Unary(_, operand) => self.check_expression(operand),
+            Expression::Binary(lhs, _, rhs) => {
+                self.check_expression(lhs);
+                self.check_expression(rhs);
+            }
+            Expression::StringLiteral(_)
+            | Expression::IntegerLiteral(_)
+            | Expression::BooleanLiteral(_)
+            | Expression::Null
+            | Expression::Error => {}
+        }
+    }
+
+    fn check_expressions(&mut self, expressions: &[Expression]) {
+        for expression in expressions {
+            self.check_expression(expression);
+        }
+    }
+
+    /// Declares a variable with `let`, warning if it shadows another one.
+    fn declare(&mut self, name: &str, span: Span) {
+        if let Some(shadowed) = self.lookup(name) {
+            let mut diagnostic = Diagnostic::new_warning(
+                format!("`{}` shadows a variable that is already in scope", name),
+                Label::new(
+                    self.file_id,
+                    span,
+                    format!("shadows the earlier `{}`", name),
+                ),
+            );
+            if let Some(shadowed_span) = shadowed.span {
+                diagnostic = diagnostic.with_secondary_labels(vec![Label::new(
+                    self.file_id,
+                    shadowed_span,
+                    "first declared here",
+                )]);
+            }
+            self.report(Lint::Shadowing, diagnostic);
+        }
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .push(Binding::new(name, Some(span)));
+    }
+
+    /// Marks the variable or the function that a name refers to as used.
+    fn use_name(&mut self, name: &str) {
+        if let Some(binding) = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.iter_mut().rev().find(|binding| binding.name == name))
+        {
+            binding.used = true;
+            return;
+        }
+
+        // Like the compiler, functions of the module come before the ones brought into scope
+        // with `use`.
+        let qualified = qualify(self.module, name);
+        let qualified = if self.used.contains_key(&qualified) {
+            qualified
+        } else {
+            match self.uses.get(name) {
+                Some(qualified) => qualified.clone(),
+                None => return,
+            }
+        };
+        self.use_function(qualified);
+    }
+
+    fn use_function(&mut self, qualified: String) {
+        if self.function.as_ref() == Some(&qualified) {
+            return;
+        }
+        if let Some(used) = self.used.get_mut(&qualified) {
+            *used = true;
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.iter().rev().find(|binding| binding.name == name))
+    }
+
+    /// Leaves a scope, warning about the variables declared in it that were never used.
+    fn pop_scope(&mut self) {
+        for binding in self.scopes.pop().unwrap() {
+            let span = match binding.span {
+                Some(span) if !binding.used && !binding.name.starts_with('_') => span,
+                _ => continue,
+            };
+            let diagnostic = Diagnostic::new_warning(
+                format!("unused variable: `{}`", binding.name),
+                Label::new(self.file_id, span, "never used"),
+            )
+            .with_notes(vec![format!(
+                "help: if this is intentional, prefix it with an underscore: `_{}`",
+                binding.name
+            )]);
+            self.report(Lint::UnusedVariables, diagnostic);
+        }
+    }
+
+    fn report(&mut self, lint: Lint, diagnostic: Diagnostic) {
+        self.diagnostics
+            .extend(self.levels.report(lint, diagnostic));
+    }
+}
+
+fn pattern_bindings(pattern: &Pattern, bindings: &mut Vec<Binding>) {
+    match pattern {
+        Pattern::Binding(name) => bindings.push(Binding::new(name, None)),
+        Pattern::Variant(_, _, fields) => {
+            for field in fields {
+                pattern_bindings(field, bindings);
+            }
+        }
+        Pattern::Wildcard | Pattern::IntegerLiteral(_) | Pattern::BooleanLiteral(_) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// The messages of the diagnostics of a program with modules of these names and sources,
+    /// the first of which is the file being compiled.
+    fn lint(sources: &[(&str, &str)], levels: &LintLevels) -> Vec<String> {
+        let mut files = Files::new();
+        let modules = sources
+            .iter()
+            .map(|(name, source)| {
+                let file_id = files.add(format!("{}.faang", name), *source);
+                let program = crate::parse(source, file_id).unwrap();
+                Module {
+                    name: name.to_string(),
+                    file_id,
+                    program,
+                }
+            })
+            .collect::<Vec<_>>();
+        check(&files, &modules, levels)
+            .iter()
+            .map(|diagnostic| {
+                let severity = match diagnostic.severity {
+                    Severity::Error => "error",
+                    _ => "warning",
+                };
+                let span = diagnostic.primary_label.span;
+                format!(
+                    "{}: {} at {}..{}",
+                    severity,
+                    diagnostic.message,
+                    span.start().to_usize(),
+                    span.end().to_usize()
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn reports_unused_variables_and_shadowing() {
+        let source = "func count(n: int) -> int {
+    let total = 0;
+    let _ignored = 1;
+    let unused = n;
+    unused = 2;
+    if n > 0 {
+        let total = total + n;
+        return total;
+    }
+    return match n { x => total };
+}
+count(1)";
+        assert_eq!(
+            lint(&[("", source)], &LintLevels::default()),
+            vec![
+                "warning: unused variable: `unused` at 77..83",
+                "warning: `total` shadows a variable that is already in scope at 132..137",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_unused_functions() {
+        let main = "import \"utils.faang\";
+use utils::twice;
+func unused() { unused(); }
+func _private() {}
+func main() { println(utils::name()); }
+main();
+twice(1)";
+        let utils = "func twice(n: int) -> int { return n * 2; }
+func name() -> string { return \"utils\"; }
+func helper() {}";
+        assert_eq!(
+            lint(&[("", main), ("utils", utils)], &LintLevels::default()),
+            vec![
+                "warning: function `unused` is never used at 45..51",
+                "warning: function `helper` is never used at 91..97",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_unreachable_code() {
+        let source = "func early() {
+    return;
+    println(\"one\");
+    println(\"two\");
+}
+early()";
+        assert_eq!(
+            lint(&[("", source)], &LintLevels::default()),
+            vec!["warning: unreachable statement at 31..45"]
+        );
+    }
+
+    #[test]
+    fn applies_lint_levels() {
+        let source = "#[allow(unused_variables)]
+func quiet() { let a = 1; }
+#[deny(warnings)]
+#[warn(no_such_lint)]
+#[inline()]
+func strict() { let b = 1; }
+let c = 1;
+quiet();
+strict()";
+        let mut levels = LintLevels::default();
+        assert_eq!(
+            lint(&[("", source)], &levels),
+            vec![
+                "warning: unknown lint: `no_such_lint` at 73..94",
+                "warning: unknown attribute `inline` at 95..106",
+                "error: unused variable: `b` at 127..128",
+                "warning: unused variable: `c` at 140..141",
+            ]
+        );
+
+        levels.set("unused-variables", Level::Deny).unwrap();
+        levels.set("warnings", Level::Allow).unwrap();
+        assert_eq!(levels.level(Lint::UnusedVariables), Level::Allow);
+        levels.set("unused_variables", Level::Deny).unwrap();
+        assert_eq!(
+            lint(&[("", source)], &levels),
+            vec![
+                "warning: unknown lint: `no_such_lint` at 73..94",
+                "warning: unknown attribute `inline` at 95..106",
+                "error: unused variable: `b` at 127..128",
+                "error: unused variable: `c` at 140..141",
+            ]
+        );
+        assert_eq!(
+            levels.set("unused", Level::Warn),
+            Err("unknown lint: `unused`".to_string())
+        );
+    }
+}
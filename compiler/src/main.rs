@@ -1,19 +1,22 @@
-use clap::{App, Arg};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use colored::*;
 use inkwell::context::Context;
 
 use faang::{
-    ast,
-    compiler::{self, external::stdio, stdlib},
-    parser,
+    ast, error_codes, format, json,
+    lints::{self, Level, LintLevels},
+    manifest::Profile,
+    modules, package, parser,
+    testing::{self, Outcome},
 };
 
 use lalrpop_util::ParseError;
 use std::collections::HashMap;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
-use codespan::{Files, Span};
+use codespan::{FileId, Files, Span};
 use codespan_reporting::{
     diagnostic,
     term::{
@@ -22,133 +25,677 @@ use codespan_reporting::{
     },
 };
 static SRC: &str = "src";
+static BUILD: &str = "build";
+static RELEASE: &str = "release";
+static TEST: &str = "test";
+static EXPLAIN: &str = "explain";
+static FMT: &str = "fmt";
+static CHECK: &str = "check";
+static CODE: &str = "CODE";
+static ERROR_FORMAT: &str = "error-format";
+static EMIT: &str = "emit";
+static ALLOW: &str = "allow";
+static WARN: &str = "warn";
+static DENY: &str = "deny";
+
+/// How errors are reported.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ErrorFormat {
+    /// Rendered along with the source they are about.
+    Human,
+    /// As JSON objects, one per line, in the form of `faang::json`.
+    Json,
+}
 
 fn main() {
     let matches = App::new("FAANG Compiler")
         .version("1.0")
         .author("Ryan Lee <drdgvhbh@gmail.com>")
         .about("Compiles the FAANG language - A language for leetcoding!")
+        .setting(AppSettings::SubcommandsNegateReqs)
         .arg(
             Arg::with_name(SRC)
                 .value_name("SOURCE")
                 .help("The source glass file to compile")
                 .required(true),
         )
+        .arg(
+            Arg::with_name(ERROR_FORMAT)
+                .long(ERROR_FORMAT)
+                .value_name("FORMAT")
+                .help("How errors are reported")
+                .possible_values(&["human", "json"])
+                .global(true),
+        )
+        .arg(
+            Arg::with_name(EMIT)
+                .long(EMIT)
+                .value_name("KIND")
+                .help("Prints the tokens or the syntax tree of the source without compiling it")
+                .possible_values(&["ast", "tokens"]),
+        )
+        .arg(lint_flag(ALLOW, "A", "Allows a lint"))
+        .arg(lint_flag(WARN, "W", "Warns about a lint"))
+        .arg(lint_flag(DENY, "D", "Denies a lint"))
+        .subcommand(
+            SubCommand::with_name(BUILD)
+                .about("Builds the package described by the faang.toml in the current directory")
+                .arg(
+                    Arg::with_name(RELEASE)
+                        .long(RELEASE)
+                        .help("Builds with the release profile"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(TEST)
+                .about(
+                    "Runs the test blocks of a source file, or of the package in the current \
+                     directory",
+                )
+                .arg(
+                    Arg::with_name(SRC)
+                        .value_name("SOURCE")
+                        .help("The source file to test"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(FMT)
+                .about("Formats source files, or the package in the current directory, in place")
+                .arg(
+                    Arg::with_name(CHECK)
+                        .long(CHECK)
+                        .help("Lists the files that are not formatted instead of formatting them"),
+                )
+                .arg(
+                    Arg::with_name(SRC)
+                        .value_name("SOURCE")
+                        .help("The source files to format")
+                        .multiple(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name(EXPLAIN)
+                .about("Describes the error with a code, such as E0001")
+                .arg(
+                    Arg::with_name(CODE)
+                        .value_name("CODE")
+                        .help("The code of the error")
+                        .required(true),
+                ),
+        )
         .get_matches();
 
-    let source_file_name = matches.value_of(SRC).unwrap();
-    let contents = read_to_string(source_file_name);
+    // A global argument that comes after a subcommand is only in the matches of the subcommand.
+    let error_format = match matches
+        .subcommand()
+        .1
+        .and_then(|matches| matches.value_of(ERROR_FORMAT))
+        .or_else(|| matches.value_of(ERROR_FORMAT))
+    {
+        Some("json") => ErrorFormat::Json,
+        _ => ErrorFormat::Human,
+    };
 
-    let mut writer = BufferedStandardStream::stderr(ColorChoice::Auto);
     let mut files = Files::new();
-    let file_id = files.add(source_file_name, &contents);
-
-    let parse_result = faang::parse(&contents, file_id);
-    if parse_result.is_err() {
-        let diagnostics = parse_result.unwrap_err();
-        for diagnostic in diagnostics {
-            codespan_reporting::term::emit(&mut writer, &Config::default(), &files, &diagnostic)
-                .unwrap();
+    // Global arguments are copied into the matches of the subcommand.
+    let lint_levels = match lint_levels(matches.subcommand().1.unwrap_or(&matches)) {
+        Err(err) => exit_with_error(&files, error_format, package::Error::Message(err)),
+        Ok(lint_levels) => lint_levels,
+    };
+    match matches.subcommand() {
+        (subcommand, Some(matches)) if subcommand == BUILD => {
+            let release = matches.is_present(RELEASE);
+            match package::build(Path::new("."), release, &lint_levels, &mut files) {
+                Err(err) => exit_with_error(&files, error_format, err),
+                Ok(report) => {
+                    report_diagnostics(&files, error_format, &report.warnings);
+                    eprintln!(
+                        "{} {} module(s), {} cached",
+                        "Compiled".green().bold(),
+                        report.compiled.len(),
+                        report.cached.len()
+                    );
+                }
+            }
+        }
+        (subcommand, Some(matches)) if subcommand == TEST => {
+            let (name, modules) = match matches.value_of(SRC) {
+                Some(source_file_name) => (
+                    file_stem(source_file_name),
+                    load_file(&mut files, error_format, source_file_name),
+                ),
+                None => match package::load(Path::new("."), &mut files) {
+                    Err(err) => exit_with_error(&files, error_format, err),
+                    Ok((manifest, modules)) => (manifest.name, modules),
+                },
+            };
+            check_lints(&files, error_format, &lint_levels, &modules);
+            run_tests(&files, error_format, &name, modules);
+        }
+        (subcommand, Some(matches)) if subcommand == FMT => {
+            let paths = match matches.values_of(SRC) {
+                Some(source_file_names) => source_file_names.map(PathBuf::from).collect(),
+                None => match package::sources(Path::new(".")) {
+                    Err(err) => exit_with_error(&files, error_format, err),
+                    Ok((_, sources)) => sources,
+                },
+            };
+            format_files(&mut files, error_format, &paths, matches.is_present(CHECK));
+        }
+        (subcommand, Some(matches)) if subcommand == EXPLAIN => {
+            let code = matches.value_of(CODE).unwrap();
+            match error_codes::explain(code) {
+                Some(explanation) => print!("{}", explanation),
+                None => {
+                    eprintln!("{} `{}` is not an error code", "error:".red().bold(), code);
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => {
+            let source_file_name = matches.value_of(SRC).unwrap();
+            if let Some(kind) = matches.value_of(EMIT) {
+                emit(&mut files, error_format, source_file_name, kind);
+                return;
+            }
+            let modules = load_file(&mut files, error_format, source_file_name);
+            check_lints(&files, error_format, &lint_levels, &modules);
+            let file_ids = file_ids(&modules);
+            let (program, imports) = split_modules(modules);
+
+            let file_stem = file_stem(source_file_name);
+            let context = Context::create();
+            let result = package::compile(
+                &context, &file_stem, &program, &imports, None, &files, &file_ids,
+            )
+            .and_then(|module| {
+                package::write_artifacts(
+                    &module,
+                    &file_stem,
+                    Profile::Debug,
+                    &Path::new(package::TARGET).join(Profile::Debug.to_string()),
+                )
+            });
+            if let Err(err) = result {
+                exit_with_error(&files, error_format, package::Error::Message(err));
+            }
         }
-        writer.flush().unwrap();
-        std::process::exit(1);
     }
+}
+
+/// A flag setting the level of a lint, or of every lint for `warnings`, which may be given
+/// any number of times.
+fn lint_flag<'a, 'b>(name: &'a str, short: &str, help: &'b str) -> Arg<'a, 'b> {
+    Arg::with_name(name)
+        .short(short)
+        .value_name("LINT")
+        .help(help)
+        .multiple(true)
+        .number_of_values(1)
+        .global(true)
+}
 
-    let program = parse_result.unwrap();
+/// The lint levels set by the `-A`, `-W` and `-D` flags. The last flag for a lint wins.
+fn lint_levels(matches: &ArgMatches) -> Result<LintLevels, String> {
+    let mut flags = vec![];
+    for (arg, level) in &[
+        (ALLOW, Level::Allow),
+        (WARN, Level::Warn),
+        (DENY, Level::Deny),
+    ] {
+        if let (Some(indices), Some(names)) = (matches.indices_of(arg), matches.values_of(arg)) {
+            flags.extend(
+                indices
+                    .zip(names)
+                    .map(|(index, name)| (index, *level, name)),
+            );
+        }
+    }
+    flags.sort_by_key(|(index, _, _)| *index);
 
+    let mut lint_levels = LintLevels::default();
+    for (_, level, name) in flags {
+        lint_levels.set(name, level)?;
+    }
+    Ok(lint_levels)
+}
+
+/// Reports the lints of the loaded modules, exiting if any of them is denied.
+fn check_lints(
+    files: &Files,
+    error_format: ErrorFormat,
+    lint_levels: &LintLevels,
+    modules: &[modules::Module],
+) {
+    let diagnostics = lints::check(files, modules, lint_levels);
+    if lints::has_errors(&diagnostics) {
+        exit_with_error(
+            files,
+            error_format,
+            package::Error::Diagnostics(diagnostics),
+        );
+    }
+    report_diagnostics(files, error_format, &diagnostics);
+}
+
+/// Compiles the `test` blocks of the modules and runs each of them, exiting with a nonzero
+/// status if any fails.
+fn run_tests(files: &Files, error_format: ErrorFormat, name: &str, modules: Vec<modules::Module>) {
+    let file_ids = file_ids(&modules);
+    let (program, imports) = split_modules(modules);
     let context = Context::create();
-    let module = context.create_module(source_file_name);
-    let builder = context.create_builder();
-
-    stdio::add(
-        &[
-            stdio::Features::PRINTF,
-            stdio::Features::FPRINTF,
-            stdio::Features::FOPEN,
-            stdio::Features::FFLUSH,
-        ],
-        &context,
-        &module,
+    let (module, tests) =
+        match package::compile_tests(&context, name, &program, &imports, files, &file_ids) {
+            Err(err) => exit_with_error(files, error_format, package::Error::Message(err)),
+            Ok(compiled) => compiled,
+        };
+
+    println!("running {} test(s)", tests.len());
+    let start = Instant::now();
+    let mut failed = 0;
+    let result = testing::run(&module, &tests, |result| {
+        let test_name = if result.test.module.is_empty() {
+            result.test.name.clone()
+        } else {
+            format!("{}: {}", result.test.module, result.test.name)
+        };
+        let outcome = match result.outcome {
+            Outcome::Passed => "ok".green(),
+            Outcome::Failed { status } if testing::is_panic(status) => "FAILED".red(),
+            Outcome::Failed { status } => format!("FAILED (exit status {})", status).red(),
+            Outcome::Crashed { signal } => format!("FAILED (signal {})", signal).red(),
+        };
+        if !result.passed() {
+            failed += 1;
+        }
+        println!(
+            "test {} ... {} ({:.2}ms)",
+            test_name,
+            outcome,
+            result.duration.as_secs_f64() * 1000.0
+        );
+    });
+    if let Err(err) = result {
+        exit_with_error(files, error_format, package::Error::Message(err));
+    }
+
+    println!(
+        "\ntest result: {}. {} passed; {} failed; finished in {:.2}ms",
+        if failed == 0 {
+            "ok".green()
+        } else {
+            "FAILED".red()
+        },
+        tests.len() - failed,
+        failed,
+        start.elapsed().as_secs_f64() * 1000.0
     );
+    if failed > 0 {
+        std::process::exit(1);
+    }
+}
 
-    stdlib::add(&[stdlib::Features::PRINTLN], &context, &module, &builder);
+/// Loads a source file and the files it imports, exiting if any of them has errors.
+fn load_file(
+    files: &mut Files,
+    error_format: ErrorFormat,
+    source_file_name: &str,
+) -> Vec<modules::Module> {
+    let file_id = read_file(files, error_format, source_file_name);
 
-    let mut compiler = compiler::Compiler::new(&context, &module, &builder);
-    let module = compiler.compile(&program).unwrap();
-    module.verify().unwrap();
+    match modules::load(files, file_id, Path::new(source_file_name)) {
+        Err(diagnostics) => exit_with_error(
+            files,
+            error_format,
+            package::Error::Diagnostics(diagnostics),
+        ),
+        Ok(modules) => modules,
+    }
+}
 
-    let file_stem = Path::new(source_file_name).file_stem().unwrap();
-    module
-        .print_to_file(format!("{}.ll", file_stem.to_string_lossy()))
-        .unwrap();
+/// Formats source files in place or, with `check`, lists the ones that are not formatted and
+/// exits with an error if there are any.
+fn format_files(files: &mut Files, error_format: ErrorFormat, paths: &[PathBuf], check: bool) {
+    let mut unformatted = 0;
+    for path in paths {
+        let contents = match std::fs::read_to_string(path) {
+            Err(err) => exit_with_error(
+                files,
+                error_format,
+                package::Error::Message(format!("{}: {}", path.display(), err)),
+            ),
+            Ok(contents) => contents,
+        };
+        let file_id = files.add(path.to_string_lossy(), &contents);
+
+        let formatted = match format::format_source(&contents, file_id) {
+            Err(diagnostics) => exit_with_error(
+                files,
+                error_format,
+                package::Error::Diagnostics(diagnostics),
+            ),
+            Ok(formatted) => formatted,
+        };
+        if formatted == contents {
+            continue;
+        }
+        if check {
+            println!("{}", path.display());
+            unformatted += 1;
+        } else if let Err(err) = std::fs::write(path, formatted) {
+            exit_with_error(
+                files,
+                error_format,
+                package::Error::Message(format!("{}: {}", path.display(), err)),
+            );
+        }
+    }
+
+    if unformatted > 0 {
+        exit_with_error(
+            files,
+            error_format,
+            package::Error::Message(format!("{} file(s) are not formatted", unformatted)),
+        );
+    }
+}
+
+/// Reads a source file into `files`, exiting if it cannot be read.
+fn read_file(files: &mut Files, error_format: ErrorFormat, source_file_name: &str) -> FileId {
+    match std::fs::read_to_string(source_file_name) {
+        Err(err) => exit_with_error(
+            files,
+            error_format,
+            package::Error::Message(format!("{}: {}", source_file_name, err)),
+        ),
+        Ok(contents) => files.add(source_file_name, &contents),
+    }
+}
+
+/// Prints the tokens or the syntax tree of a source file, which is `kind`, for debugging the
+/// parser.
+fn emit(files: &mut Files, error_format: ErrorFormat, source_file_name: &str, kind: &str) {
+    let file_id = read_file(files, error_format, source_file_name);
+    let source = files.source(file_id);
+    let result = match kind {
+        "tokens" => faang::tokenize(source, file_id).map(|tokens| {
+            for (start, token, end) in tokens {
+                println!("{}..{} {:?}", start, end, token);
+            }
+        }),
+        _ => faang::parse(source, file_id).map(|program| println!("{:#?}", program)),
+    };
+    if let Err(diagnostics) = result {
+        exit_with_error(
+            files,
+            error_format,
+            package::Error::Diagnostics(diagnostics),
+        );
+    }
+}
+
+/// The file of every loaded module.
+fn file_ids(modules: &[modules::Module]) -> Vec<(String, FileId)> {
+    modules
+        .iter()
+        .map(|module| (module.name.clone(), module.file_id))
+        .collect()
+}
+
+/// Splits loaded modules into the program being compiled and the modules it imports.
+fn split_modules(modules: Vec<modules::Module>) -> (ast::Program, Vec<(String, ast::Program)>) {
+    let mut modules = modules.into_iter();
+    let program = modules.next().unwrap().program;
+    let imports = modules
+        .map(|module| (module.name, module.program))
+        .collect();
+
+    (program, imports)
+}
+
+fn file_stem(source_file_name: &str) -> String {
+    Path::new(source_file_name)
+        .file_stem()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned()
 }
 
-fn read_to_string(file_path: &str) -> String {
-    match std::fs::read_to_string(file_path) {
-        Err(err) => {
-            eprintln!("{} {}: {}", "error:".red().bold(), file_path, err);
+fn exit_with_error(files: &Files, error_format: ErrorFormat, err: package::Error) -> ! {
+    match (error_format, err) {
+        (_, package::Error::Diagnostics(diagnostics)) => {
+            report_diagnostics(files, error_format, &diagnostics)
+        }
+        (ErrorFormat::Human, package::Error::Message(err)) => {
+            eprintln!("{} {}", "error:".red().bold(), err)
+        }
+        (ErrorFormat::Json, package::Error::Message(err)) => eprintln!("{}", json::error(&err)),
+    }
+
+    std::process::exit(1);
+}
 
-            std::process::exit(1);
+fn report_diagnostics(
+    files: &Files,
+    error_format: ErrorFormat,
+    diagnostics: &[diagnostic::Diagnostic],
+) {
+    match error_format {
+        ErrorFormat::Human => emit_diagnostics(files, diagnostics),
+        ErrorFormat::Json => {
+            for diagnostic in diagnostics {
+                eprintln!("{}", json::diagnostic(files, diagnostic));
+            }
         }
-        Ok(contents) => contents,
     }
 }
 
+fn emit_diagnostics(files: &Files, diagnostics: &[diagnostic::Diagnostic]) {
+    let mut writer = BufferedStandardStream::stderr(ColorChoice::Auto);
+    for diagnostic in diagnostics {
+        codespan_reporting::term::emit(&mut writer, &Config::default(), files, diagnostic).unwrap();
+    }
+    if let Some(code) = diagnostics
+        .iter()
+        .find_map(|diagnostic| diagnostic.code.as_ref())
+    {
+        writeln!(
+            writer,
+            "For more information about an error, try `faang explain {}`.",
+            code
+        )
+        .unwrap();
+    }
+    writer.flush().unwrap();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use inkwell::OptimizationLevel;
+    use faang::manifest;
     use pretty_assertions::assert_eq;
-    use std::fs::File;
     use tempfile::tempdir;
 
     #[test]
-    fn test_hello_world() {
-        let context = Context::create();
-        let module = context.create_module("test");
-        let builder = context.create_builder();
+    fn test_run_str_in_test() {
+        // libtest runs tests on threads of their own, so the program cannot be forked.
+        let mut session = faang::Session::new();
+        let output = session
+            .run_str("main.faang", "println(\"Hello!\")")
+            .unwrap();
+        assert_eq!(output.stdout, "Hello!\n");
+        assert_eq!(output.status, 0);
+    }
+
+    #[test]
+    fn test_build_package() {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(manifest::FILE_NAME),
+            "[package]\nname = \"solutions\"\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(dir.path().join("src").join("lib")).unwrap();
+        std::fs::write(
+            dir.path().join("src").join("main.faang"),
+            "println(utils::greeting())",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("src").join("lib").join("utils.faang"),
+            "func greeting() -> string { return \"Hello World!\"; }",
+        )
+        .unwrap();
+
+        let report =
+            package::build(dir.path(), false, &LintLevels::default(), &mut Files::new()).unwrap();
+        assert_eq!(report.compiled.len(), 2);
+
+        let out_dir = dir.path().join("target").join("debug");
+        assert!(out_dir.join("solutions.bc").exists());
+        let ir = std::fs::read_to_string(out_dir.join("solutions.ll")).unwrap();
+        assert!(ir.contains(r#"@"FAANG_utils::greeting""#));
+    }
 
+    #[test]
+    fn test_build_lints() {
         let dir = tempdir().unwrap();
-        let file_path = dir.path().join("hello_world.txt");
-        let file_path_str = file_path.to_str().unwrap();
-
-        stdio::add(
-            &[
-                stdio::Features::FPRINTF,
-                stdio::Features::FOPEN,
-                stdio::Features::FFLUSH,
-            ],
-            &context,
-            &module,
+        std::fs::write(
+            dir.path().join(manifest::FILE_NAME),
+            "[package]\nname = \"solutions\"\n",
+        )
+        .unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::write(
+            dir.path().join("src").join("main.faang"),
+            "func unused() {}\nlet answer = 42;\nreturn;\nprintln(\"unreachable\")",
+        )
+        .unwrap();
+
+        let report =
+            package::build(dir.path(), false, &LintLevels::default(), &mut Files::new()).unwrap();
+        let messages = report
+            .warnings
+            .iter()
+            .map(|warning| warning.message.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            messages,
+            vec![
+                "function `unused` is never used",
+                "unused variable: `answer`",
+                "unreachable statement",
+            ]
         );
-        compiler::external::stdio::mock::add_printf(file_path_str, &context, &module, &builder);
 
-        stdlib::add(&[stdlib::Features::PRINTLN], &context, &module, &builder);
+        let mut lint_levels = LintLevels::default();
+        lint_levels.set("warnings", Level::Allow).unwrap();
+        lint_levels.set("unused-functions", Level::Deny).unwrap();
+        match package::build(dir.path(), false, &lint_levels, &mut Files::new()) {
+            Err(package::Error::Diagnostics(diagnostics)) => {
+                assert_eq!(diagnostics.len(), 1);
+                assert_eq!(diagnostics[0].message, "function `unused` is never used");
+            }
+            result => panic!("expected a denied lint, found {:?}", result),
+        }
+    }
 
-        let program_parser = parser::ExpressionParser::new();
-        let program = program_parser
-            .parse(&mut vec![], "println(\"Hello World!\")")
-            .unwrap();
-        let mut compiler = compiler::Compiler::new(&context, &module, &builder);
-        compiler.compile(&program).unwrap();
+    #[test]
+    fn test_incremental_build() {
+        let dir = tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(manifest::FILE_NAME),
+            "[package]\nname = \"solutions\"\n",
+        )
+        .unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        let main_path = dir.path().join("src").join("main.faang");
+        let utils_path = dir.path().join("src").join("utils.faang");
+        let math_path = dir.path().join("src").join("math.faang");
+        std::fs::write(&main_path, "println(utils::greeting())").unwrap();
+        std::fs::write(
+            &utils_path,
+            "func greeting() -> string { return \"Hello World!\"; }",
+        )
+        .unwrap();
+        std::fs::write(
+            &math_path,
+            "func square(num: int) -> int { return num * num; }",
+        )
+        .unwrap();
 
-        module.verify().unwrap();
+        let report =
+            package::build(dir.path(), false, &LintLevels::default(), &mut Files::new()).unwrap();
+        assert_eq!(report.parsed.len(), 3);
+        assert_eq!(report.compiled.len(), 3);
+        assert_eq!(report.cached.len(), 0);
 
-        let ee = module
-            .create_jit_execution_engine(OptimizationLevel::None)
-            .unwrap();
-        File::create(file_path.clone()).unwrap();
+        // An unchanged rebuild neither parses nor does codegen.
+        let report =
+            package::build(dir.path(), false, &LintLevels::default(), &mut Files::new()).unwrap();
+        assert_eq!(report.parsed.len(), 0);
+        assert_eq!(report.compiled.len(), 0);
+        assert_eq!(report.cached.len(), 3);
+
+        // Changing a body only recompiles its module.
+        std::fs::write(
+            &utils_path,
+            "func greeting() -> string { return \"Hello FAANG!\"; }",
+        )
+        .unwrap();
+        let report =
+            package::build(dir.path(), false, &LintLevels::default(), &mut Files::new()).unwrap();
+        assert_eq!(report.parsed, vec![utils_path.clone()]);
+        assert_eq!(report.compiled, vec![utils_path.clone()]);
+        assert_eq!(report.cached.len(), 2);
+
+        // Changing a signature recompiles the modules that depend on it, and no others.
+        std::fs::write(
+            &utils_path,
+            "func greeting() -> string { return \"Hello FAANG!\"; }\n\
+             func farewell() -> string { return \"Bye!\"; }",
+        )
+        .unwrap();
+        let report =
+            package::build(dir.path(), false, &LintLevels::default(), &mut Files::new()).unwrap();
+        assert_eq!(report.compiled.len(), 2);
+        assert_eq!(report.cached, vec![math_path.clone()]);
+
+        // Each module has a syntax tree, an interface, bitcode and an object file cached.
+        let cache_dir = dir.path().join("target").join("cache");
+        assert_eq!(std::fs::read_dir(&cache_dir).unwrap().count(), 12);
 
-        unsafe {
-            let main_fcn = ee
-                .get_function::<unsafe extern "C" fn() -> i32>("main")
-                .expect("main function should be defined");
-            main_fcn.call();
+        // Cached code that cannot be loaded is compiled again and overwritten.
+        for entry in std::fs::read_dir(&cache_dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension() == Some("bc".as_ref()) {
+                std::fs::write(path, "not bitcode").unwrap();
+            }
         }
+        let report =
+            package::build(dir.path(), false, &LintLevels::default(), &mut Files::new()).unwrap();
+        assert_eq!(report.compiled.len(), 3);
+        assert_eq!(report.cached.len(), 0);
+        let report =
+            package::build(dir.path(), false, &LintLevels::default(), &mut Files::new()).unwrap();
+        assert_eq!(report.cached.len(), 3);
+    }
 
-        assert_eq!(
-            "Hello World!\n",
-            std::fs::read_to_string(file_path_str).unwrap()
-        );
+    #[test]
+    fn test_missing_import() {
+        let dir = tempdir().unwrap();
+        let main_path = dir.path().join("main.faang");
+
+        let mut files = Files::new();
+        let file_id = files.add(main_path.to_string_lossy(), r#"import "nowhere.faang";"#);
+        let diagnostics = match modules::load(&mut files, file_id, &main_path) {
+            Ok(_) => panic!("loading a missing import should fail"),
+            Err(diagnostics) => diagnostics,
+        };
+
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.starts_with("cannot read"));
+        assert_eq!(diagnostics[0].primary_label.file_id, file_id);
+        assert_eq!(diagnostics[0].primary_label.span, Span::new(0, 23));
     }
 }
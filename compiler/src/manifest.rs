@@ -0,0 +1,197 @@
+//! The `faang.toml` manifest of a package.
+//!
+//! ```toml
+//! [package]
+//! name = "solutions"
+//! source = "src"         # the default
+//! entry = "main.faang"   # the default, relative to `source`
+//!
+//! [build]
+//! profile = "release"    # or "debug", the default
+//! ```
+//!
+//! Only the subset of TOML needed for this is understood: sections, and keys set to strings
+//! without escapes, which are rejected.
+use inkwell::OptimizationLevel;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub const FILE_NAME: &str = "faang.toml";
+
+#[derive(Debug, PartialEq)]
+pub struct Manifest {
+    pub name: String,
+    /// The directory holding the source files, relative to the manifest.
+    pub source: PathBuf,
+    /// The file holding the top level statements, relative to `source`.
+    pub entry: PathBuf,
+    pub profile: Profile,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Profile {
+    Debug,
+    Release,
+}
+
+impl Profile {
+    pub fn optimization_level(self) -> OptimizationLevel {
+        match self {
+            Profile::Debug => OptimizationLevel::None,
+            Profile::Release => OptimizationLevel::Aggressive,
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Profile::Debug => write!(f, "debug"),
+            Profile::Release => write!(f, "release"),
+        }
+    }
+}
+
+impl Manifest {
+    pub fn read(path: &Path) -> Result<Manifest, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("cannot read `{}`: {}", path.display(), err))?;
+        Manifest::parse(&text).map_err(|err| format!("{}: {}", path.display(), err))
+    }
+
+    pub fn parse(text: &str) -> Result<Manifest, String> {
+        let mut name = None;
+        let mut source = PathBuf::from("src");
+        let mut entry = PathBuf::from("main.faang");
+        let mut profile = Profile::Debug;
+
+        let mut section = String::new();
+        for (index, line) in text.lines().enumerate() {
+            let error = |message: String| format!("line {}: {}", index + 1, message);
+            let line = strip_comment(line).map_err(error)?.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            if line.starts_with('[') {
+                if !line.ends_with(']') {
+                    return Err(error("expected `]` after the section name".into()));
+                }
+                section = line[1..line.len() - 1].trim().into();
+                if section != "package" && section != "build" {
+                    return Err(error(format!("unknown section `[{}]`", section)));
+                }
+                continue;
+            }
+
+            let (key, value) = match line.find('=') {
+                Some(index) => (line[..index].trim(), line[index + 1..].trim()),
+                None => return Err(error("expected `key = \"value\"`".into())),
+            };
+            if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+                return Err(error(format!("expected a string for `{}`", key)));
+            }
+            let value = &value[1..value.len() - 1];
+
+            match (section.as_str(), key) {
+                ("package", "name") => name = Some(value.to_string()),
+                ("package", "source") => source = value.into(),
+                ("package", "entry") => entry = value.into(),
+                ("build", "profile") => {
+                    profile = match value {
+                        "debug" => Profile::Debug,
+                        "release" => Profile::Release,
+                        _ => {
+                            return Err(error(format!(
+                                "unknown profile `{}`, expected `debug` or `release`",
+                                value
+                            )))
+                        }
+                    }
+                }
+                ("", _) => return Err(error(format!("`{}` is not in a section", key))),
+                _ => return Err(error(format!("unknown key `{}` in `[{}]`", key, section))),
+            }
+        }
+
+        Ok(Manifest {
+            name: name.ok_or("missing `name` in `[package]`")?,
+            source,
+            entry,
+            profile,
+        })
+    }
+}
+
+/// Removes a `#` comment, unless the `#` is inside a string. Strings cannot have escapes, as
+/// an escaped `"` would otherwise be taken for the end of the string.
+fn strip_comment(line: &str) -> Result<&str, String> {
+    let mut in_string = false;
+    for (index, c) in line.char_indices() {
+        match c {
+            '"' => in_string = !in_string,
+            '\\' if in_string => return Err("escapes in strings are not supported".into()),
+            '#' if !in_string => return Ok(&line[..index]),
+            _ => {}
+        }
+    }
+    Ok(line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_manifest() {
+        let manifest = Manifest::parse(
+            r#"
+            # Solutions to interview questions.
+            [package]
+            name = "solutions"
+            source = "lib"
+            entry = "run.faang" # runs every solution
+
+            [build]
+            profile = "release"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            manifest,
+            Manifest {
+                name: "solutions".into(),
+                source: "lib".into(),
+                entry: "run.faang".into(),
+                profile: Profile::Release,
+            }
+        );
+    }
+
+    #[test]
+    fn uses_defaults() {
+        let manifest = Manifest::parse("[package]\nname = \"solutions\"").unwrap();
+        assert_eq!(manifest.source, PathBuf::from("src"));
+        assert_eq!(manifest.entry, PathBuf::from("main.faang"));
+        assert_eq!(manifest.profile, Profile::Debug);
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let err = Manifest::parse("[package]\nname = \"solutions\"\nentyr = \"main.faang\"");
+        assert_eq!(
+            err,
+            Err("line 3: unknown key `entyr` in `[package]`".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_escapes() {
+        let err = Manifest::parse("[package]\nname = \"a\\\"#b\"");
+        assert_eq!(
+            err,
+            Err("line 2: escapes in strings are not supported".to_string())
+        );
+    }
+}
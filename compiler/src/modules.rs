@@ -0,0 +1,180 @@
+//! Loading the source files imported by a program.
+//!
+//! Every file is added to `Files` with its own `FileId`, so diagnostics point into the file
+//! they are about.
+use codespan::{FileId, Files, Span};
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::ast::{self, Item};
+use crate::error_codes;
+
+/// A parsed source file.
+#[derive(Debug)]
+pub struct Module {
+    /// The stem of the file name, such as `utils` for `utils.faang`. It is empty for the
+    /// file being compiled.
+    pub name: String,
+    pub file_id: FileId,
+    pub program: ast::Program,
+}
+
+/// Parses the file being compiled, which must already be in `files`, and every file it
+/// imports, directly or through other imports. Imports are relative to the file containing
+/// them. The file being compiled is the first module.
+pub fn load(
+    files: &mut Files,
+    file_id: FileId,
+    path: &Path,
+) -> Result<Vec<Module>, Vec<Diagnostic>> {
+    load_package(files, file_id, path, &[])
+}
+
+/// Like `load`, but also loads the other source files of a package, which must already be in
+/// `files`, as if the file being compiled imported each of them.
+pub fn load_package(
+    files: &mut Files,
+    file_id: FileId,
+    path: &Path,
+    sources: &[(FileId, PathBuf)],
+) -> Result<Vec<Module>, Vec<Diagnostic>> {
+    load_package_with(files, file_id, path, sources, |files, file_id, _| {
+        crate::parse(files.source(file_id), file_id)
+    })
+}
+
+/// Like `load_package`, but gets the syntax tree of each file from `parse`, which is given
+/// the name of its module too, so that the trees of unchanged files can come from a cache.
+pub fn load_package_with<P>(
+    files: &mut Files,
+    file_id: FileId,
+    path: &Path,
+    sources: &[(FileId, PathBuf)],
+    mut parse: P,
+) -> Result<Vec<Module>, Vec<Diagnostic>>
+where
+    P: FnMut(&Files, FileId, &str) -> Result<ast::Program, Vec<Diagnostic>>,
+{
+    let mut modules = vec![];
+    let mut diagnostics = vec![];
+    let entry = canonicalize(path);
+    let mut loaded: HashMap<String, PathBuf> = HashMap::new();
+    let mut pending = vec![];
+
+    for (source_id, source_path) in sources {
+        let canonical = canonicalize(source_path);
+        if canonical == entry {
+            continue;
+        }
+        let name = module_name(source_path);
+        if let Some(loaded_path) = loaded.get(&name) {
+            diagnostics.push(
+                Diagnostic::new_error(
+                    format!(
+                        "module `{}` is defined by both `{}` and `{}`",
+                        name,
+                        loaded_path.display(),
+                        source_path.display()
+                    ),
+                    Label::new(
+                        *source_id,
+                        Span::initial(),
+                        "a different file with the same name",
+                    ),
+                )
+                .with_code(error_codes::DUPLICATE_MODULE),
+            );
+            continue;
+        }
+        loaded.insert(name.clone(), canonical);
+        pending.push((name, *source_id, source_path.clone()));
+    }
+    // The file being compiled is parsed first, so that it is the first module.
+    pending.push((String::new(), file_id, path.to_path_buf()));
+
+    while let Some((name, file_id, path)) = pending.pop() {
+        let program = match parse(files, file_id, &name) {
+            Ok(program) => program,
+            Err(errors) => {
+                diagnostics.extend(errors);
+                continue;
+            }
+        };
+
+        let directory = path.parent().unwrap_or_else(|| Path::new(""));
+        for item in &program.items {
+            let import = match item {
+                Item::Import(import) => import,
+                _ => continue,
+            };
+            let import_path = directory.join(&import.path);
+            let import_name = module_name(&import_path);
+            let canonical = canonicalize(&import_path);
+
+            let error = |code: &str, message: String, label: String| {
+                Diagnostic::new_error(message, Label::new(file_id, import.span, label))
+                    .with_code(code)
+            };
+            if canonical == entry {
+                diagnostics.push(error(
+                    error_codes::IMPORT_OF_ENTRY,
+                    "cannot import the file being compiled".into(),
+                    "imported here".into(),
+                ));
+                continue;
+            }
+            match loaded.get(&import_name) {
+                Some(loaded_path) if *loaded_path == canonical => continue,
+                Some(loaded_path) => {
+                    diagnostics.push(error(
+                        error_codes::DUPLICATE_MODULE,
+                        format!(
+                            "module `{}` is already imported from `{}`",
+                            import_name,
+                            loaded_path.display()
+                        ),
+                        "a different file with the same name".into(),
+                    ));
+                    continue;
+                }
+                None => {}
+            }
+
+            match std::fs::read_to_string(&import_path) {
+                Ok(source) => {
+                    let import_id = files.add(import_path.to_string_lossy(), source);
+                    loaded.insert(import_name.clone(), canonical);
+                    pending.push((import_name, import_id, import_path));
+                }
+                Err(err) => diagnostics.push(error(
+                    error_codes::UNREADABLE_IMPORT,
+                    format!("cannot read `{}`: {}", import_path.display(), err),
+                    "imported here".into(),
+                )),
+            }
+        }
+
+        modules.push(Module {
+            name,
+            file_id,
+            program,
+        });
+    }
+
+    if diagnostics.is_empty() {
+        Ok(modules)
+    } else {
+        Err(diagnostics)
+    }
+}
+
+fn module_name(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn canonicalize(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
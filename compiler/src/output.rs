@@ -0,0 +1,169 @@
+//! Where the output of a program run with the JIT goes.
+//!
+//! Compiled code writes what it prints to the streams in the `stdlib::STDOUT` and
+//! `stdlib::STDERR` globals, and to the standard streams of the C library while those are
+//! null, which they are in a compiled executable. A host installs a `Sink` into a module
+//! before creating its execution engine to send the output elsewhere, such as into a buffer
+//! it reads afterwards.
+use inkwell::{context::Context, module::Linkage, module::Module, AddressSpace};
+use std::fs::File;
+use std::mem::ManuallyDrop;
+use std::os::raw::{c_char, c_void};
+use std::os::unix::{fs::FileExt, io::FromRawFd};
+
+use crate::compiler::stdlib;
+
+extern "C" {
+    static stdout: *mut c_void;
+    static stderr: *mut c_void;
+    fn open_memstream(buffer: *mut *mut c_char, size: *mut usize) -> *mut c_void;
+    fn tmpfile() -> *mut c_void;
+    fn fileno(file: *mut c_void) -> i32;
+    fn fflush(file: *mut c_void) -> i32;
+    fn fclose(file: *mut c_void) -> i32;
+    fn free(pointer: *mut c_void);
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    fn global(self) -> &'static str {
+        match self {
+            Stream::Stdout => stdlib::STDOUT,
+            Stream::Stderr => stdlib::STDERR,
+        }
+    }
+}
+
+/// A C stream that output can be written to.
+pub struct Sink {
+    file: *mut c_void,
+    kind: Kind,
+}
+
+enum Kind {
+    Standard,
+    Buffer(Box<Buffer>),
+    Temporary,
+}
+
+/// The memory `open_memstream` writes into, which it updates when the stream is flushed.
+struct Buffer {
+    data: *mut c_char,
+    size: usize,
+}
+
+impl Sink {
+    /// The standard output of the host.
+    pub fn stdout() -> Sink {
+        Sink {
+            file: unsafe { stdout },
+            kind: Kind::Standard,
+        }
+    }
+
+    /// The standard error of the host.
+    pub fn stderr() -> Sink {
+        Sink {
+            file: unsafe { stderr },
+            kind: Kind::Standard,
+        }
+    }
+
+    /// An in-memory buffer, read with `contents`.
+    pub fn buffer() -> Result<Sink, String> {
+        let mut buffer = Box::new(Buffer {
+            data: std::ptr::null_mut(),
+            size: 0,
+        });
+        let file = unsafe { open_memstream(&mut buffer.data, &mut buffer.size) };
+        if file.is_null() {
+            return Err("cannot create an output buffer".into());
+        }
+
+        Ok(Sink {
+            file,
+            kind: Kind::Buffer(buffer),
+        })
+    }
+
+    /// A temporary file, read with `contents`. Unlike a buffer, it also holds what child
+    /// processes write into it.
+    pub fn temporary() -> Result<Sink, String> {
+        let file = unsafe { tmpfile() };
+        if file.is_null() {
+            return Err("cannot create a temporary output file".into());
+        }
+
+        Ok(Sink {
+            file,
+            kind: Kind::Temporary,
+        })
+    }
+
+    /// What has been written into the sink, unless it is a standard stream.
+    pub fn contents(&self) -> Option<String> {
+        unsafe { fflush(self.file) };
+        let bytes = match &self.kind {
+            Kind::Standard => return None,
+            Kind::Buffer(buffer) if buffer.data.is_null() => vec![],
+            Kind::Buffer(buffer) => unsafe {
+                std::slice::from_raw_parts(buffer.data as *const u8, buffer.size).to_vec()
+            },
+            Kind::Temporary => {
+                // Reading at an offset leaves the position the stream writes at alone.
+                let file = ManuallyDrop::new(unsafe { File::from_raw_fd(fileno(self.file)) });
+                let mut bytes = vec![];
+                let mut chunk = [0; 4096];
+                loop {
+                    match file.read_at(&mut chunk, bytes.len() as u64) {
+                        Ok(0) | Err(_) => break,
+                        Ok(read) => bytes.extend_from_slice(&chunk[..read]),
+                    }
+                }
+                bytes
+            }
+        };
+
+        Some(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    /// Makes the code in `module` write `stream` into the sink. This must happen before an
+    /// execution engine is created for the module, and the sink must outlive the engine.
+    ///
+    /// The address of the sink is compiled into the module, so the module can only run in
+    /// this process afterwards.
+    pub fn install(&self, context: &Context, module: &Module, stream: Stream) {
+        let global = match module.get_global(stream.global()) {
+            Some(global) => global,
+            // The module never writes to the stream.
+            None => return,
+        };
+        let file = context
+            .i64_type()
+            .const_int(self.file as u64, false)
+            .const_to_pointer(context.i8_type().ptr_type(AddressSpace::Generic));
+        // A common global must be initialized to null.
+        global.set_linkage(Linkage::Internal);
+        global.set_initializer(&file);
+    }
+}
+
+impl Drop for Sink {
+    fn drop(&mut self) {
+        match &self.kind {
+            Kind::Standard => {}
+            Kind::Buffer(buffer) => unsafe {
+                fclose(self.file);
+                free(buffer.data as *mut c_void);
+            },
+            Kind::Temporary => unsafe {
+                fclose(self.file);
+            },
+        }
+    }
+}
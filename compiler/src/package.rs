@@ -0,0 +1,698 @@
+//! Building the package described by a `faang.toml`.
+//!
+//! Every module is compiled into its own LLVM module, and the modules are linked into the
+//! final artifacts. Three things are cached for each module under `target/cache`:
+//!
+//! - Its syntax tree, keyed by a SHA-256 hash of the compiler version and its source, so that
+//!   unchanged files are not lexed and parsed again.
+//! - Its interface, which is what the code of other modules depends on: its types, its `use`
+//!   declarations, the signatures of its functions and the bodies of its generic functions.
+//!   It is stored once the module has been type-checked, and a changed module is compiled
+//!   against the stored interfaces of the other modules rather than their code.
+//! - Its bitcode, which is linked into the artifacts, and its object file for the host. They
+//!   are keyed by a hash of its source, the types of every module, since types are shared by
+//!   all of them, and the interfaces of the modules it depends on.
+//!
+//! A module depends on the modules that its `use` declarations and its calls such as
+//! `utils::helper()` resolve to, and on everything those depend on in turn. Changing the body
+//! of a function only recompiles its module, while changing a signature also recompiles the
+//! modules that depend on it.
+use codespan::{FileId, Files};
+use codespan_reporting::diagnostic::Diagnostic;
+use inkwell::{
+    builder::Builder,
+    context::Context,
+    module::Module,
+    passes::{PassManager, PassManagerBuilder},
+    targets::{CodeModel, FileType, InitializationConfig, RelocMode, Target, TargetMachine},
+    OptimizationLevel,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use crate::ast::{self, Expression, Item, LambdaBody, MatchArmBody, Statement, StatementKind};
+use crate::compiler::{
+    self,
+    external::{self, stdio},
+    stdlib, TestFunction,
+};
+use crate::lints::{self, LintLevels};
+use crate::manifest::{self, Manifest, Profile};
+use crate::modules;
+
+pub const TARGET: &str = "target";
+
+/// The extensions of the files in the cache.
+const CACHE_EXTENSIONS: [&str; 4] = ["ast", "interface", "bc", "o"];
+
+#[derive(Debug)]
+pub enum Error {
+    /// Errors in the source files, which are in the `Files` passed to `build`.
+    Diagnostics(Vec<Diagnostic>),
+    Message(String),
+}
+
+/// The source files of the modules built by `build`: the ones that were parsed rather than
+/// read from the cache, and the ones that were compiled or whose cached code was reused. The
+/// warnings about the package come along with them.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub parsed: Vec<PathBuf>,
+    pub compiled: Vec<PathBuf>,
+    pub cached: Vec<PathBuf>,
+    pub warnings: Vec<Diagnostic>,
+}
+
+/// Builds the package whose manifest is in `root`, writing the artifacts into
+/// `target/<profile>`. Fails without building anything if a lint denied by `lint_levels` is
+/// found.
+pub fn build(
+    root: &Path,
+    release: bool,
+    lint_levels: &LintLevels,
+    files: &mut Files,
+) -> Result<Report, Error> {
+    let cache_dir = root.join(TARGET).join("cache");
+    std::fs::create_dir_all(&cache_dir).map_err(|err| {
+        Error::Message(format!("cannot create `{}`: {}", cache_dir.display(), err))
+    })?;
+
+    let mut source_hashes = HashMap::new();
+    let mut parsed = HashSet::new();
+    let (manifest, modules) = load_with(root, files, |files, file_id, name| {
+        let hash = source_hash(files, file_id, name);
+        let cached = read_cache(&cache_path(&cache_dir, name, &hash, "ast"));
+        source_hashes.insert(file_id, hash);
+        match cached {
+            Some(program) => Ok(program),
+            None => {
+                parsed.insert(file_id);
+                crate::parse(files.source(file_id), file_id)
+            }
+        }
+    })?;
+    let mut report = Report::default();
+    let mut cache_paths = vec![];
+    for module in &modules {
+        let ast_path = cache_path(
+            &cache_dir,
+            &module.name,
+            &source_hashes[&module.file_id],
+            "ast",
+        );
+        if parsed.contains(&module.file_id) {
+            write_cache(&ast_path, &module.program)?;
+            report
+                .parsed
+                .push(PathBuf::from(files.name(module.file_id)));
+        }
+        cache_paths.push(ast_path);
+    }
+
+    report.warnings = lints::check(files, &modules, lint_levels);
+    if lints::has_errors(&report.warnings) {
+        return Err(Error::Diagnostics(report.warnings));
+    }
+    let profile = if release {
+        Profile::Release
+    } else {
+        manifest.profile
+    };
+
+    let interface_paths = modules
+        .iter()
+        .map(|module| {
+            let hash = &source_hashes[&module.file_id];
+            cache_path(&cache_dir, &module.name, hash, "interface")
+        })
+        .collect::<Vec<_>>();
+    let interfaces = modules
+        .iter()
+        .zip(&interface_paths)
+        .map(|(module, path)| read_cache(path).unwrap_or_else(|| interface(&module.program)))
+        .collect::<Vec<_>>();
+    let keys = cache_keys(files, &modules, &source_hashes, &interfaces);
+    let file_ids = modules
+        .iter()
+        .map(|module| (module.name.clone(), module.file_id))
+        .collect::<Vec<_>>();
+
+    let context = Context::create();
+    let linked = context.create_module(&manifest.name);
+    let mut target_machine = None;
+    for (index, module) in modules.iter().enumerate() {
+        let source_path = PathBuf::from(files.name(module.file_id));
+        let bitcode_path = cache_path(&cache_dir, &module.name, &keys[index], "bc");
+        let object_path = cache_path(&cache_dir, &module.name, &keys[index], "o");
+
+        // A cache entry that cannot be read is compiled again and overwritten.
+        let cached = if object_path.exists() {
+            Module::parse_bitcode_from_path(&bitcode_path, &context).ok()
+        } else {
+            None
+        };
+        let compiled = match cached {
+            Some(cached) => {
+                report.cached.push(source_path);
+                cached
+            }
+            None => {
+                // The other modules are only declared, which their interfaces are enough for.
+                let mut programs = interfaces.clone();
+                programs[index] = module.program.clone();
+                let mut programs = modules
+                    .iter()
+                    .map(|module| module.name.clone())
+                    .zip(programs);
+                let (_, program) = programs.next().unwrap();
+                let imports = programs.collect::<Vec<_>>();
+                let compiled = compile(
+                    &context,
+                    &module.name,
+                    &program,
+                    &imports,
+                    Some(module.name.as_str()),
+                    files,
+                    &file_ids,
+                )
+                .map_err(Error::Message)?;
+
+                if !compiled.write_bitcode_to_path(&bitcode_path) {
+                    return Err(Error::Message(format!(
+                        "cannot write `{}`",
+                        bitcode_path.display()
+                    )));
+                }
+                let target_machine = match &mut target_machine {
+                    Some(target_machine) => target_machine,
+                    None => target_machine.get_or_insert(host_target_machine()?),
+                };
+                target_machine
+                    .write_to_file(&compiled, FileType::Object, &object_path)
+                    .map_err(|err| {
+                        Error::Message(format!(
+                            "cannot write `{}`: {}",
+                            object_path.display(),
+                            err.to_string()
+                        ))
+                    })?;
+                write_cache(&interface_paths[index], &interfaces[index])?;
+                report.compiled.push(source_path);
+                compiled
+            }
+        };
+        linked
+            .link_in_module(compiled)
+            .map_err(|err| Error::Message(err.to_string()))?;
+        cache_paths.extend(vec![
+            interface_paths[index].clone(),
+            bitcode_path,
+            object_path,
+        ]);
+    }
+    remove_stale(&cache_dir, &cache_paths);
+
+    linked
+        .verify()
+        .map_err(|err| Error::Message(err.to_string()))?;
+    write_artifacts(
+        &linked,
+        &manifest.name,
+        profile,
+        &root.join(TARGET).join(profile.to_string()),
+    )
+    .map_err(Error::Message)?;
+
+    Ok(report)
+}
+
+/// Reads the manifest of the package in `root` and loads its modules, with the entry module
+/// first.
+pub fn load(root: &Path, files: &mut Files) -> Result<(Manifest, Vec<modules::Module>), Error> {
+    load_with(root, files, |files, file_id, _| {
+        crate::parse(files.source(file_id), file_id)
+    })
+}
+
+/// Loads a package like `load`, getting the syntax tree of each file from `parse` as
+/// `modules::load_package_with` does.
+fn load_with<P>(
+    root: &Path,
+    files: &mut Files,
+    parse: P,
+) -> Result<(Manifest, Vec<modules::Module>), Error>
+where
+    P: FnMut(&Files, FileId, &str) -> Result<ast::Program, Vec<Diagnostic>>,
+{
+    let (manifest, sources) = sources(root)?;
+
+    let entry_path = root.join(&manifest.source).join(&manifest.entry);
+    let file_id = add_file(files, &entry_path)?;
+    let sources = sources
+        .into_iter()
+        .filter(|path| *path != entry_path)
+        .map(|path| Ok((add_file(files, &path)?, path)))
+        .collect::<Result<Vec<_>, _>>()?;
+    let modules = modules::load_package_with(files, file_id, &entry_path, &sources, parse)
+        .map_err(Error::Diagnostics)?;
+
+    Ok((manifest, modules))
+}
+
+/// Reads the manifest of the package in `root` and finds its source files, sorted by path.
+pub fn sources(root: &Path) -> Result<(Manifest, Vec<PathBuf>), Error> {
+    let manifest = Manifest::read(&root.join(manifest::FILE_NAME)).map_err(Error::Message)?;
+
+    let source_dir = root.join(&manifest.source);
+    let mut sources = vec![];
+    discover_sources(&source_dir, &mut sources).map_err(|err| {
+        Error::Message(format!("cannot read `{}`: {}", source_dir.display(), err))
+    })?;
+    sources.sort();
+
+    Ok((manifest, sources))
+}
+
+/// Compiles a program and the modules it imports into an LLVM module, along with the parts
+/// of the runtime the compiled code calls. With `only`, only the functions of that module
+/// are compiled, as with `Compiler::compile_module`. `file_ids` holds the source files of
+/// the modules in `files` by module name, which panics use to report where they happen.
+pub fn compile(
+    context: &Context,
+    name: &str,
+    program: &ast::Program,
+    imports: &[(String, ast::Program)],
+    only: Option<&str>,
+    files: &Files,
+    file_ids: &[(String, FileId)],
+) -> Result<Module, String> {
+    let module = context.create_module(name);
+    let builder = context.create_builder();
+    add_runtime(context, &module, &builder);
+
+    let mut compiler = compiler::Compiler::new(context, &module, &builder);
+    for (module_name, file_id) in file_ids {
+        compiler.add_source(module_name, files.name(*file_id), files.source(*file_id));
+    }
+    match only {
+        Some(only) => compiler.compile_module(only, program, imports)?,
+        None => compiler.compile_modules(program, imports)?,
+    };
+    module.verify().map_err(|err| err.to_string())?;
+
+    Ok(module)
+}
+
+/// Compiles a program like `compile`, along with the `test` blocks of every module, returning
+/// the compiled tests.
+pub fn compile_tests(
+    context: &Context,
+    name: &str,
+    program: &ast::Program,
+    imports: &[(String, ast::Program)],
+    files: &Files,
+    file_ids: &[(String, FileId)],
+) -> Result<(Module, Vec<TestFunction>), String> {
+    let module = context.create_module(name);
+    let builder = context.create_builder();
+    add_runtime(context, &module, &builder);
+
+    let mut compiler = compiler::Compiler::new(context, &module, &builder);
+    for (module_name, file_id) in file_ids {
+        compiler.add_source(module_name, files.name(*file_id), files.source(*file_id));
+    }
+    let tests = compiler.compile_tests(program, imports)?;
+    module.verify().map_err(|err| err.to_string())?;
+
+    Ok((module, tests))
+}
+
+/// Adds the parts of the runtime that compiled code may call.
+pub(crate) fn add_runtime(context: &Context, module: &Module, builder: &Builder) {
+    stdio::add(
+        &[
+            stdio::Features::PRINTF,
+            stdio::Features::FPRINTF,
+            stdio::Features::FOPEN,
+            stdio::Features::FFLUSH,
+            stdio::Features::FCLOSE,
+            stdio::Features::PERROR,
+            stdio::Features::GETLINE,
+            stdio::Features::GETDELIM,
+            stdio::Features::SCANF,
+            stdio::Features::STDIN,
+            stdio::Features::STDOUT,
+            stdio::Features::STDERR,
+        ],
+        context,
+        module,
+    );
+    external::stdlib::add(
+        &[
+            external::stdlib::Features::MALLOC,
+            external::stdlib::Features::EXIT,
+        ],
+        context,
+        module,
+    );
+    external::string::add(&[external::string::Features::STRCMP], context, module);
+
+    stdlib::add(
+        &[
+            stdlib::Features::OUTPUT,
+            stdlib::Features::PRINTLN,
+            stdlib::Features::ARGS,
+            stdlib::Features::READLINE,
+            stdlib::Features::READINT,
+            stdlib::Features::READALL,
+            stdlib::Features::OPEN,
+            stdlib::Features::WRITE,
+            stdlib::Features::READTOSTRING,
+            stdlib::Features::CLOSE,
+            stdlib::Features::EXIT,
+            stdlib::Features::PANIC,
+        ],
+        context,
+        module,
+        builder,
+    );
+}
+
+/// Optimizes a module for the profile and writes it into `<name>.ll` and `<name>.bc` in
+/// `out_dir`.
+pub fn write_artifacts(
+    module: &Module,
+    name: &str,
+    profile: Profile,
+    out_dir: &Path,
+) -> Result<(), String> {
+    if profile == Profile::Release {
+        let pass_manager_builder = PassManagerBuilder::create();
+        pass_manager_builder.set_optimization_level(profile.optimization_level());
+        let pass_manager = PassManager::create_for_module();
+        pass_manager_builder.populate_module_pass_manager(&pass_manager);
+        pass_manager.run_on_module(module);
+    }
+
+    std::fs::create_dir_all(out_dir)
+        .map_err(|err| format!("cannot create `{}`: {}", out_dir.display(), err))?;
+    module
+        .print_to_file(out_dir.join(format!("{}.ll", name)))
+        .map_err(|err| err.to_string())?;
+    let bitcode_path = out_dir.join(format!("{}.bc", name));
+    if !module.write_bitcode_to_path(&bitcode_path) {
+        return Err(format!("cannot write `{}`", bitcode_path.display()));
+    }
+
+    Ok(())
+}
+
+fn add_file(files: &mut Files, path: &Path) -> Result<FileId, Error> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| Error::Message(format!("cannot read `{}`: {}", path.display(), err)))?;
+    Ok(files.add(path.to_string_lossy(), source))
+}
+
+/// Finds the `.faang` files in a directory and its subdirectories.
+fn discover_sources(dir: &Path, sources: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            discover_sources(&path, sources)?;
+        } else if path.extension() == Some(OsStr::new("faang")) {
+            sources.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates the target machine of the host, which emits the object files of the modules.
+fn host_target_machine() -> Result<TargetMachine, Error> {
+    Target::initialize_native(&InitializationConfig::default()).map_err(Error::Message)?;
+    let triple = TargetMachine::get_default_triple().to_string();
+    let target = Target::from_triple(&triple).map_err(|err| Error::Message(err.to_string()))?;
+    target
+        .create_target_machine(
+            &triple,
+            "generic",
+            "",
+            OptimizationLevel::Default,
+            RelocMode::PIC,
+            CodeModel::Default,
+        )
+        .ok_or_else(|| Error::Message(format!("cannot create a target machine for `{}`", triple)))
+}
+
+/// The hash of the source of a module, which keys the cache of its syntax tree and interface.
+fn source_hash(files: &Files, file_id: FileId, name: &str) -> String {
+    let mut hasher = Sha256::new();
+    hash_field(&mut hasher, env!("CARGO_PKG_VERSION").as_bytes());
+    hash_field(&mut hasher, name.as_bytes());
+    // Panics report the name of the file they happen in.
+    hash_field(&mut hasher, files.name(file_id).as_bytes());
+    hash_field(&mut hasher, files.source(file_id).as_bytes());
+    format!("{:x}", hasher.result())
+}
+
+/// The keys of the compiled code of the modules, which change when their source, the types of
+/// any module or the interfaces of the modules they depend on change.
+fn cache_keys(
+    files: &Files,
+    modules: &[modules::Module],
+    source_hashes: &HashMap<FileId, String>,
+    interfaces: &[ast::Program],
+) -> Vec<String> {
+    // Interfaces are printed, so that they do not change when declarations move around.
+    let printed = interfaces
+        .iter()
+        .map(|interface| {
+            let types = interface
+                .items
+                .iter()
+                .filter(|item| matches!(item, Item::Struct(_) | Item::Enum(_)))
+                .cloned()
+                .collect();
+            (
+                ast::Program { items: types }.to_string(),
+                interface.to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    let dependencies = dependencies(modules);
+
+    modules
+        .iter()
+        .enumerate()
+        .map(|(index, module)| {
+            let mut hasher = Sha256::new();
+            hash_field(&mut hasher, source_hashes[&module.file_id].as_bytes());
+            hash_field(&mut hasher, files.name(module.file_id).as_bytes());
+            for (other, (types, interface)) in printed.iter().enumerate() {
+                if other == index {
+                    continue;
+                }
+                hash_field(&mut hasher, modules[other].name.as_bytes());
+                if dependencies[index].contains(&other) {
+                    hash_field(&mut hasher, interface.as_bytes());
+                } else {
+                    hash_field(&mut hasher, types.as_bytes());
+                }
+            }
+            format!("{:x}", hasher.result())
+        })
+        .collect()
+}
+
+/// The parts of a module that the code of other modules depends on, which are all that
+/// compiling another module needs of it: its types and `use` declarations, the signatures of
+/// its functions, and its generic functions, whose instances are compiled along with the
+/// modules that use them.
+fn interface(program: &ast::Program) -> ast::Program {
+    let items = program
+        .items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Struct(_) | Item::Enum(_) | Item::Use(_) => Some(item.clone()),
+            Item::Function(function) if !function.type_parameters.is_empty() => Some(item.clone()),
+            Item::Function(function) => Some(Item::Function(ast::Function {
+                body: vec![],
+                doc: None,
+                attributes: vec![],
+                ..function.clone()
+            })),
+            _ => None,
+        })
+        .collect();
+    ast::Program { items }
+}
+
+/// The modules that the code of each module depends on, by index, in order. A module depends
+/// on the modules that its `use` declarations and its calls such as `utils::helper()` resolve
+/// to, and on everything those depend on in turn.
+fn dependencies(modules: &[modules::Module]) -> Vec<Vec<usize>> {
+    let indices = modules
+        .iter()
+        .enumerate()
+        .map(|(index, module)| (module.name.as_str(), index))
+        .collect::<HashMap<_, _>>();
+    // A call of a function of a module parses like a variant of an enum, and is a variant if
+    // an enum has that name.
+    let enums = modules
+        .iter()
+        .flat_map(|module| &module.program.items)
+        .filter_map(|item| match item {
+            Item::Enum(declaration) => Some(declaration.name.as_str()),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+    let direct = modules
+        .iter()
+        .map(|module| {
+            let mut names = HashSet::new();
+            for item in &module.program.items {
+                match item {
+                    Item::Use(declaration) => {
+                        names.insert(declaration.module.as_str());
+                    }
+                    Item::Function(function) => variant_names(&function.body, &mut names),
+                    Item::Statement(statement) => {
+                        variant_names(std::slice::from_ref(statement), &mut names)
+                    }
+                    _ => {}
+                }
+            }
+            names
+                .into_iter()
+                .filter(|name| !enums.contains(name))
+                .filter_map(|name| indices.get(name).copied())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    (0..modules.len())
+        .map(|index| {
+            let mut dependencies = vec![false; modules.len()];
+            let mut pending = vec![index];
+            while let Some(current) = pending.pop() {
+                for &other in &direct[current] {
+                    if other != index && !dependencies[other] {
+                        dependencies[other] = true;
+                        pending.push(other);
+                    }
+                }
+            }
+            (0..modules.len())
+                .filter(|&other| dependencies[other])
+                .collect()
+        })
+        .collect()
+}
+
+/// Adds the names before the `::` of the variants in some statements to `names`.
+fn variant_names<'p>(statements: &'p [Statement], names: &mut HashSet<&'p str>) {
+    for statement in statements {
+        match &statement.kind {
+            StatementKind::Let(_, _, value)
+            | StatementKind::Expression(value)
+            | StatementKind::Return(Some(value)) => expression_variant_names(value, names),
+            StatementKind::Assignment(place, value) => {
+                expression_variant_names(place, names);
+                expression_variant_names(value, names);
+            }
+            StatementKind::If(condition, then, otherwise) => {
+                expression_variant_names(condition, names);
+                variant_names(then, names);
+                variant_names(otherwise, names);
+            }
+            StatementKind::While(condition, body) => {
+                expression_variant_names(condition, names);
+                variant_names(body, names);
+            }
+            StatementKind::Return(None) | StatementKind::Error => {}
+        }
+    }
+}
+
+fn expression_variant_names<'p>(expression: &'p Expression, names: &mut HashSet<&'p str>) {
+    let operands: Vec<&Expression> = match expression {
+        Expression::Variant(name, _, args) => {
+            names.insert(name.as_str());
+            args.iter().collect()
+        }
+        Expression::Invocation(_, args) | Expression::Array(args) => args.iter().collect(),
+        Expression::Call(callee, args) => std::iter::once(&**callee).chain(args).collect(),
+        Expression::FieldAccess(object, _) | Expression::Unary(_, object) => vec![object],
+        Expression::StructLiteral(_, fields) => fields.iter().map(|(_, value)| value).collect(),
+        Expression::Match(scrutinee, arms) => {
+            for arm in arms {
+                match &arm.body {
+                    MatchArmBody::Expression(body) => expression_variant_names(body, names),
+                    MatchArmBody::Block(body) => variant_names(body, names),
+                }
+            }
+            vec![scrutinee]
+        }
+        Expression::Lambda(lambda) => match &lambda.body {
+            LambdaBody::Expression(body) => vec![body],
+            LambdaBody::Block(body) => {
+                variant_names(body, names);
+                vec![]
+            }
+        },
+        Expression::Index(lhs, rhs) | Expression::Binary(lhs, _, rhs) => vec![lhs, rhs],
+        Expression::StringLiteral(_)
+        | Expression::IntegerLiteral(_)
+        | Expression::BooleanLiteral(_)
+        | Expression::Null
+        | Expression::Variable(_)
+        | Expression::Error => vec![],
+    };
+    for operand in operands {
+        expression_variant_names(operand, names);
+    }
+}
+
+/// Adds a field to a cache key, after its length, so that where one field ends and the next
+/// starts is part of the key.
+fn hash_field(hasher: &mut Sha256, field: &[u8]) {
+    hasher.input((field.len() as u64).to_le_bytes());
+    hasher.input(field);
+}
+
+/// The path of a file in the cache, named after its module, its key and what it holds.
+fn cache_path(cache_dir: &Path, name: &str, key: &str, extension: &str) -> PathBuf {
+    let name = if name.is_empty() { "main" } else { name };
+    cache_dir.join(format!("{}-{}.{}", name, key, extension))
+}
+
+/// Reads a syntax tree from the cache, if it is there and can be read.
+fn read_cache<T: DeserializeOwned>(path: &Path) -> Option<T> {
+    let text = std::fs::read(path).ok()?;
+    serde_json::from_slice(&text).ok()
+}
+
+fn write_cache<T: Serialize>(path: &Path, value: &T) -> Result<(), Error> {
+    let text = serde_json::to_vec(value).map_err(|err| Error::Message(err.to_string()))?;
+    std::fs::write(path, text)
+        .map_err(|err| Error::Message(format!("cannot write `{}`: {}", path.display(), err)))
+}
+
+/// Removes the old versions of the cached files of the modules.
+fn remove_stale(cache_dir: &Path, cache_paths: &[PathBuf]) {
+    if let Ok(entries) = std::fs::read_dir(cache_dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let cached = matches!(
+                path.extension().and_then(OsStr::to_str),
+                Some(extension) if CACHE_EXTENSIONS.contains(&extension)
+            );
+            if cached && !cache_paths.contains(&path) {
+                let _ = std::fs::remove_file(path);
+            }
+        }
+    }
+}
@@ -1,5 +1,3 @@
 mod parser;
 
-pub use parser::{ExpressionParser, KeywordParser, StringLiteralParser, Token};
-
-use codespan::ByteIndex;
+pub use parser::{ExpressionParser, KeywordParser, ProgramParser, StringLiteralParser};
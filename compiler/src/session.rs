@@ -0,0 +1,295 @@
+//! Compiling and running programs from other Rust code.
+//!
+//! ```no_run
+//! let mut session = faang::Session::new();
+//! match session.run_str("main.faang", "println(\"Hello World!\")") {
+//!     Ok(output) => assert_eq!(output.stdout, "Hello World!\n"),
+//!     Err(faang::session::Error::Diagnostics) => session.emit_diagnostics(),
+//!     Err(faang::session::Error::Message(message)) => eprintln!("{}", message),
+//! }
+//! ```
+use codespan::Files;
+use codespan_reporting::{
+    diagnostic::{Diagnostic, Label},
+    term::{
+        self,
+        termcolor::{ColorChoice, StandardStream},
+        Config,
+    },
+};
+use inkwell::{
+    context::Context,
+    execution_engine::{ExecutionEngine, JitFunction},
+    module::Module,
+    OptimizationLevel,
+};
+use std::ffi::CString;
+use std::os::raw::{c_char, c_void};
+use std::path::Path;
+
+use crate::compiler::{external, Compiler};
+use crate::error_codes;
+use crate::modules;
+use crate::output::{Sink, Stream};
+use crate::package;
+use crate::testing::{self, Outcome};
+
+/// What a program printed and the status it exited with.
+#[derive(Debug, PartialEq)]
+pub struct Output {
+    pub stdout: String,
+    pub stderr: String,
+    /// The exit status, which is 128 plus the number of the signal if a signal killed the
+    /// program, as in a shell.
+    pub status: i32,
+}
+
+/// Why a program could not be compiled or run.
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// Errors in the program, which are in `Session::diagnostics`.
+    Diagnostics,
+    Message(String),
+}
+
+/// Compiles programs, keeping their source files so that diagnostics can be rendered.
+pub struct Session {
+    context: Context,
+    files: Files,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Default for Session {
+    fn default() -> Session {
+        Session::new()
+    }
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Session {
+            context: Context::create(),
+            files: Files::new(),
+            diagnostics: vec![],
+        }
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// The source files of every program compiled in the session.
+    pub fn files(&self) -> &Files {
+        &self.files
+    }
+
+    /// The errors of the last program that failed to compile or run.
+    pub fn diagnostics(&self) -> &[Diagnostic] {
+        &self.diagnostics
+    }
+
+    /// Writes the diagnostics to the standard error.
+    pub fn emit_diagnostics(&self) {
+        let mut writer = StandardStream::stderr(ColorChoice::Auto);
+        for diagnostic in &self.diagnostics {
+            term::emit(&mut writer, &Config::default(), &self.files, diagnostic).unwrap();
+        }
+    }
+
+    /// Compiles a program into a module of the session's context, along with the runtime.
+    /// `name` is the file name the program is reported under, and its imports are relative
+    /// to it.
+    ///
+    /// The module must not outlive the session.
+    pub fn compile_str(&mut self, name: &str, source: &str) -> Result<Module, Error> {
+        self.diagnostics.clear();
+        let file_id = self.files.add(name, source);
+        let modules = match modules::load(&mut self.files, file_id, Path::new(name)) {
+            Err(diagnostics) => {
+                self.diagnostics = diagnostics;
+                return Err(Error::Diagnostics);
+            }
+            Ok(modules) => modules,
+        };
+        let file_ids = modules
+            .iter()
+            .map(|module| (module.name.clone(), module.file_id))
+            .collect::<Vec<_>>();
+        let mut modules = modules.into_iter();
+        let program = modules.next().unwrap().program;
+        let imports = modules
+            .map(|module| (module.name, module.program))
+            .collect::<Vec<_>>();
+
+        let stem = Path::new(name).file_stem().map_or_else(
+            || name.to_string(),
+            |stem| stem.to_string_lossy().into_owned(),
+        );
+        let module = self.context.create_module(&stem);
+        let builder = self.context.create_builder();
+        package::add_runtime(&self.context, &module, &builder);
+
+        let mut compiler = Compiler::new(&self.context, &module, &builder);
+        for (module_name, file_id) in &file_ids {
+            compiler.add_source(
+                module_name,
+                self.files.name(*file_id),
+                self.files.source(*file_id),
+            );
+        }
+        if let Err(err) = compiler.compile_modules(&program, &imports) {
+            let (module_name, span) = compiler.position();
+            let file_id = file_ids
+                .iter()
+                .find(|(name, _)| name == module_name)
+                .map_or(file_id, |(_, file_id)| *file_id);
+            let label = Label::new(file_id, span, "in this statement");
+            self.diagnostics
+                .push(Diagnostic::new_error(err, label).with_code(error_codes::TYPE_ERROR));
+            return Err(Error::Diagnostics);
+        }
+        module
+            .verify()
+            .map_err(|err| Error::Message(format!("invalid code was generated: {}", err)))?;
+
+        Ok(module)
+    }
+
+    /// Compiles a program like `compile_str` and runs it with the JIT, capturing what it
+    /// prints.
+    ///
+    /// From a process with a single thread, the program runs in a forked child as by
+    /// `testing::run_isolated`, so even a crash only ends the child. Otherwise, such as in a
+    /// `#[test]` function, which libtest runs on a thread of its own, the program runs on a
+    /// new thread of this process, where `exit` and `panic` only end that thread but a crash
+    /// ends the whole process.
+    pub fn run_str(&mut self, name: &str, source: &str) -> Result<Output, Error> {
+        self.run_str_with_args(name, source, &[])
+    }
+
+    /// Runs a program like `run_str`, passing it `args` after its name, which `args()`
+    /// returns.
+    pub fn run_str_with_args(
+        &mut self,
+        name: &str,
+        source: &str,
+        args: &[&str],
+    ) -> Result<Output, Error> {
+        let module = self.compile_str(name, source)?;
+        let args = std::iter::once(name)
+            .chain(args.iter().copied())
+            .map(CString::new)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| Error::Message(err.to_string()))?;
+        let argv = args.iter().map(|arg| arg.as_ptr()).collect::<Vec<_>>();
+        let result = if testing::is_single_threaded() {
+            run_isolated(&self.context, &module, &argv)
+        } else {
+            run_in_thread(&self.context, &module, &argv)
+        };
+        result.map_err(Error::Message)
+    }
+}
+
+type MainFunction = unsafe extern "C" fn(i32, *const *const c_char) -> i32;
+
+fn run_isolated(
+    context: &Context,
+    module: &Module,
+    argv: &[*const c_char],
+) -> Result<Output, String> {
+    let stdout = Sink::temporary()?;
+    stdout.install(context, module, Stream::Stdout);
+    let stderr = Sink::temporary()?;
+    stderr.install(context, module, Stream::Stderr);
+
+    let ee = create_execution_engine(module)?;
+    let main_fcn = unsafe { get_main(&ee)? };
+    let outcome = testing::run_isolated(|| {
+        let status = unsafe { main_fcn.call(argv.len() as i32, argv.as_ptr()) };
+        std::process::exit(status);
+    })?;
+
+    Ok(Output {
+        stdout: stdout.contents().unwrap_or_default(),
+        stderr: stderr.contents().unwrap_or_default(),
+        status: match outcome {
+            Outcome::Passed => 0,
+            Outcome::Failed { status } => status,
+            Outcome::Crashed { signal } => 128 + signal,
+        },
+    })
+}
+
+/// What the thread that runs a program is started with.
+struct Start<'a> {
+    main_fcn: JitFunction<MainFunction>,
+    argv: &'a [*const c_char],
+}
+
+fn run_in_thread(
+    context: &Context,
+    module: &Module,
+    argv: &[*const c_char],
+) -> Result<Output, String> {
+    let stdout = Sink::buffer()?;
+    stdout.install(context, module, Stream::Stdout);
+    let stderr = Sink::buffer()?;
+    stderr.install(context, module, Stream::Stderr);
+
+    let ee = create_execution_engine(module)?;
+    // Exiting ends the thread the program runs on instead of the process.
+    if let Some(exit_fcn) = module.get_function(external::stdlib::EXIT) {
+        ee.add_global_mapping(&exit_fcn, exit_thread as extern "C" fn(i32) as usize);
+    }
+    let main_fcn = unsafe { get_main(&ee)? };
+    let mut start = Start { main_fcn, argv };
+
+    let mut thread = 0;
+    let mut status = std::ptr::null_mut();
+    unsafe {
+        let start = &mut start as *mut Start as *mut c_void;
+        if libc::pthread_create(&mut thread, std::ptr::null(), start_thread, start) != 0 {
+            return Err("cannot start a thread".into());
+        }
+        if libc::pthread_join(thread, &mut status) != 0 {
+            return Err("cannot wait for the thread to finish".into());
+        }
+    }
+
+    Ok(Output {
+        stdout: stdout.contents().unwrap_or_default(),
+        stderr: stderr.contents().unwrap_or_default(),
+        status: status as isize as i32,
+    })
+}
+
+/// Runs the program, returning its exit status like `exit_thread` does.
+extern "C" fn start_thread(start: *mut c_void) -> *mut c_void {
+    let start = unsafe { &*(start as *const Start) };
+    let status = unsafe {
+        start
+            .main_fcn
+            .call(start.argv.len() as i32, start.argv.as_ptr())
+    };
+    status as isize as *mut c_void
+}
+
+/// Takes the place of the C library's `exit` for a program run on a thread. The C library
+/// unwinds the frames of the program, none of which have anything to clean up.
+extern "C" fn exit_thread(status: i32) {
+    unsafe { libc::pthread_exit(status as isize as *mut c_void) }
+}
+
+fn create_execution_engine(module: &Module) -> Result<ExecutionEngine, String> {
+    // The code generator only turns tail calls into jumps when it optimizes, and deep
+    // recursion relies on that.
+    module
+        .create_jit_execution_engine(OptimizationLevel::Default)
+        .map_err(|err| err.to_string())
+}
+
+unsafe fn get_main(ee: &ExecutionEngine) -> Result<JitFunction<MainFunction>, String> {
+    ee.get_function::<MainFunction>("main")
+        .map_err(|err| format!("cannot find `main`: {:?}", err))
+}
@@ -0,0 +1,123 @@
+//! Running the `test` blocks compiled by `package::compile_tests`.
+//!
+//! Every test runs in a child process of its own, since a failing test ends the process it
+//! runs in: a panic prints its message and calls `exit`. The exit status of the child tells
+//! whether the test passed.
+//!
+//! The child is forked, which is only safe while the process has a single thread: the child
+//! gets a copy of every lock another thread holds, such as the allocator's, and nothing ever
+//! releases it. Running tests fails in a process with other threads, including the threads
+//! libtest runs each `#[test]` on, so Rust tests that run programs use `harness = false`.
+use inkwell::{execution_engine::JitFunction, module::Module, OptimizationLevel};
+use std::time::{Duration, Instant};
+
+use crate::compiler::{stdlib, TestFunction};
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    Passed,
+    /// The test panicked, or exited with a nonzero status.
+    Failed {
+        status: i32,
+    },
+    /// The test was killed by a signal, such as a segmentation fault.
+    Crashed {
+        signal: i32,
+    },
+}
+
+#[derive(Debug)]
+pub struct TestResult {
+    pub test: TestFunction,
+    pub outcome: Outcome,
+    pub duration: Duration,
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.outcome == Outcome::Passed
+    }
+}
+
+/// Runs the tests in order, calling `on_result` as each one finishes.
+pub fn run<F>(module: &Module, tests: &[TestFunction], mut on_result: F) -> Result<(), String>
+where
+    F: FnMut(TestResult),
+{
+    // Optimized like `Session::run_str`, so that tail calls do not grow the stack.
+    let ee = module
+        .create_jit_execution_engine(OptimizationLevel::Default)
+        .map_err(|err| err.to_string())?;
+
+    for test in tests {
+        let test_fcn: JitFunction<unsafe extern "C" fn()> = unsafe {
+            ee.get_function(&test.function)
+                .map_err(|err| format!("cannot find test `{}`: {:?}", test.name, err))?
+        };
+
+        let start = Instant::now();
+        let outcome = run_isolated(|| unsafe { test_fcn.call() })
+            .map_err(|err| format!("cannot run test `{}`: {}", test.name, err))?;
+
+        on_result(TestResult {
+            test: test.clone(),
+            outcome,
+            duration: start.elapsed(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Calls `f` in a child process and waits for it to finish. The child exits with status 0
+/// once `f` returns, unless `f` ends it first.
+///
+/// Fails without calling `f` unless the calling thread is the only thread of the process.
+pub fn run_isolated<F: FnOnce()>(f: F) -> Result<Outcome, String> {
+    if !is_single_threaded() {
+        return Err("cannot start a child process while other threads are running".into());
+    }
+
+    match unsafe { libc::fork() } {
+        -1 => Err("cannot start a child process".into()),
+        0 => {
+            f();
+            // Exiting through the C library flushes what the child printed.
+            std::process::exit(0);
+        }
+        pid => wait(pid),
+    }
+}
+
+/// Whether the process has a single thread, counting the threads Linux lists for it.
+pub(crate) fn is_single_threaded() -> bool {
+    match std::fs::read_dir("/proc/self/task") {
+        Ok(threads) => threads.count() == 1,
+        Err(_) => false,
+    }
+}
+
+fn wait(pid: libc::pid_t) -> Result<Outcome, String> {
+    let mut status = 0;
+    unsafe {
+        if libc::waitpid(pid, &mut status, 0) != pid {
+            return Err("cannot wait for the child process to finish".into());
+        }
+
+        Ok(if libc::WIFEXITED(status) {
+            match libc::WEXITSTATUS(status) {
+                0 => Outcome::Passed,
+                status => Outcome::Failed { status },
+            }
+        } else {
+            Outcome::Crashed {
+                signal: libc::WTERMSIG(status),
+            }
+        })
+    }
+}
+
+/// Whether an exit status is the one a panic ends the program with.
+pub fn is_panic(status: i32) -> bool {
+    status as u64 == stdlib::PANIC_STATUS
+}
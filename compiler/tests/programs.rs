@@ -0,0 +1,167 @@
+//! Compiles and runs every program in `tests/programs`, checking it against the annotations
+//! it starts with:
+//!
+//! - `// args: <args>` are the arguments the program is run with, separated by whitespace.
+//! - `// expect-stdout: <line>` is a line the program prints. A program that compiles must
+//!   print exactly these lines.
+//! - `// expect-stderr: <line>` is a line the program prints to the standard error, where the
+//!   paths of programs are relative to `tests/programs`. There must be exactly these lines.
+//! - `// expect-status: <status>` is the status the program exits with, which is 0 otherwise.
+//! - `// expect-error: <text>` is text that the diagnostics of a program that fails to compile
+//!   contain.
+//!
+//! Each program runs in an empty working directory, where it may create files. Programs run
+//! in child processes forked from the main thread, so this test has no harness.
+use codespan::Files;
+use codespan_reporting::{
+    diagnostic::Diagnostic,
+    term::{self, termcolor::NoColor, Config},
+};
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::Path;
+
+use faang::{
+    session::{self, Output},
+    Session,
+};
+
+static ARGS: &str = "// args:";
+static EXPECT_STDOUT: &str = "// expect-stdout:";
+static EXPECT_STDERR: &str = "// expect-stderr:";
+static EXPECT_STATUS: &str = "// expect-status:";
+static EXPECT_ERROR: &str = "// expect-error:";
+
+#[derive(Default)]
+struct Expectations {
+    args: Vec<String>,
+    stdout: Vec<String>,
+    stderr: Vec<String>,
+    status: i32,
+    errors: Vec<String>,
+}
+
+fn main() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("programs");
+    let mut paths = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension() == Some(OsStr::new("faang")))
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(!paths.is_empty(), "no programs in `{}`", dir.display());
+
+    let failures = paths
+        .iter()
+        .filter_map(|path| {
+            check(path)
+                .err()
+                .map(|err| format!("{}: {}", path.display(), err))
+        })
+        .collect::<Vec<_>>();
+    assert!(failures.is_empty(), "\n\n{}\n", failures.join("\n\n"));
+}
+
+fn check(path: &Path) -> Result<(), String> {
+    let source = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    let expectations = parse_expectations(&source);
+
+    match (
+        run(path, source, &expectations.args),
+        expectations.errors.is_empty(),
+    ) {
+        (Ok(output), true) => {
+            let expected = Output {
+                stdout: lines(&expectations.stdout),
+                stderr: lines(&expectations.stderr),
+                status: expectations.status,
+            };
+            let dir = format!("{}/", path.parent().unwrap().display());
+            let output = Output {
+                stderr: output.stderr.replace(&dir, ""),
+                ..output
+            };
+            if output == expected {
+                Ok(())
+            } else {
+                Err(format!(
+                    "unexpected output\n=== expected {}=== found {}",
+                    describe(&expected),
+                    describe(&output)
+                ))
+            }
+        }
+        (Ok(_), false) => Err("expected the program to fail to compile".to_string()),
+        (Err(errors), true) => Err(errors),
+        (Err(errors), false) => match expectations
+            .errors
+            .iter()
+            .find(|expected| !errors.contains(expected.as_str()))
+        {
+            None => Ok(()),
+            Some(expected) => Err(format!(
+                "expected an error containing `{}`, found\n{}",
+                expected, errors
+            )),
+        },
+    }
+}
+
+/// Collects the annotations of a program.
+fn parse_expectations(source: &str) -> Expectations {
+    let mut expectations = Expectations::default();
+    for line in source.lines() {
+        if let Some(text) = line.strip_prefix(ARGS) {
+            expectations
+                .args
+                .extend(text.split_whitespace().map(str::to_string));
+        } else if let Some(text) = line.strip_prefix(EXPECT_STDOUT) {
+            expectations.stdout.push(text.trim().to_string());
+        } else if let Some(text) = line.strip_prefix(EXPECT_STDERR) {
+            expectations.stderr.push(text.trim().to_string());
+        } else if let Some(text) = line.strip_prefix(EXPECT_STATUS) {
+            expectations.status = text.trim().parse().expect("the status is not a number");
+        } else if let Some(text) = line.strip_prefix(EXPECT_ERROR) {
+            expectations.errors.push(text.trim().to_string());
+        }
+    }
+
+    expectations
+}
+
+fn lines(lines: &[String]) -> String {
+    lines.iter().map(|line| format!("{}\n", line)).collect()
+}
+
+fn describe(output: &Output) -> String {
+    format!(
+        "status {}\n--- stdout\n{}--- stderr\n{}",
+        output.status, output.stdout, output.stderr
+    )
+}
+
+/// Compiles a program and runs it in a new temporary working directory. Fails with the
+/// rendered diagnostics if the program does not compile.
+fn run(path: &Path, source: String, args: &[String]) -> Result<Output, String> {
+    let dir = tempfile::tempdir().map_err(|err| err.to_string())?;
+    env::set_current_dir(dir.path()).map_err(|err| err.to_string())?;
+
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+    let mut session = Session::new();
+    match session.run_str_with_args(&path.to_string_lossy(), &source, &args) {
+        Err(session::Error::Diagnostics) => Err(render(session.files(), session.diagnostics())),
+        Err(session::Error::Message(message)) => Err(message),
+        Ok(output) => Ok(output),
+    }
+}
+
+fn render(files: &Files, diagnostics: &[Diagnostic]) -> String {
+    let mut writer = NoColor::new(vec![]);
+    for diagnostic in diagnostics {
+        term::emit(&mut writer, &Config::default(), files, diagnostic).unwrap();
+    }
+    String::from_utf8(writer.into_inner()).unwrap()
+}
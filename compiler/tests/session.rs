@@ -0,0 +1,191 @@
+//! Runs programs with the JIT, mostly in child processes with `Session::run_str` and
+//! `testing::run`. Forking is only safe from a process with a single thread, so this test has
+//! no harness: the tests run in order on the main thread, and the first to fail ends the run.
+//! `Session::run_str` also runs programs while other threads are running, which a test checks.
+use codespan::{Files, Span};
+use inkwell::{context::Context, OptimizationLevel};
+use pretty_assertions::assert_eq;
+use std::sync::mpsc;
+use std::thread;
+
+use faang::{
+    compiler::stdlib,
+    lexer::Lexer,
+    output::{Sink, Stream},
+    package, parser,
+    session::{Error, Output},
+    testing::{self, Outcome},
+    Session,
+};
+
+fn main() {
+    let tests: &[(&str, fn())] = &[
+        ("test_hello_world", test_hello_world),
+        ("test_output_buffer", test_output_buffer),
+        ("test_session_diagnostics", test_session_diagnostics),
+        ("test_test_blocks", test_test_blocks),
+        ("test_refuses_to_fork_threads", test_refuses_to_fork_threads),
+        ("test_run_beside_threads", test_run_beside_threads),
+    ];
+    for (name, test) in tests {
+        test();
+        println!("test {} ... ok", name);
+    }
+}
+
+fn test_hello_world() {
+    let mut session = Session::new();
+    let output = session
+        .run_str("hello_world.faang", "println(\"Hello World!\")")
+        .unwrap();
+
+    assert_eq!(
+        output,
+        Output {
+            stdout: "Hello World!\n".to_string(),
+            stderr: String::new(),
+            status: 0,
+        }
+    );
+}
+
+fn test_output_buffer() {
+    let mut session = Session::new();
+    let module = session
+        .compile_str("greeting.faang", "func greet() { println(\"Hello!\"); }")
+        .unwrap();
+
+    let stdout = Sink::buffer().unwrap();
+    stdout.install(session.context(), &module, Stream::Stdout);
+    let ee = module
+        .create_jit_execution_engine(OptimizationLevel::None)
+        .unwrap();
+    unsafe {
+        let greet_fcn = ee
+            .get_function::<unsafe extern "C" fn()>("FAANG_greet")
+            .expect("greet function should be defined");
+        greet_fcn.call();
+        greet_fcn.call();
+    }
+
+    assert_eq!(stdout.contents(), Some("Hello!\nHello!\n".to_string()));
+}
+
+fn test_session_diagnostics() {
+    let source = "println(\"ok\");\nlet count: int = \"three\";\n";
+    let mut session = Session::new();
+    assert_eq!(
+        session.run_str("count.faang", source),
+        Err(Error::Diagnostics)
+    );
+
+    let diagnostics = session.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        "mismatched types: expected `int`, found `string`"
+    );
+    assert_eq!(diagnostics[0].code, Some("E0012".to_string()));
+    assert_eq!(diagnostics[0].primary_label.span, Span::new(15, 39));
+    assert_eq!(diagnostics[0].primary_label.message, "in this statement");
+
+    let output = session
+        .run_str("panic.faang", "println(\"before\");\npanic(\"oops\")")
+        .unwrap();
+    assert_eq!(output.stdout, "before\n");
+    assert_eq!(output.stderr, "panicked at 'oops', panic.faang:2:1\n");
+    assert_eq!(output.status, stdlib::PANIC_STATUS as i32);
+}
+
+fn test_test_blocks() {
+    let program_parser = parser::ProgramParser::new();
+    let program = program_parser
+        .parse(
+            &mut vec![],
+            Lexer::new(
+                r#"
+            func double(num: int) -> int {
+                return num * 2;
+            }
+
+            test "doubles numbers" {
+                assert_eq(double(21), 42);
+                assert_eq("answer", "answer");
+            }
+
+            test "fails an assertion" {
+                assert_eq(double(2), 5);
+            }
+
+            test "exits" {
+                exit(3);
+            }
+            "#,
+            ),
+        )
+        .unwrap();
+
+    let context = Context::create();
+    let module =
+        package::compile(&context, "test", &program, &[], None, &Files::new(), &[]).unwrap();
+    assert!(!module.print_to_string().to_string().contains("FAANG_test."));
+
+    let context = Context::create();
+    let (module, tests) =
+        package::compile_tests(&context, "test", &program, &[], &Files::new(), &[]).unwrap();
+    let mut results = vec![];
+    testing::run(&module, &tests, |result| {
+        results.push((result.test.name, result.outcome))
+    })
+    .unwrap();
+
+    assert_eq!(
+        results,
+        vec![
+            ("doubles numbers".to_string(), Outcome::Passed),
+            (
+                "fails an assertion".to_string(),
+                Outcome::Failed {
+                    status: stdlib::PANIC_STATUS as i32
+                }
+            ),
+            ("exits".to_string(), Outcome::Failed { status: 3 }),
+        ]
+    );
+}
+
+fn test_refuses_to_fork_threads() {
+    let (stop, stopped) = mpsc::channel::<()>();
+    let thread = thread::spawn(move || stopped.recv());
+    assert_eq!(
+        testing::run_isolated(|| {}),
+        Err("cannot start a child process while other threads are running".to_string())
+    );
+
+    drop(stop);
+    thread.join().unwrap().unwrap_err();
+    assert!(testing::run_isolated(|| {}).is_ok());
+}
+
+fn test_run_beside_threads() {
+    let (stop, stopped) = mpsc::channel::<()>();
+    let thread = thread::spawn(move || stopped.recv());
+
+    let mut session = Session::new();
+    let output = session.run_str("main.faang", "println(\"ok\")").unwrap();
+    assert_eq!(output.stdout, "ok\n");
+
+    // Exiting only ends the thread the program runs on.
+    let output = session
+        .run_str("exit.faang", "println(\"before\");\nexit(3)")
+        .unwrap();
+    assert_eq!(output.stdout, "before\n");
+    assert_eq!(output.status, 3);
+
+    let output = session.run_str("panic.faang", "panic(\"oops\")").unwrap();
+    assert_eq!(output.stderr, "panicked at 'oops', panic.faang:1:1\n");
+    assert_eq!(output.status, stdlib::PANIC_STATUS as i32);
+
+    drop(stop);
+    thread.join().unwrap().unwrap_err();
+}
@@ -0,0 +1,112 @@
+use crate::lsp::{
+	Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, Location, NumberOrString,
+	Position, Range, Url,
+};
+use codespan::Files;
+use codespan_reporting::diagnostic::{self, Severity};
+
+/// The syntax errors of a document, as published to the client.
+pub fn diagnostics(uri: &Url, source: &str) -> Vec<Diagnostic> {
+	let mut files = Files::new();
+	let file_id = files.add(uri.as_str(), source);
+	let (_, diagnostics) = faang::parse_partial(source, file_id);
+	diagnostics
+		.iter()
+		.map(|diagnostic| convert(uri, source, diagnostic))
+		.collect()
+}
+
+fn convert(uri: &Url, source: &str, diagnostic: &diagnostic::Diagnostic) -> Diagnostic {
+	// Editors show only the message, so it also holds what the labels and notes say.
+	let mut message = diagnostic.message.clone();
+	let label = &diagnostic.primary_label.message;
+	if !label.is_empty() && *label != diagnostic.message {
+		message.push('\n');
+		message.push_str(label);
+	}
+	for note in &diagnostic.notes {
+		message.push('\n');
+		message.push_str(note);
+	}
+
+	let mut converted =
+		Diagnostic::new_simple(range(source, diagnostic.primary_label.span), message);
+	converted.severity = Some(match diagnostic.severity {
+		Severity::Bug | Severity::Error => DiagnosticSeverity::Error,
+		Severity::Warning => DiagnosticSeverity::Warning,
+		Severity::Note => DiagnosticSeverity::Information,
+		Severity::Help => DiagnosticSeverity::Hint,
+	});
+	converted.code = diagnostic.code.clone().map(NumberOrString::String);
+	converted.source = Some("faang".into());
+	if !diagnostic.secondary_labels.is_empty() {
+		converted.related_information = Some(
+			diagnostic
+				.secondary_labels
+				.iter()
+				.map(|label| DiagnosticRelatedInformation {
+					location: Location::new(uri.clone(), range(source, label.span)),
+					message: label.message.clone(),
+				})
+				.collect(),
+		);
+	}
+	converted
+}
+
+fn range(source: &str, span: codespan::Span) -> Range {
+	Range::new(
+		position(source, span.start().to_usize()),
+		position(source, span.end().to_usize()),
+	)
+}
+
+/// The position of a byte offset, whose character is counted in UTF-16 code units.
+fn position(source: &str, offset: usize) -> Position {
+	let before = source.get(..offset).unwrap_or(source);
+	let line_start = before.rfind('\n').map_or(0, |index| index + 1);
+	Position::new(
+		before.matches('\n').count() as u64,
+		before[line_start..].encode_utf16().count() as u64,
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use pretty_assertions::assert_eq;
+
+	#[test]
+	fn converts_syntax_errors() {
+		let uri = Url::parse("file:///main.faang").unwrap();
+		let diagnostics = diagnostics(&uri, "let x = 1;\nprintln(\"é🦀\";");
+
+		assert_eq!(diagnostics.len(), 1);
+		let diagnostic = &diagnostics[0];
+		assert_eq!(
+			diagnostic.range,
+			Range::new(Position::new(1, 13), Position::new(1, 14))
+		);
+		assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::Error));
+		assert_eq!(
+			diagnostic.code,
+			Some(NumberOrString::String("E0001".into()))
+		);
+		assert_eq!(
+			diagnostic.message,
+			"unrecognized token: ;\nexpected one of `)`, `,` or `]`\nhelp: close the `(` with a `)`"
+		);
+		assert_eq!(
+			diagnostic.related_information,
+			Some(vec![DiagnosticRelatedInformation {
+				location: Location::new(uri, Range::new(Position::new(1, 7), Position::new(1, 8))),
+				message: "unclosed delimiter".into(),
+			}])
+		);
+	}
+
+	#[test]
+	fn counts_characters_in_utf16() {
+		assert_eq!(position("a\ncé🦀x", 9), Position::new(1, 4));
+	}
+}
@@ -0,0 +1,35 @@
+use crate::lsp::{Position, Range, TextEdit};
+use faang::format;
+use faang::lexer::Lexer;
+use faang::parser::ProgramParser;
+
+/// Formats a whole document like `faang fmt`, unless it has syntax errors.
+pub fn formatting(source: &str) -> Option<Vec<TextEdit>> {
+	let mut errors = vec![];
+	let program = ProgramParser::new()
+		.parse(&mut errors, Lexer::new(source))
+		.ok()?;
+	if !errors.is_empty() {
+		return None;
+	}
+
+	let formatted = format::format(source, &program);
+	if formatted == source {
+		return Some(vec![]);
+	}
+	Some(vec![TextEdit {
+		range: Range {
+			start: Position::new(0, 0),
+			end: end(source),
+		},
+		new_text: formatted,
+	}])
+}
+
+/// The position after the last character of a document, in UTF-16 code units like the
+/// positions of the protocol.
+fn end(source: &str) -> Position {
+	let line = source.matches('\n').count();
+	let last_line = source.rsplit('\n').next().unwrap_or("");
+	Position::new(line as u64, last_line.encode_utf16().count() as u64)
+}
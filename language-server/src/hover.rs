@@ -0,0 +1,72 @@
+use crate::lsp::{Hover, HoverContents, MarkupContent, MarkupKind, Position};
+use faang::ast::{Function, Item};
+use faang::lexer::{Lexer, Token};
+use faang::parser::ProgramParser;
+
+/// Shows the signature and the doc comment of the function whose name is at `position`.
+pub fn hover(source: &str, position: Position) -> Option<Hover> {
+	let offset = offset(source, position)?;
+	let name = Lexer::new(source)
+		.filter_map(Result::ok)
+		.find(|(start, _, end)| *start <= offset && offset <= *end)
+		.and_then(|(_, token, _)| match token {
+			Token::Identifier(name) => Some(name),
+			_ => None,
+		})?;
+
+	let program = ProgramParser::new()
+		.parse(&mut vec![], Lexer::new(source))
+		.ok()?;
+	let function = program.items.iter().find_map(|item| match item {
+		Item::Function(function) if function.name == name => Some(function),
+		_ => None,
+	})?;
+
+	let mut value = format!("```faang\n{}\n```", signature(function));
+	if let Some(doc) = &function.doc {
+		value.push_str("\n\n");
+		value.push_str(doc);
+	}
+	Some(Hover {
+		contents: HoverContents::Markup(MarkupContent {
+			kind: MarkupKind::Markdown,
+			value,
+		}),
+		range: None,
+	})
+}
+
+fn signature(function: &Function) -> String {
+	let mut signature = format!("func {}", function.name);
+	if !function.type_parameters.is_empty() {
+		signature.push_str(&format!("<{}>", function.type_parameters.join(", ")));
+	}
+	let parameters = function
+		.parameters
+		.iter()
+		.map(|(name, ty)| format!("{}: {}", name, ty))
+		.collect::<Vec<_>>();
+	signature.push_str(&format!("({})", parameters.join(", ")));
+	if let Some(return_type) = &function.return_type {
+		signature.push_str(&format!(" -> {}", return_type));
+	}
+	signature
+}
+
+/// The byte offset of a position, whose character is counted in UTF-16 code units.
+fn offset(source: &str, position: Position) -> Option<usize> {
+	let line_start = source
+		.split('\n')
+		.take(position.line as usize)
+		.map(|line| line.len() + 1)
+		.sum::<usize>();
+	let line = source.get(line_start..)?.split('\n').next()?;
+	let mut units = 0;
+	for (index, c) in line.char_indices() {
+		if units >= position.character as usize {
+			return Some(line_start + index);
+		}
+		units += c.len_utf16();
+	}
+	Some(line_start + line.len())
+}
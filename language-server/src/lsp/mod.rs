@@ -6,6 +6,7 @@ use self::stdio::{
 	ServerBuilder,
 };
 use log::{debug, trace};
+use lsp_types::notification::{self, Notification};
 pub use lsp_types::*;
 use std::sync::{Arc, Mutex, MutexGuard};
 
@@ -13,11 +14,45 @@ pub trait LSPHandler {
 	fn initialize(&self, params: InitializeParams) -> InitializeResult;
 	fn initialized(&self, params: InitializedParams);
 	fn text_document_completion(&self, params: CompletionParams) -> CompletionResponse;
-	fn text_document_did_open(&self, params: DidOpenTextDocumentParams);
-	fn text_document_did_change(&self, params: DidChangeTextDocumentParams);
+	fn text_document_hover(&self, params: TextDocumentPositionParams) -> Option<Hover>;
+	fn text_document_formatting(&self, params: DocumentFormattingParams) -> Option<Vec<TextEdit>>;
+	fn text_document_did_open(&mut self, params: DidOpenTextDocumentParams);
+	fn text_document_did_change(&mut self, params: DidChangeTextDocumentParams);
 	fn text_document_did_save(&self, params: DidSaveTextDocumentParams);
 }
 
+/// Sends notifications to the client. They are queued while a message is handled, and are
+/// written after its response.
+#[derive(Clone, Default)]
+pub struct Client {
+	outbox: Arc<Mutex<Vec<String>>>,
+}
+
+impl Client {
+	pub fn publish_diagnostics(&self, params: PublishDiagnosticsParams) {
+		self.notify::<notification::PublishDiagnostics>(params);
+	}
+
+	fn notify<N: Notification>(&self, params: N::Params) {
+		let message = serde_json::json!({
+			"jsonrpc": "2.0",
+			"method": N::METHOD,
+			"params": params,
+		});
+		if let Ok(mut outbox) = self.outbox.lock() {
+			outbox.push(message.to_string());
+		}
+	}
+
+	/// Removes the queued notifications.
+	fn take(&self) -> Vec<String> {
+		match self.outbox.lock() {
+			Ok(mut outbox) => std::mem::replace(&mut *outbox, vec![]),
+			Err(_) => vec![],
+		}
+	}
+}
+
 macro_rules! add_method {
 	($handler:expr, $io:expr, $name:expr, $handler_call:expr) => {{
 		let handler_c = $handler.clone();
@@ -60,7 +95,7 @@ macro_rules! add_notif {
 		}};
 }
 
-pub fn start_lsp_server<H: LSPHandler>(handler: Arc<Mutex<H>>)
+pub fn start_lsp_server<H: LSPHandler>(handler: Arc<Mutex<H>>, client: Client)
 where
 	H: std::marker::Send,
 	H: 'static,
@@ -79,6 +114,18 @@ where
 		"textDocument/completion",
 		|handler: MutexGuard<H>, params| handler.text_document_completion(params)
 	);
+	add_method!(
+		handler,
+		io,
+		"textDocument/hover",
+		|handler: MutexGuard<H>, params| handler.text_document_hover(params)
+	);
+	add_method!(
+		handler,
+		io,
+		"textDocument/formatting",
+		|handler: MutexGuard<H>, params| handler.text_document_formatting(params)
+	);
 	add_notif!(
 		handler,
 		io,
@@ -89,13 +136,13 @@ where
 		handler,
 		io,
 		"textDocument/didOpen",
-		|handler: MutexGuard<H>, params| handler.text_document_did_open(params)
+		|mut handler: MutexGuard<H>, params| handler.text_document_did_open(params)
 	);
 	add_notif!(
 		handler,
 		io,
 		"textDocument/didChange",
-		|handler: MutexGuard<H>, params| handler.text_document_did_change(params)
+		|mut handler: MutexGuard<H>, params| handler.text_document_did_change(params)
 	);
 	add_notif!(
 		handler,
@@ -103,5 +150,5 @@ where
 		"textDocument/didSave",
 		|handler: MutexGuard<H>, params| handler.text_document_did_save(params)
 	);
-	ServerBuilder::new(io).build();
+	ServerBuilder::new(io, client).build();
 }
@@ -4,30 +4,35 @@ use tokio_stdin_stdout;
 pub use jsonrpc_core;
 
 use super::codec::LSPCodec;
+use super::Client;
 use bytes::Bytes;
 use jsonrpc_core::IoHandler;
 use log;
+use std::io;
 use std::sync::Arc;
-use tokio::prelude::{Future, Stream};
+use tokio::prelude::{stream, Future, Stream};
 use tokio_codec::{FramedRead, FramedWrite};
 
 pub struct ServerBuilder {
 	handler: Arc<IoHandler>,
+	client: Client,
 }
 
 impl ServerBuilder {
-	pub fn new<T>(handler: T) -> Self
+	pub fn new<T>(handler: T, client: Client) -> Self
 	where
 		T: Into<IoHandler>,
 	{
 		ServerBuilder {
 			handler: Arc::new(handler.into()),
+			client,
 		}
 	}
 
 	/// Will block until EOF is read or until an error occurs.
 	/// The server reads from STDIN line-by-line, one request is taken
-	/// per line and each response is written to STDOUT on a new line.
+	/// per line and each response is written to STDOUT on a new line,
+	/// followed by the notifications sent while handling the request.
 	pub fn build(&self) {
 		let mut core = tokio_core::reactor::Core::new().unwrap();
 
@@ -37,12 +42,18 @@ impl ServerBuilder {
 		let framed_stdin = FramedRead::new(stdin, LSPCodec::new());
 		let framed_stdout = FramedWrite::new(stdout, LSPCodec::new());
 		let handler = self.handler.clone();
+		let client = self.client.clone();
 		let future = framed_stdin
 			.and_then(move |line| {
 				log::trace!("Request: {}", std::str::from_utf8(&line).unwrap());
 				return process(&handler, std::str::from_utf8(&line).unwrap().into())
 					.map_err(|_| unreachable!());
 			})
+			.map(move |response| {
+				let notifications = client.take().into_iter().map(Bytes::from);
+				stream::iter_ok::<_, io::Error>(std::iter::once(response).chain(notifications))
+			})
+			.flatten()
 			.forward(framed_stdout)
 			.map(|_| ())
 			.map_err(|e| panic!("{:?}", e));
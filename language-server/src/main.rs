@@ -1,3 +1,6 @@
+mod diagnostics;
+mod formatting;
+mod hover;
 mod lsp;
 
 #[macro_use]
@@ -10,6 +13,16 @@ use std::sync::{Arc, Mutex};
 #[derive(Clone)]
 struct Handler {
 	files: HashMap<String, String>,
+	client: Client,
+}
+
+impl Handler {
+	fn update(&mut self, uri: Url, text: String) {
+		let diagnostics = diagnostics::diagnostics(&uri, &text);
+		self.files.insert(uri.to_string(), text);
+		self.client
+			.publish_diagnostics(PublishDiagnosticsParams { uri, diagnostics });
+	}
 }
 
 impl LSPHandler for Handler {
@@ -19,7 +32,7 @@ impl LSPHandler for Handler {
 				text_document_sync: Some(TextDocumentSyncCapability::Kind(
 					TextDocumentSyncKind::Full,
 				)),
-				hover_provider: None,
+				hover_provider: Some(true),
 				completion_provider: Some(CompletionOptions {
 					resolve_provider: Some(true),
 					trigger_characters: None,
@@ -34,7 +47,7 @@ impl LSPHandler for Handler {
 				workspace_symbol_provider: None,
 				code_action_provider: None,
 				code_lens_provider: None,
-				document_formatting_provider: None,
+				document_formatting_provider: Some(true),
 				document_range_formatting_provider: None,
 				document_on_type_formatting_provider: None,
 				rename_provider: None,
@@ -69,15 +82,37 @@ impl LSPHandler for Handler {
 		}])
 	}
 
+	fn text_document_hover(&self, params: TextDocumentPositionParams) -> Option<Hover> {
+		let source = self.files.get(&params.text_document.uri.to_string())?;
+		hover::hover(source, params.position)
+	}
+
+	fn text_document_formatting(&self, params: DocumentFormattingParams) -> Option<Vec<TextEdit>> {
+		let source = self.files.get(&params.text_document.uri.to_string())?;
+		formatting::formatting(source)
+	}
+
 	fn initialized(&self, _params: InitializedParams) {}
-	fn text_document_did_open(&self, _params: DidOpenTextDocumentParams) {}
-	fn text_document_did_change(&self, _params: DidChangeTextDocumentParams) {}
+
+	fn text_document_did_open(&mut self, params: DidOpenTextDocumentParams) {
+		self.update(params.text_document.uri, params.text_document.text);
+	}
+
+	fn text_document_did_change(&mut self, params: DidChangeTextDocumentParams) {
+		// The whole text is sent on every change.
+		if let Some(change) = params.content_changes.into_iter().last() {
+			self.update(params.text_document.uri, change.text);
+		}
+	}
+
 	fn text_document_did_save(&self, _params: DidSaveTextDocumentParams) {}
 }
 
 fn main() {
+	let client = Client::default();
 	let handler = Handler {
 		files: HashMap::default(),
+		client: client.clone(),
 	};
 	stderrlog::new()
 		.module(module_path!())
@@ -85,5 +120,5 @@ fn main() {
 		.timestamp(stderrlog::Timestamp::Second)
 		.init()
 		.unwrap();
-	start_lsp_server(Arc::new(Mutex::new(handler)));
+	start_lsp_server(Arc::new(Mutex::new(handler)), client);
 }